@@ -4,73 +4,174 @@ use proc_macro_crate::{crate_name, FoundCrate};
 use quote::quote;
 use std::iter::once;
 use syn::{
-    parse, parse::Parser, punctuated::Punctuated, spanned::Spanned, token::Comma, Expr, ExprLit,
-    ExprPath, FnArg, ForeignItem, ForeignItemFn, Ident, ItemForeignMod, Lit, LitInt, Meta,
-    NestedMeta, Result, ReturnType, Signature,
+    parse,
+    parse::{Parse, ParseStream},
+    Abi, Attribute, BinOp, Expr, ExprBinary, ExprGroup, ExprLit, ExprParen, ExprPath, FnArg,
+    ForeignItem, ForeignItemFn, ForeignItemStatic, GenericArgument, Ident, Item, ItemForeignMod,
+    ItemMod, Lit, LitBool, LitInt, Meta, MetaNameValue, NestedMeta, Pat, PatIdent, PatType, Path,
+    PathArguments, Result, ReturnType, Signature, Token, Type, TypeReference, Visibility,
 };
 
 pub fn parse_windows_dll(
     metadata: TokenStream,
     input: TokenStream,
 ) -> Result<proc_macro2::TokenStream> {
-    let (dll_name, dll_name_span, load_library_ex_flags) = parse_attribute_args(metadata)?;
-    let functions = parse_extern_block(
-        &dll_name,
-        dll_name_span,
-        load_library_ex_flags.as_ref(),
-        input,
-    )?;
+    let args = parse_attribute_args(metadata)?;
+    let functions = parse_dll_item(&args, input)?;
     Ok(functions)
 }
 
-/// Extract the arguments from the #[dll] macro.
-pub fn parse_attribute_args(metadata: TokenStream) -> Result<(String, Span, Option<Expr>)> {
-    // Our arguments take the form of `LitStr[, Expr]?`, where the first argument
-    // is the dll name, and the second arg is a flag to pass to LoadLibraryExW.
-    // The easiest way to represent this is with a Punctuated list of expr,
-    // which we will limit to two elements manually.
-    let parser = Punctuated::<Expr, Comma>::parse_terminated;
-    let args: Punctuated<Expr, Comma> = parser.parse(metadata)?;
-
-    // Extract dll name
+/// Arguments passed to the #[dll] macro.
+pub struct DllArgs {
+    /// The dll name and its span, `None` if the block uses an existing dll type
+    pub name: Option<(String, Span)>,
+    pub flags: Option<Expr>,
+    pub type_name: Option<Path>,
+    pub vis: Option<Visibility>,
+    /// Whether to import the dll with `#[link(kind = "raw-dylib")]` instead of loading it
+    pub raw_dylib: Option<LitBool>,
+}
+
+const DLL_ARG_KEYS: &str = "`name`, `flags`, `type_name`, `vis`, `raw_dylib`";
+
+impl Parse for DllArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        // Our arguments take the form of `LitStr[, Expr]?`, where the first argument
+        // is the dll name, and the second arg is a flag to pass to LoadLibraryExW.
+        // They can also be passed by name, e.g. `name = "user32", flags = ...`,
+        // as long as the named arguments come after the positional ones.
+        let mut name = None;
+        let mut flags = None;
+        let mut type_name: Option<Path> = None;
+        let mut vis: Option<Visibility> = None;
+        let mut raw_dylib: Option<LitBool> = None;
+        let mut positional = 0;
+        let mut named = false;
+
+        while !input.is_empty() {
+            if input.peek(Ident) && input.peek2(Token![=]) {
+                let key: Ident = input.parse()?;
+                input.parse::<Token![=]>()?;
+                named = true;
+
+                match key.to_string().as_str() {
+                    "name" => set_arg(&mut name, &key, dll_name(input.parse()?)?)?,
+                    "flags" => set_arg(&mut flags, &key, input.parse()?)?,
+                    "type_name" => set_arg(&mut type_name, &key, input.parse()?)?,
+                    "vis" => set_arg(&mut vis, &key, input.parse()?)?,
+                    "raw_dylib" => set_arg(&mut raw_dylib, &key, input.parse()?)?,
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            &key,
+                            format!(
+                                "Unknown argument `{}` passed to dll macro, expected one of {}.",
+                                key, DLL_ARG_KEYS
+                            ),
+                        ))
+                    }
+                }
+            } else {
+                let expr: Expr = input.parse()?;
+                if named {
+                    return Err(syn::Error::new_spanned(
+                        expr,
+                        "Positional arguments must come before named arguments.",
+                    ));
+                }
+
+                match positional {
+                    0 => name = Some(dll_name(expr)?),
+                    1 => flags = Some(expr),
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            expr,
+                            "Too many arguments passed to dll macro.",
+                        ))
+                    }
+                }
+                positional += 1;
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        if name.is_none() {
+            // Without a name, the functions are added to an existing dll type,
+            // which has already been given all the other arguments
+            if type_name.is_none() {
+                return Err(syn::Error::new(
+                    Span::call_site(),
+                    "Missing DLL name in dll macro.",
+                ));
+            }
+            if let Some(flags) = &flags {
+                return Err(syn::Error::new_spanned(
+                    flags,
+                    "`flags` can only be passed together with a DLL name.",
+                ));
+            }
+            if let Some(vis) = &vis {
+                return Err(syn::Error::new_spanned(
+                    vis,
+                    "`vis` can only be passed together with a DLL name.",
+                ));
+            }
+            if let Some(raw_dylib) = &raw_dylib {
+                return Err(syn::Error::new_spanned(
+                    raw_dylib,
+                    "`raw_dylib` can only be passed together with a DLL name.",
+                ));
+            }
+        }
+
+        Ok(Self {
+            name,
+            flags,
+            type_name,
+            vis,
+            raw_dylib,
+        })
+    }
+}
+
+fn set_arg<T>(arg: &mut Option<T>, key: &Ident, value: T) -> Result<()> {
+    if arg.is_some() {
+        return Err(syn::Error::new_spanned(
+            key,
+            format!("Argument `{}` passed to dll macro more than once.", key),
+        ));
+    }
+    *arg = Some(value);
+    Ok(())
+}
+
+fn dll_name(expr: Expr) -> Result<(String, Span)> {
     let error_text = "DLL name must be a string or identifier";
-    let mut args_it = args.clone().into_iter();
-    let (dll, dll_span) = match args_it.next().unwrap() {
+    match expr {
         Expr::Lit(ExprLit {
             lit: Lit::Str(s), ..
-        }) => (s.value(), s.span()),
+        }) => Ok((s.value(), s.span())),
         Expr::Path(ExprPath { path, .. }) => match path.get_ident() {
-            Some(ident) => (ident.to_string(), ident.span()),
-            None => return Err(syn::Error::new(path.span(), error_text)),
+            Some(ident) => Ok((ident.to_string(), ident.span())),
+            None => Err(syn::Error::new_spanned(path, error_text)),
         },
-        expr => return Err(syn::Error::new(expr.span(), error_text)),
-    };
-
-    // Extract the library args (if they exist).
-    let load_library_args = args_it.next();
-
-    // Ensure there aren't any extra flags afterwards.
-    if args_it.next().is_some() {
-        return Err(syn::Error::new(
-            args.span(),
-            "Too many arguments passed to dll macro.",
-        ));
+        expr => Err(syn::Error::new_spanned(expr, error_text)),
     }
-
-    Ok((dll, dll_span, load_library_args))
 }
 
-pub fn parse_extern_block(
-    dll_name: &str,
-    dll_name_span: Span,
-    load_library_ex_flags: Option<&Expr>,
-    input: TokenStream,
-) -> Result<proc_macro2::TokenStream> {
-    let wide_dll_name = dll_name.encode_utf16().chain(once(0));
-    let wide_dll_name = quote! { (&[#(#wide_dll_name),*]).as_ptr() };
+/// Extract the arguments from the #[dll] macro.
+pub fn parse_attribute_args(metadata: TokenStream) -> Result<DllArgs> {
+    parse(metadata)
+}
 
-    let found_crate =
-        crate_name("windows-dll").expect("windows-dll crate not found");
+/// Generate the code for an extern block, or a module containing extern blocks
+/// which share a single dll type.
+pub fn parse_dll_item(args: &DllArgs, input: TokenStream) -> Result<proc_macro2::TokenStream> {
+    let found_crate = crate_name("windows-dll")
+        .map_err(|err| syn::Error::new(Span::call_site(), err.to_string()))?;
     let crate_name = match found_crate {
         FoundCrate::Itself => {
             Ident::new("windows_dll", Span::call_site())
@@ -80,16 +181,1142 @@ pub fn parse_extern_block(
         },
     };
 
-    let dll_type_name = if dll_name.ends_with(".dll") {
-        let mut pieces = dll_name.rsplitn(3, |c| c == '.' || c == '\\' || c == '/');
-        let _ext = pieces.next().unwrap();
-        pieces.next().unwrap()
+    let raw_dylib = raw_dylib_link(args)?;
+
+    match parse(input)? {
+        Item::ForeignMod(block) => {
+            let len = block.items.len();
+            let (dll_type, dll_impl) = dll_type(&crate_name, args, len, raw_dylib.is_some())?;
+            let functions =
+                parse_extern_block(&crate_name, &dll_type, 0, block, raw_dylib.as_ref())?;
+
+            Ok(quote! {
+                #dll_impl
+                #functions
+            })
+        }
+        Item::Mod(ItemMod {
+            attrs,
+            vis,
+            mod_token,
+            ident,
+            content: Some((_, items)),
+            ..
+        }) => {
+            let len = items
+                .iter()
+                .map(|item| match item {
+                    Item::ForeignMod(block) => block.items.len(),
+                    _ => 0,
+                })
+                .sum();
+            let (dll_type, dll_impl) = dll_type(&crate_name, args, len, raw_dylib.is_some())?;
+
+            let mut index = 0;
+            let items = items
+                .into_iter()
+                .map(|item| match item {
+                    Item::ForeignMod(block) => {
+                        let start = index;
+                        index += block.items.len();
+                        parse_extern_block(&crate_name, &dll_type, start, block, raw_dylib.as_ref())
+                    }
+                    item => Ok(quote! { #item }),
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            Ok(quote! {
+                #(#attrs)*
+                #vis #mod_token #ident {
+                    #dll_impl
+                    #(#items)*
+                }
+            })
+        }
+        Item::Mod(item) => Err(syn::Error::new_spanned(
+            item,
+            "The dll macro can only be used on modules with a body.",
+        )),
+        item => Err(syn::Error::new_spanned(
+            item,
+            "The dll macro can only be used on extern blocks and modules.",
+        )),
+    }
+}
+
+/// The path of the dll type, and its definition if a DLL name was passed
+fn dll_type(
+    crate_name: &Ident,
+    args: &DllArgs,
+    len: usize,
+    raw_dylib: bool,
+) -> Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    match &args.name {
+        Some((dll_name, dll_name_span)) => {
+            let dll_type_ident = match &args.type_name {
+                Some(path) => match path.get_ident() {
+                    Some(ident) => ident.clone(),
+                    None => {
+                        return Err(syn::Error::new_spanned(
+                            path,
+                            "`type_name` must be an identifier when a DLL name is passed.",
+                        ))
+                    }
+                },
+                None => dll_type_ident(dll_name, *dll_name_span)?,
+            };
+            let dll_impl = dll_impl(
+                crate_name,
+                &dll_type_ident,
+                args.vis.as_ref(),
+                dll_name,
+                args.flags.as_ref(),
+                len,
+                raw_dylib,
+            );
+
+            Ok((quote! { #dll_type_ident }, dll_impl))
+        }
+        None => {
+            let path = &args.type_name;
+            Ok((quote! { #path }, quote! {}))
+        }
+    }
+}
+
+/// The `#[link]` attributes importing the dll when the block is compiled in raw-dylib mode,
+/// chosen with the `raw_dylib` argument or else the `raw-dylib` feature.
+/// Blocks adding functions to an existing dll type always load them at runtime.
+fn raw_dylib_link(args: &DllArgs) -> Result<Option<proc_macro2::TokenStream>> {
+    let (dll_name, dll_name_span) = match &args.name {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let enabled = match &args.raw_dylib {
+        Some(raw_dylib) => raw_dylib.value,
+        None => cfg!(feature = "raw-dylib"),
+    };
+    if !enabled {
+        return Ok(None);
+    }
+    if dll_name.contains(&['\\', '/'][..]) {
+        return Err(syn::Error::new(
+            *dll_name_span,
+            "A DLL with a path can't be imported with `raw_dylib`, pass `raw_dylib = false` to load it at runtime.",
+        ));
+    }
+
+    // The linker appends `.dll` to the name, unless it is used verbatim
+    let link = match dll_name.len().checked_sub(".dll".len()) {
+        Some(len) if matches!(dll_name.get(len..), Some(ext) if ext.eq_ignore_ascii_case(".dll")) =>
+        {
+            let stem = &dll_name[..len];
+            quote! { name = #stem }
+        }
+        _ if dll_name.contains('.') => quote! { name = #dll_name, modifiers = "+verbatim" },
+        _ => quote! { name = #dll_name },
+    };
+
+    // Dlls export their functions undecorated, which has to be asked for on x86
+    Ok(Some(quote! {
+        #[cfg_attr(target_arch = "x86", link(#link, kind = "raw-dylib", import_name_type = "undecorated"))]
+        #[cfg_attr(not(target_arch = "x86"), link(#link, kind = "raw-dylib"))]
+    }))
+}
+
+/// Generate the items of an extern block, numbering them from `start_index`
+fn parse_extern_block(
+    crate_name: &Ident,
+    dll_type: &proc_macro2::TokenStream,
+    start_index: usize,
+    block: ItemForeignMod,
+    raw_dylib: Option<&proc_macro2::TokenStream>,
+) -> Result<proc_macro2::TokenStream> {
+    let ItemForeignMod { abi, items, .. } = block;
+
+    let idents: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item {
+            ForeignItem::Fn(function) => Some(function.sig.ident.clone()),
+            ForeignItem::Static(item) => Some(item.ident.clone()),
+            _ => None,
+        })
+        .collect();
+
+    let functions = items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let index = start_index + index;
+            match item {
+                ForeignItem::Fn(function) => {
+                    parse_function(crate_name, dll_type, &abi, index, function, raw_dylib)
+                }
+                ForeignItem::Static(item) => {
+                    parse_static(crate_name, dll_type, &abi, index, item, raw_dylib)
+                }
+                item => Err(syn::Error::new_spanned(
+                    item,
+                    "Only functions and statics are supported in a dll extern block.",
+                )),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let registration = registration(crate_name, dll_type, &idents);
+    // Exports imported with raw-dylib are listed in the import table instead
+    let audit_record = match raw_dylib {
+        Some(_) => quote! {},
+        None => audit_record(crate_name, dll_type, &idents),
+    };
+
+    Ok(quote! {
+        #(#functions)*
+        #registration
+        #audit_record
+    })
+}
+
+/// With the `audit` feature, write the dll and the items of an extern block to the
+/// `.wdll` section of the binary
+fn audit_record(
+    crate_name: &Ident,
+    dll_type: &proc_macro2::TokenStream,
+    idents: &[Ident],
+) -> proc_macro2::TokenStream {
+    if !cfg!(feature = "audit") {
+        return quote! {};
+    }
+
+    let internal = quote! { #crate_name::macro_internal };
+    let record = Ident::new("AUDIT_RECORD", Span::mixed_site());
+    let procs = Ident::new("PROCS", Span::mixed_site());
+    let len = Ident::new("LEN", Span::mixed_site());
+
+    quote! {
+        const _: () = {
+            const #procs: &[#crate_name::Proc] = &[#(<#idents as #crate_name::WindowsDllProc>::PROC),*];
+            const #len: usize = #internal::audit_record_len(<#dll_type as #crate_name::WindowsDll>::LIB, #procs);
+
+            #[used]
+            #[link_section = ".wdll"]
+            static #record: [u8; #len] = #internal::audit_record::<#len>(<#dll_type as #crate_name::WindowsDll>::LIB, #procs);
+        };
+    }
+}
+
+/// With the `registry` feature, add the dll and the items of an extern block to the
+/// list returned by `registered_dlls()`
+fn registration(
+    crate_name: &Ident,
+    dll_type: &proc_macro2::TokenStream,
+    idents: &[Ident],
+) -> proc_macro2::TokenStream {
+    if !cfg!(feature = "registry") {
+        return quote! {};
+    }
+
+    let internal = quote! { #crate_name::macro_internal };
+    let registration = Ident::new("REGISTRATION", Span::mixed_site());
+
+    quote! {
+        const _: () = {
+            #[#internal::linkme::distributed_slice(#internal::DLL_REGISTRATIONS)]
+            #[linkme(crate = #internal::linkme)]
+            static #registration: #internal::DllRegistration = #internal::DllRegistration {
+                lib: <#dll_type as #crate_name::WindowsDll>::LIB,
+                flags: #crate_name::LoadFlags::from_native(<#dll_type as #crate_name::WindowsDll>::FLAGS),
+                procs: &[#(<#idents as #crate_name::WindowsDllProc>::PROC),*],
+            };
+        };
+    }
+}
+
+fn parse_function(
+    crate_name: &Ident,
+    dll_type: &proc_macro2::TokenStream,
+    abi: &Abi,
+    index: usize,
+    function: ForeignItemFn,
+    raw_dylib: Option<&proc_macro2::TokenStream>,
+) -> Result<proc_macro2::TokenStream> {
+    let ForeignItemFn {
+        attrs, vis, sig, ..
+    } = function;
+
+    let ItemAttrs {
+        link: link_attr,
+        fallible: fallible_attr,
+        safe: safe_attr,
+        bool_return: bool_attr,
+        min_os,
+        attrs,
+    } = parse_item_attrs(attrs)?;
+
+    let Signature {
+        ident,
+        generics,
+        inputs,
+        variadic,
+        output,
+        ..
+    } = &sig;
+
+    if !generics.params.is_empty() || generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(
+            generics,
+            "Generic functions can't be loaded from a dll.",
+        ));
+    }
+    if let Some(variadic) = variadic {
+        let c_abi = match &abi.name {
+            Some(name) => name.value() == "C" || name.value() == "cdecl",
+            None => true,
+        };
+        if !c_abi {
+            return Err(syn::Error::new_spanned(
+                variadic,
+                "Variadic functions are only supported in `extern \"C\"` blocks.",
+            ));
+        }
+        if let Some(safe_attr) = &safe_attr {
+            return Err(syn::Error::new_spanned(
+                &safe_attr.attr,
+                "Variadic functions can't be safe.",
+            ));
+        }
+    }
+    if let Some(SafeAttr { strict: true, .. }) = safe_attr {
+        let types = inputs
+            .iter()
+            .filter_map(|input| match input {
+                FnArg::Typed(PatType { ty, .. }) => Some(&**ty),
+                FnArg::Receiver(_) => None,
+            })
+            .chain(match output {
+                ReturnType::Type(_, ty) => Some(&**ty),
+                ReturnType::Default => None,
+            });
+        for ty in types {
+            if let Some(ptr) = find_raw_pointer(ty) {
+                let message = match ptr {
+                    Type::Path(path) => format!(
+                        "Functions marked `#[safe(strict)]` can't take or return raw pointers. \
+                         `{}` is rejected as a Win32 alias of a pointer or handle: aliases \
+                         are recognized by name on a best-effort basis, so any type with \
+                         one of these names is rejected and other aliases are accepted.",
+                        path.path.segments.last().unwrap().ident
+                    ),
+                    _ => "Functions marked `#[safe(strict)]` can't take or return raw pointers."
+                        .to_string(),
+                };
+                return Err(syn::Error::new_spanned(ptr, message));
+            }
+        }
+    }
+
+    // Arguments that aren't plain identifiers get a hygienic name, and the locals of
+    // the generated function are hygienic as well, so that any name or pattern works
+    let mut arguments = Vec::new();
+    let mut argument_names = Vec::new();
+    let mut raw_arguments = Vec::new();
+    let mut argument_values = Vec::new();
+    let mut argument_types = Vec::new();
+    let mut conversions = Vec::new();
+    let mut marshalled = bool_attr.is_some();
+    for (i, input) in inputs.iter().enumerate() {
+        match input {
+            FnArg::Typed(PatType { attrs, pat, ty, .. }) => {
+                let name = match &**pat {
+                    Pat::Ident(PatIdent {
+                        by_ref: None,
+                        subpat: None,
+                        ident,
+                        ..
+                    }) => ident.clone(),
+                    _ => Ident::new(&format!("arg{}", i), Span::mixed_site()),
+                };
+                let (marshal, attrs) = parse_marshal_attr(attrs)?;
+                marshalled |= marshal.is_some();
+                let (value, sig_type) = match marshal {
+                    None => (quote! { #name }, quote! { #ty }),
+                    Some(Marshal::Wide) => {
+                        let buffer = Ident::new(&format!("arg{}_wide", i), Span::mixed_site());
+                        conversions.push(quote! {
+                            let #buffer = #crate_name::macro_internal::to_wide(&#name);
+                        });
+                        (quote! { #buffer.as_ptr() }, quote! { *const u16 })
+                    }
+                    Some(Marshal::Ansi) => {
+                        let buffer = Ident::new(&format!("arg{}_ansi", i), Span::mixed_site());
+                        conversions.push(quote! {
+                            let #buffer = #crate_name::macro_internal::to_ansi(&#name);
+                        });
+                        (quote! { #buffer.as_ptr() }, quote! { *const u8 })
+                    }
+                    Some(Marshal::Bool) => (quote! { i32::from(#name) }, quote! { i32 }),
+                    Some(Marshal::Out) => match &**ty {
+                        Type::Reference(TypeReference {
+                            mutability: Some(_),
+                            elem,
+                            ..
+                        }) => (quote! { #name as *mut #elem }, quote! { *mut #elem }),
+                        ty => {
+                            return Err(syn::Error::new_spanned(
+                                ty,
+                                "`#[out]` arguments must be mutable references.",
+                            ))
+                        }
+                    },
+                };
+                arguments.push(quote! { #(#attrs)* #name: #ty });
+                raw_arguments.push(quote! { #(#attrs)* #name: #sig_type });
+                argument_names.push(name);
+                argument_values.push(value);
+                argument_types.push(sig_type);
+            }
+            FnArg::Receiver(receiver) => {
+                return Err(syn::Error::new_spanned(
+                    receiver,
+                    "`self` arguments are not supported in dll functions.",
+                ))
+            }
+        }
+    }
+
+    // `#[bool]` on the function converts a `BOOL` return value to `bool`
+    let sig_output = match (&bool_attr, output) {
+        (None, output) => quote! { #output },
+        (Some(_), ReturnType::Type(arrow, ty)) if is_bool(ty) => quote! { #arrow i32 },
+        (Some(attr), _) => {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "`#[bool]` can only be used on functions returning `bool`.",
+            ))
+        }
+    };
+    if marshalled && variadic.is_some() {
+        return Err(syn::Error::new_spanned(
+            variadic,
+            "Marshalling attributes are not supported on variadic functions.",
+        ));
+    }
+
+    let variadic = variadic.as_ref().map(|variadic| quote! { , #variadic });
+    let func = Ident::new("func", Span::mixed_site());
+
+    let link = link_attr.unwrap_or_else(|| Link::Name(ident.to_string()));
+
+    let fallible_return_type = |output: proc_macro2::TokenStream| {
+        if fallible_attr {
+            quote! { -> #crate_name::macro_internal::Result<#output, #crate_name::Error<#ident>> }
+        } else {
+            quote! { -> #output }
+        }
+    };
+    let outer_return_type = match &output {
+        ReturnType::Default if !fallible_attr => quote! {},
+        ReturnType::Default => fallible_return_type(quote! { () }),
+        ReturnType::Type(_, ty) => fallible_return_type(quote! { #ty }),
+    };
+
+    let get_fn_ptr = if fallible_attr {
+        quote! {
+            <#ident as #crate_name::WindowsDllProc>::proc()?
+        }
+    } else {
+        quote! {
+            <#ident as #crate_name::WindowsDllProc>::proc()
+                .unwrap_or_else(|err| panic!("{}", err))
+        }
+    };
+    let ok = |value: proc_macro2::TokenStream| {
+        if fallible_attr {
+            quote! { Ok(#value) }
+        } else {
+            value
+        }
+    };
+
+    let (trace, call) = trace_call(
+        crate_name,
+        ident,
+        &argument_names,
+        quote! { #func( #(#argument_values),* ) },
+    );
+    let count_call = if cfg!(feature = "call-stats") {
+        quote! { <#ident as #crate_name::WindowsDllProc>::cache().count_call(); }
+    } else {
+        quote! {}
+    };
+    let call = match bool_attr {
+        Some(_) => quote! { #call != 0 },
+        None => call,
+    };
+    let return_value = ok(call);
+
+    // Variadic functions can't be defined in stable Rust, so the wrapper returns
+    // the function pointer instead of forwarding the arguments
+    let wrapper = if variadic.is_some() {
+        let sig = quote! { <#ident as #crate_name::WindowsDllProc>::Sig };
+        let (return_type, return_value) = if fallible_attr {
+            (
+                quote! { #crate_name::macro_internal::Result<#sig, #crate_name::Error<#ident>> },
+                quote! { <#ident as #crate_name::WindowsDllProc>::proc() },
+            )
+        } else {
+            (sig, get_fn_ptr.clone())
+        };
+
+        quote! {
+            #(#attrs)*
+            #vis unsafe fn #ident() -> #return_type {
+                #return_value
+            }
+        }
+    } else if safe_attr.is_some() {
+        // The declaration asserts that calling the function is safe
+        quote! {
+            #(#attrs)*
+            #vis fn #ident ( #(#arguments),* ) #outer_return_type {
+                unsafe {
+                    let #func = #get_fn_ptr;
+                    #count_call
+                    #(#conversions)*
+                    #trace
+
+                    #return_value
+                }
+            }
+        }
+    } else {
+        quote! {
+            #(#attrs)*
+            #vis unsafe fn #ident ( #(#arguments),* ) #outer_return_type {
+                let #func = #get_fn_ptr;
+                #count_call
+                #(#conversions)*
+                #trace
+
+                #return_value
+            }
+        }
+    };
+
+    // With marshalling, the function taking the types of the dll is still available as `raw`
+    let raw = if marshalled {
+        let raw_return_type = match &output {
+            ReturnType::Default if !fallible_attr => quote! {},
+            ReturnType::Default => fallible_return_type(quote! { () }),
+            ReturnType::Type(..) if bool_attr.is_some() => fallible_return_type(quote! { i32 }),
+            ReturnType::Type(_, ty) => fallible_return_type(quote! { #ty }),
+        };
+        let (raw_trace, raw_call) = trace_call(
+            crate_name,
+            ident,
+            &argument_names,
+            quote! { #func( #(#argument_names),* ) },
+        );
+        let raw_return_value = ok(raw_call);
+
+        quote! {
+            impl #ident {
+                #[allow(non_snake_case)]
+                #vis unsafe fn raw( #(#raw_arguments),* ) #raw_return_type {
+                    let #func = #get_fn_ptr;
+                    #count_call
+                    #raw_trace
+
+                    #raw_return_value
+                }
+            }
+        }
     } else {
-        let mut pieces = dll_name.rsplitn(3, |c| c == '\\' || c == '/');
+        quote! {}
+    };
+    let sig = quote! { unsafe #abi fn( #(#argument_types),* #variadic ) #sig_output };
+    let import = Ident::new("import", Span::mixed_site());
+    let raw_dylib = raw_dylib.map(|link_attrs| {
+        raw_dylib_proc(
+            crate_name,
+            link_attrs,
+            abi,
+            &link,
+            quote! { fn #import( #(_: #argument_types),* #variadic ) #sig_output; },
+            quote! { #import as Self::Sig },
+        )
+    });
+    let proc_impl = proc_impl(
+        crate_name,
+        dll_type,
+        &vis,
+        ident,
+        index,
+        &sig,
+        &link,
+        raw_dylib,
+        min_os.map(|min_os| min_os.check(crate_name)),
+    );
 
-        pieces.next().unwrap()
+    Ok(quote! {
+        #proc_impl
+        #raw
+        #wrapper
+    })
+}
+
+fn parse_static(
+    crate_name: &Ident,
+    dll_type: &proc_macro2::TokenStream,
+    abi: &Abi,
+    index: usize,
+    item: ForeignItemStatic,
+    raw_dylib: Option<&proc_macro2::TokenStream>,
+) -> Result<proc_macro2::TokenStream> {
+    let ForeignItemStatic {
+        attrs,
+        vis,
+        mutability,
+        ident,
+        ty,
+        ..
+    } = item;
+
+    let ItemAttrs {
+        link: link_attr,
+        fallible: fallible_attr,
+        safe: safe_attr,
+        bool_return,
+        min_os,
+        attrs,
+    } = parse_item_attrs(attrs)?;
+    if let Some(safe_attr) = safe_attr {
+        return Err(syn::Error::new_spanned(
+            safe_attr.attr,
+            "`#[safe]` is only supported on functions.",
+        ));
+    }
+    if let Some(bool_attr) = bool_return {
+        return Err(syn::Error::new_spanned(
+            bool_attr,
+            "`#[bool]` is only supported on functions.",
+        ));
+    }
+    let link = link_attr.unwrap_or_else(|| Link::Name(ident.to_string()));
+
+    // The accessor returns a shared reference to immutable statics, and a raw pointer
+    // to mutable ones, because there is no way to synchronize access to them
+    let sig = match mutability {
+        Some(_) => quote! { *mut #ty },
+        None => quote! { &'static #ty },
+    };
+
+    let accessor = if fallible_attr {
+        quote! {
+            #(#attrs)*
+            #[allow(non_snake_case)]
+            #vis unsafe fn #ident() -> #crate_name::macro_internal::Result<#sig, #crate_name::Error<#ident>> {
+                <#ident as #crate_name::WindowsDllProc>::proc()
+            }
+        }
+    } else {
+        quote! {
+            #(#attrs)*
+            #[allow(non_snake_case)]
+            #vis unsafe fn #ident() -> #sig {
+                <#ident as #crate_name::WindowsDllProc>::proc()
+                    .unwrap_or_else(|err| panic!("{}", err))
+            }
+        }
+    };
+    let import = Ident::new("import", Span::mixed_site());
+    let raw_dylib = raw_dylib.map(|link_attrs| {
+        let (item, value) = match mutability {
+            Some(_) => (
+                quote! { static mut #import: #ty; },
+                quote! { #crate_name::macro_internal::core::ptr::addr_of_mut!(#import) },
+            ),
+            None => (quote! { static #import: #ty; }, quote! { &#import }),
+        };
+        raw_dylib_proc(crate_name, link_attrs, abi, &link, item, value)
+    });
+    let proc_impl = proc_impl(
+        crate_name,
+        dll_type,
+        &vis,
+        &ident,
+        index,
+        &sig,
+        &link,
+        raw_dylib,
+        min_os.map(|min_os| min_os.check(crate_name)),
+    );
+
+    Ok(quote! {
+        #proc_impl
+        #accessor
+    })
+}
+
+/// The attributes of a function or static handled by the macro
+struct ItemAttrs {
+    link: Option<Link>,
+    fallible: bool,
+    safe: Option<SafeAttr>,
+    /// `#[bool]`, convert a `BOOL` return value to `bool`
+    bool_return: Option<Attribute>,
+    min_os: Option<MinOs>,
+    /// The remaining attributes, passed through to the wrapper
+    attrs: Vec<Attribute>,
+}
+
+/// `#[min_os(major = 10, minor = 0, build = 17763)]`, the oldest Windows version exporting
+/// the item. Any of the parts can be left out, only the given ones are compared.
+#[derive(Default)]
+struct MinOs {
+    major: Option<u32>,
+    minor: Option<u32>,
+    build: Option<u32>,
+}
+
+impl MinOs {
+    /// An expression checking whether the process runs on this version or a later one
+    fn check(&self, crate_name: &Ident) -> proc_macro2::TokenStream {
+        let part = |part: Option<u32>| match part {
+            Some(part) => quote! { #crate_name::macro_internal::Option::Some(#part) },
+            None => quote! { #crate_name::macro_internal::Option::None },
+        };
+        let (major, minor, build) = (part(self.major), part(self.minor), part(self.build));
+        quote! {
+            #crate_name::macro_internal::os_version_at_least(#major, #minor, #build)
+        }
+    }
+}
+
+/// `#[safe]` or `#[safe(strict)]`
+struct SafeAttr {
+    attr: Attribute,
+    /// Reject signatures containing raw pointers
+    strict: bool,
+}
+
+fn parse_item_attrs(attrs: Vec<Attribute>) -> Result<ItemAttrs> {
+    let mut link = None;
+    let mut fallible = false;
+    let mut safe = None;
+    let mut bool_return = None;
+    let mut min_os = None;
+    let mut other_attrs = Vec::new();
+    for attr in attrs {
+        if attr.path.is_ident("link_ordinal") {
+            link = Some(Link::Ordinal(link_ordinal(&attr)?));
+        } else if attr.path.is_ident("link_name") {
+            link = Some(Link::Name(link_name(&attr)?));
+        } else if attr.path.is_ident("fallible") {
+            fallible = true;
+        } else if attr.path.is_ident("safe") {
+            let strict = safe_strict(&attr)?;
+            safe = Some(SafeAttr { attr, strict });
+        } else if attr.path.is_ident("bool") {
+            bool_return = Some(attr);
+        } else if attr.path.is_ident("min_os") {
+            min_os = Some(parse_min_os(&attr)?);
+        } else {
+            other_attrs.push(attr);
+        }
+    }
+
+    Ok(ItemAttrs {
+        link,
+        fallible,
+        safe,
+        bool_return,
+        min_os,
+        attrs: other_attrs,
+    })
+}
+
+/// Conversion of an argument in the generated wrapper, before passing it to the dll
+enum Marshal {
+    /// `#[wide]`, a string passed as a null-terminated UTF-16 string
+    Wide,
+    /// `#[ansi]`, a string passed as a null-terminated string in the ANSI code page
+    Ansi,
+    /// `#[bool]`, a `bool` passed as `BOOL`
+    Bool,
+    /// `#[out]`, a `&mut T` passed as `*mut T`
+    Out,
+}
+
+/// Split the marshalling attribute of an argument from the ones passed through
+fn parse_marshal_attr(attrs: &[Attribute]) -> Result<(Option<Marshal>, Vec<&Attribute>)> {
+    let mut marshal = None;
+    let mut other_attrs = Vec::new();
+    for attr in attrs {
+        let kind = if attr.path.is_ident("wide") {
+            Marshal::Wide
+        } else if attr.path.is_ident("ansi") {
+            Marshal::Ansi
+        } else if attr.path.is_ident("bool") {
+            Marshal::Bool
+        } else if attr.path.is_ident("out") {
+            Marshal::Out
+        } else {
+            other_attrs.push(attr);
+            continue;
+        };
+
+        if !attr.tokens.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &attr.tokens,
+                "Marshalling attributes don't take arguments.",
+            ));
+        }
+        if marshal.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "Only one marshalling attribute can be used per argument.",
+            ));
+        }
+        marshal = Some(kind);
+    }
+
+    Ok((marshal, other_attrs))
+}
+
+fn is_bool(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.qself.is_none() && path.path.is_ident("bool"),
+        Type::Paren(paren) => is_bool(&paren.elem),
+        Type::Group(group) => is_bool(&group.elem),
+        _ => false,
+    }
+}
+
+fn parse_min_os(attr: &Attribute) -> Result<MinOs> {
+    const EXPECTED: &str =
+        "Expected `#[min_os(major = <major>, minor = <minor>, build = <build>)]`, with at least one of them.";
+
+    let list = match attr.parse_meta()? {
+        Meta::List(list) if !list.nested.is_empty() => list,
+        meta => return Err(syn::Error::new_spanned(meta, EXPECTED)),
+    };
+    let mut min_os = MinOs::default();
+    for nested in list.nested {
+        let (path, value) = match nested {
+            NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                path,
+                lit: Lit::Int(value),
+                ..
+            })) => (path, value),
+            nested => return Err(syn::Error::new_spanned(nested, EXPECTED)),
+        };
+        let part = if path.is_ident("major") {
+            &mut min_os.major
+        } else if path.is_ident("minor") {
+            &mut min_os.minor
+        } else if path.is_ident("build") {
+            &mut min_os.build
+        } else {
+            return Err(syn::Error::new_spanned(
+                path,
+                "Unknown version part, expected `major`, `minor` or `build`.",
+            ));
+        };
+        if part.is_some() {
+            return Err(syn::Error::new_spanned(
+                &path,
+                format!(
+                    "Version part `{}` passed to `#[min_os]` more than once.",
+                    path.get_ident().unwrap()
+                ),
+            ));
+        }
+        *part = Some(value.base10_parse()?);
+    }
+    Ok(min_os)
+}
+
+fn safe_strict(attr: &Attribute) -> Result<bool> {
+    match attr.parse_meta()? {
+        Meta::Path(_) => Ok(false),
+        Meta::List(list) if list.nested.len() == 1 => match list.nested.first() {
+            Some(NestedMeta::Meta(Meta::Path(path))) if path.is_ident("strict") => Ok(true),
+            _ => Err(syn::Error::new_spanned(
+                list.nested,
+                "Expected `#[safe]` or `#[safe(strict)]`.",
+            )),
+        },
+        meta => Err(syn::Error::new_spanned(
+            meta,
+            "Expected `#[safe]` or `#[safe(strict)]`.",
+        )),
+    }
+}
+
+/// Win32 aliases of pointers and handles, recognized by name since type aliases can't be
+/// resolved by the macro. The list is best-effort: it misses other aliases, and rejects any
+/// type with one of these names even when it isn't a pointer.
+const POINTER_ALIASES: &[&str] = &[
+    "PVOID",
+    "LPVOID",
+    "LPCVOID",
+    "PSTR",
+    "PCSTR",
+    "LPSTR",
+    "LPCSTR",
+    "PWSTR",
+    "PCWSTR",
+    "LPWSTR",
+    "LPCWSTR",
+    "PBYTE",
+    "LPBYTE",
+    "HANDLE",
+    "HMODULE",
+    "HINSTANCE",
+    "HWND",
+    "HDC",
+    "HKEY",
+    "HMENU",
+    "HICON",
+    "HBITMAP",
+    "HBRUSH",
+    "HFONT",
+    "HGLOBAL",
+    "HLOCAL",
+];
+
+/// Find a raw pointer in a type, or a well-known Win32 alias of one, looking through
+/// references, arrays, tuples and generic arguments
+fn find_raw_pointer(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Ptr(_) => Some(ty),
+        Type::Path(path)
+            if path.qself.is_none()
+                && matches!(
+                    path.path.segments.last(),
+                    Some(segment) if POINTER_ALIASES.contains(&segment.ident.to_string().as_str())
+                ) =>
+        {
+            Some(ty)
+        }
+        Type::Array(array) => find_raw_pointer(&array.elem),
+        Type::Slice(slice) => find_raw_pointer(&slice.elem),
+        Type::Reference(reference) => find_raw_pointer(&reference.elem),
+        Type::Group(group) => find_raw_pointer(&group.elem),
+        Type::Paren(paren) => find_raw_pointer(&paren.elem),
+        Type::Tuple(tuple) => tuple.elems.iter().find_map(find_raw_pointer),
+        Type::Path(path) => path
+            .path
+            .segments
+            .iter()
+            .filter_map(|segment| match &segment.arguments {
+                PathArguments::AngleBracketed(arguments) => Some(&arguments.args),
+                _ => None,
+            })
+            .flatten()
+            .find_map(|argument| match argument {
+                GenericArgument::Type(ty) => find_raw_pointer(ty),
+                _ => None,
+            }),
+        _ => None,
+    }
+}
+
+/// With the `trace` feature, report a call to the `CallHook` before and after making it.
+/// Returns the statements to insert before the value, and the value of the call.
+fn trace_call(
+    crate_name: &Ident,
+    ident: &Ident,
+    argument_names: &[Ident],
+    call: proc_macro2::TokenStream,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    if !cfg!(feature = "trace") {
+        return (quote! {}, call);
+    }
+
+    let started = Ident::new("started", Span::mixed_site());
+    let value = Ident::new("value", Span::mixed_site());
+    let argument_strings = argument_names.iter().map(|name| name.to_string());
+    let trace = quote! { #crate_name::trace };
+
+    let statements = quote! {
+        let #started = {
+            use #trace::{ViaDebug as _, ViaOpaque as _};
+            #trace::before::<#ident>(&[
+                #( (#argument_strings, (&&#trace::TraceArg(&#argument_names)).trace_arg()) ),*
+            ])
+        };
+        let #value = #call;
+        {
+            use #trace::{ViaDebug as _, ViaOpaque as _};
+            #trace::after::<#ident>(#started, (&&#trace::TraceArg(&#value)).trace_arg());
+        }
+    };
+
+    (statements, quote! { #value })
+}
+
+/// Generate the type representing a function or static exported from the dll
+#[allow(clippy::too_many_arguments)]
+fn proc_impl(
+    crate_name: &Ident,
+    dll_type: &proc_macro2::TokenStream,
+    vis: &Visibility,
+    ident: &Ident,
+    index: usize,
+    sig: &proc_macro2::TokenStream,
+    link: &Link,
+    raw_dylib: Option<proc_macro2::TokenStream>,
+    min_os: Option<proc_macro2::TokenStream>,
+) -> proc_macro2::TokenStream {
+    let proc = link.proc(crate_name);
+    let proc_lpcstr = link.proc_lpcstr(crate_name);
+
+    // In raw-dylib mode the export is imported by the linker, so it always exists
+    // on the versions of Windows it is available on
+    let raw_dylib_exists = match &min_os {
+        Some(check) => check.clone(),
+        None => quote! { true },
+    };
+    let (address, exists, proc_body) = match raw_dylib {
+        Some(proc_body) => (
+            quote! {
+                <Self as #crate_name::WindowsDllProc>::proc()
+                    .ok()
+                    .map(|proc| #crate_name::macro_internal::core::mem::transmute_copy(&proc))
+            },
+            quote! {
+                unsafe fn exists() -> bool {
+                    #raw_dylib_exists
+                }
+            },
+            proc_body,
+        ),
+        None => (
+            quote! {
+                <<Self as #crate_name::WindowsDllProc>::Dll as #crate_name::WindowsDll>::cache()
+                    .get_proc_address::<Self>()
+                    .ok()
+            },
+            quote! {},
+            quote! {
+                <Self::Dll as #crate_name::WindowsDll>::cache().get_proc::<#ident>()
+            },
+        ),
+    };
+
+    // On older versions of Windows the export isn't looked up at all
+    let (address, proc_body) = match min_os {
+        Some(check) => (
+            quote! {
+                if #check {
+                    #address
+                } else {
+                    #crate_name::macro_internal::Option::None
+                }
+            },
+            quote! {
+                if !#check {
+                    return #crate_name::macro_internal::Result::Err(#crate_name::Error::proc());
+                }
+                #proc_body
+            },
+        ),
+        None => (address, proc_body),
+    };
+
+    quote! {
+        #[allow(non_camel_case_types)]
+        #vis enum #ident {}
+        impl #ident {
+            /// Whether the dll and this export in it can be loaded
+            pub unsafe fn exists() -> bool {
+                <Self as #crate_name::WindowsDllProc>::exists()
+            }
+            /// The loaded export as its declared type, `None` if it can't be loaded
+            pub unsafe fn ptr() -> #crate_name::macro_internal::Option<#crate_name::Sig<Self>> {
+                <Self as #crate_name::WindowsDllProc>::proc().ok()
+            }
+            /// The address of the loaded export, `None` if it can't be loaded
+            pub unsafe fn address(
+            ) -> #crate_name::macro_internal::Option<*const #crate_name::macro_internal::core::ffi::c_void> {
+                #address
+            }
+            /// Use `ptr` instead of the export of the dll, until [`restore`](Self::restore) is called
+            pub unsafe fn override_with(ptr: #crate_name::Sig<Self>) {
+                <Self as #crate_name::WindowsDllProc>::cache().override_with(ptr)
+            }
+            /// Use the export of the dll again after [`override_with`](Self::override_with)
+            pub unsafe fn restore() {
+                <Self as #crate_name::WindowsDllProc>::cache().restore()
+            }
+        }
+
+        impl #crate_name::WindowsDllProc for #ident {
+            type Dll = #dll_type;
+            type Sig = #sig;
+            const CACHE_INDEX: usize = #index;
+            const PROC: #crate_name::Proc = #proc;
+            const PROC_LPCSTR: #crate_name::macro_internal::LPCSTR = #proc_lpcstr;
+
+            unsafe fn cache() -> &'static #crate_name::macro_internal::DllProcCache {
+                static PROC_CACHE: #crate_name::macro_internal::DllProcCache = #crate_name::macro_internal::DllProcCache::empty();
+
+                &PROC_CACHE
+            }
+            unsafe fn proc() -> #crate_name::macro_internal::Result<Self::Sig, #crate_name::Error<#ident>> {
+                #proc_body
+            }
+            #exists
+        }
+    }
+}
+
+/// The body of `proc()` for an export imported with raw-dylib, declaring `item` in an
+/// extern block linking to the dll, and returning `value` unless the export is overridden
+fn raw_dylib_proc(
+    crate_name: &Ident,
+    link_attrs: &proc_macro2::TokenStream,
+    abi: &Abi,
+    link: &Link,
+    item: proc_macro2::TokenStream,
+    value: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let link_item = match link {
+        Link::Ordinal(ordinal) => quote! { #[link_ordinal(#ordinal)] },
+        Link::Name(name) => quote! { #[link_name = #name] },
+    };
+
+    quote! {
+        #link_attrs
+        #[allow(clashing_extern_declarations)]
+        #abi {
+            #link_item
+            #item
+        }
+        #crate_name::macro_internal::Result::Ok(
+            <Self as #crate_name::WindowsDllProc>::cache().override_or(#value)
+        )
+    }
+}
+
+/// Generate the type representing the dll
+fn dll_impl(
+    crate_name: &Ident,
+    dll_type_ident: &Ident,
+    vis: Option<&Visibility>,
+    dll_name: &str,
+    load_library_ex_flags: Option<&Expr>,
+    len: usize,
+    raw_dylib: bool,
+) -> proc_macro2::TokenStream {
+    let wide_dll_name = dll_name.encode_utf16().chain(once(0));
+    let wide_dll_name = quote! { (&[#(#wide_dll_name),*]).as_ptr() };
+
+    let vis = match vis {
+        Some(vis) => quote! { #vis },
+        None => quote! { pub },
     };
-    let dll_type_ident = Ident::new(dll_type_name, dll_name_span);
 
     // Generate the flags to pass to the load_library_ex function.
     // Defaulting to 0 will make LoadLibraryExW behave like
@@ -98,17 +1325,34 @@ pub fn parse_extern_block(
     // > identical to that of the LoadLibrary function.
     // https://docs.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-loadlibraryexw
     let flags = if let Some(expr) = load_library_ex_flags {
-        quote! { #expr }
+        let flags = load_flags(expr, crate_name);
+        // The inner const is evaluated at compile time even if the library
+        // is never used, so invalid flag combinations are always reported
+        quote! {{
+            const FLAGS: #crate_name::flags::LOAD_LIBRARY_FLAGS = #flags.validated().to_native();
+            FLAGS
+        }}
     } else {
         quote! { #crate_name::flags::NO_FLAGS }
     };
 
-    let ItemForeignMod { abi, items, .. } = parse(input)?;
+    // A dll imported with raw-dylib is loaded with the executable and can't be freed
+    let raw_dylib = if raw_dylib {
+        quote! {
+            unsafe fn exists() -> bool {
+                true
+            }
+            unsafe fn free() -> bool {
+                false
+            }
+        }
+    } else {
+        quote! {}
+    };
 
-    let len = items.len();
-    let dll_impl = quote! {
+    quote! {
         #[allow(non_camel_case_types)]
-        pub enum #dll_type_ident {}
+        #vis enum #dll_type_ident {}
         impl #dll_type_ident {
             pub unsafe fn exists() -> bool {
                 <Self as #crate_name::WindowsDll>::exists()
@@ -129,133 +1373,67 @@ pub fn parse_extern_block(
 
                 &LIB_CACHE
             }
+            #raw_dylib
         }
-    };
-
-    let functions = items.into_iter().enumerate().map(|(index, item)| match item {
-        ForeignItem::Fn(ForeignItemFn {
-            attrs, vis, sig, ..
-        }) => {
-            let link_attr = attrs.iter().find_map(|attr| {
-                let meta = attr.parse_meta().ok()?;
-                if meta.path().is_ident("link_ordinal") {
-                    match meta_value(meta)? {
-                        Lit::Int(int) => Some(Link::Ordinal(int)),
-                        _ => None,
-                    }
-                } else if meta.path().is_ident("link_name") {
-                    match meta_value(meta)? {
-                        Lit::Str(string) => Some(Link::Name(string.value())),
-                        _ => None,
-                    }
-                } else {
-                    None
-                }
-            });
-
-            let fallible_attr = attrs.iter().any(|attr| match attr.parse_meta() {
-                Ok(meta) => meta.path().is_ident("fallible"),
-                Err(_) => false,
-            });
-
-            let attrs = attrs.into_iter().filter(|attr| match attr.parse_meta() {
-                Ok(meta) => {
-                    let path = meta.path();
-                    !(path.is_ident("link_ordinal")
-                        || path.is_ident("link_name")
-                        || path.is_ident("fallible"))
-                }
-                Err(_) => true,
-            });
-
-            let Signature {
-                ident,
-                inputs,
-                output,
-                ..
-            } = &sig;
-
-            use syn::{Pat, PatIdent, PatType};
-            let argument_names = inputs.iter().map(|i| match i {
-                FnArg::Typed(PatType { pat, .. }) => match &**pat {
-                    Pat::Ident(PatIdent { ident, .. }) => ident,
-                    _ => panic!("Argument type not supported"),
-                },
-                _ => panic!("Argument type not supported"),
-            });
-            let inputs: Vec<_> = inputs.into_iter().collect();
-
-            let link = link_attr.unwrap_or_else(|| Link::Name(ident.to_string()));
-
-            let outer_return_type = if fallible_attr {
-                match &output {
-                    ReturnType::Default => {
-                        quote! { -> #crate_name::macro_internal::Result<(), #crate_name::Error<#ident>> }
-                    }
-                    ReturnType::Type(_, ty) => {
-                        quote! { -> #crate_name::macro_internal::Result<#ty, #crate_name::Error<#ident>> }
-                    }
-                }
-            } else {
-                quote! { #output }
-            };
+    }
+}
 
-            let get_fn_ptr = if fallible_attr {
-                quote! {
-                    <#ident as #crate_name::WindowsDllProc>::proc()?
-                }
-            } else {
-                quote! {
-                    <#ident as #crate_name::WindowsDllProc>::proc()
-                        .unwrap_or_else(|err| panic!("{}", err))
-                }
-            };
+/// Derive the name of the dll type from the file name of the dll,
+/// replacing any characters that are not allowed in identifiers
+fn dll_type_ident(dll_name: &str, span: Span) -> Result<Ident> {
+    let file_name = dll_name.rsplit(&['\\', '/'][..]).next().unwrap_or(dll_name);
+    let stem = match file_name.len().checked_sub(".dll".len()) {
+        Some(len) if file_name[len..].eq_ignore_ascii_case(".dll") => &file_name[..len],
+        _ => file_name,
+    };
 
-            let return_value = quote! { func( #(#argument_names),* ) };
-            let return_value = if fallible_attr {
-                quote! { Ok(#return_value) }
+    let mut type_name: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
             } else {
-                return_value
-            };
-            let proc = link.proc(&crate_name);
-            let proc_lpcstr = link.proc_lpcstr(&crate_name);
-
-            quote! {
-                #[allow(non_camel_case_types)]
-                #vis enum #ident {}
-                impl #ident {
-                    pub unsafe fn exists() -> bool {
-                        <Self as #crate_name::WindowsDllProc>::exists()
-                    }
-                }
-
-                impl #crate_name::WindowsDllProc for #ident {
-                    type Dll = #dll_type_ident;
-                    type Sig = unsafe #abi fn( #(#inputs),* ) #output;
-                    const CACHE_INDEX: usize = #index;
-                    const PROC: #crate_name::Proc = #proc;
-                    const PROC_LPCSTR: #crate_name::macro_internal::LPCSTR = #proc_lpcstr;
-
-                    unsafe fn proc() -> #crate_name::macro_internal::Result<Self::Sig, #crate_name::Error<#ident>> {
-                        <Self::Dll as #crate_name::WindowsDll>::cache().get_proc::<#ident>()
-                    }
-                }
+                '_'
+            }
+        })
+        .collect();
+    if type_name.starts_with(|c: char| c.is_ascii_digit()) {
+        type_name.insert(0, '_');
+    }
+    if type_name.is_empty() || type_name.chars().all(|c| c == '_') {
+        return Err(syn::Error::new(
+            span,
+            "Cannot derive a type name from the DLL name, pass one with `type_name = ...`.",
+        ));
+    }
+    // Keywords can't be used as the type name
+    if syn::parse_str::<Ident>(&type_name).is_err() {
+        type_name.push('_');
+    }
 
-                #(#attrs)*
-                #vis unsafe fn #ident ( #(#inputs),* ) #outer_return_type {
-                    let func = #get_fn_ptr;
+    Ok(Ident::new(&type_name, span))
+}
 
-                    #return_value
-                }
-            }
+/// Convert the flags argument to a `LoadFlags` expression.
+/// `|` is not a const operation for `LoadFlags` or the `windows` crate's
+/// `LOAD_LIBRARY_FLAGS`, so each operand is converted separately
+/// and combined with the const fn `LoadFlags::union`.
+fn load_flags(expr: &Expr, crate_name: &Ident) -> proc_macro2::TokenStream {
+    match expr {
+        Expr::Binary(ExprBinary {
+            left,
+            op: BinOp::BitOr(_),
+            right,
+            ..
+        }) => {
+            let left = load_flags(left, crate_name);
+            let right = load_flags(right, crate_name);
+            quote! { #left.union(#right) }
         }
-        _ => panic!("Not a function"),
-    });
-
-    Ok(quote! {
-        #dll_impl
-        #(#functions)*
-    })
+        Expr::Paren(ExprParen { expr, .. }) => load_flags(expr, crate_name),
+        Expr::Group(ExprGroup { expr, .. }) => load_flags(expr, crate_name),
+        expr => quote! { #crate_name::macro_internal::LoadFlagsArg(#expr).load_flags() },
+    }
 }
 
 enum Link {
@@ -282,8 +1460,33 @@ impl Link {
     }
 }
 
-fn meta_value(meta: Meta) -> Option<Lit> {
-    match meta {
+fn link_ordinal(attr: &Attribute) -> Result<LitInt> {
+    match meta_value(attr)? {
+        Some(Lit::Int(ordinal)) => {
+            ordinal.base10_parse::<u16>()?;
+            Ok(ordinal)
+        }
+        Some(lit) => Err(syn::Error::new_spanned(lit, "Expected an integer ordinal.")),
+        None => Err(syn::Error::new_spanned(
+            attr,
+            "Expected `#[link_ordinal = <ordinal>]`.",
+        )),
+    }
+}
+
+fn link_name(attr: &Attribute) -> Result<String> {
+    match meta_value(attr)? {
+        Some(Lit::Str(name)) => Ok(name.value()),
+        Some(lit) => Err(syn::Error::new_spanned(lit, "Expected a string.")),
+        None => Err(syn::Error::new_spanned(
+            attr,
+            "Expected `#[link_name = \"<name>\"]`.",
+        )),
+    }
+}
+
+fn meta_value(attr: &Attribute) -> Result<Option<Lit>> {
+    Ok(match attr.parse_meta()? {
         Meta::List(mut list) => {
             if list.nested.len() == 1 {
                 list.nested.pop().and_then(|pair| match pair.into_value() {
@@ -296,5 +1499,5 @@ fn meta_value(meta: Meta) -> Option<Lit> {
         }
         Meta::NameValue(name_value) => Some(name_value.lit),
         _ => None,
-    }
+    })
 }
@@ -1,6 +1,5 @@
 use core::{ffi::c_void, mem};
 use minifb::{Key, Window, WindowOptions};
-use once_cell::sync::Lazy;
 use windows_dll::dll;
 
 const WIDTH: usize = 640;
@@ -40,58 +39,11 @@ mod platform {
     use super::*;
     use winapi::shared::{
         basetsd::SIZE_T,
-        minwindef::{BOOL, DWORD, FALSE, ULONG},
-        ntdef::{NTSTATUS, NT_SUCCESS, PVOID, WCHAR},
+        minwindef::{BOOL, FALSE},
+        ntdef::PVOID,
         windef::HWND,
     };
 
-    static WIN10_BUILD: Lazy<Option<DWORD>> = Lazy::new(|| {
-        #[dll(ntdll)]
-        extern "system" {
-            #[allow(non_snake_case)]
-            fn RtlGetVersion(lpVersionInformation: *mut OSVERSIONINFOW) -> NTSTATUS;
-        }
-
-        #[allow(non_snake_case)]
-        #[repr(C)]
-        struct OSVERSIONINFOW {
-            dwOSVersionInfoSize: ULONG,
-            dwMajorVersion: ULONG,
-            dwMinorVersion: ULONG,
-            dwBuildNumber: ULONG,
-            dwPlatformId: ULONG,
-            szCSDVersion: [WCHAR; 128],
-        }
-        unsafe {
-            if !RtlGetVersion::exists() {
-                return None;
-            }
-            let mut version_info = OSVERSIONINFOW {
-                dwOSVersionInfoSize: 0,
-                dwMajorVersion: 0,
-                dwMinorVersion: 0,
-                dwBuildNumber: 0,
-                dwPlatformId: 0,
-                szCSDVersion: [0; 128],
-            };
-            let status = RtlGetVersion(&mut version_info);
-
-            if NT_SUCCESS(status)
-                && version_info.dwMajorVersion == 10
-                && version_info.dwMinorVersion == 0
-            {
-                Some(version_info.dwBuildNumber)
-            } else {
-                None
-            }
-        }
-    });
-
-    static DARK_MODE_SUPPORTED: Lazy<bool> = Lazy::new(|| match *WIN10_BUILD {
-        Some(build) => build >= 17763,
-        None => false,
-    });
-
     pub fn dark_dwm_decorations(minifb_hwnd: *mut c_void, enable_dark_mode: bool) -> bool {
         let hwnd = minifb_hwnd as HWND;
 
@@ -110,13 +62,15 @@ mod platform {
         #[dll(user32)]
         extern "system" {
             #[allow(non_snake_case)]
+            #[min_os(major = 10, minor = 0, build = 17763)]
             fn SetWindowCompositionAttribute(
                 h_wnd: HWND,
                 data: *mut WINDOWCOMPOSITIONATTRIBDATA,
             ) -> BOOL;
         }
 
-        if *DARK_MODE_SUPPORTED && unsafe { SetWindowCompositionAttribute::exists() } {
+        // `exists` is false before Windows 10 1809, without loading user32
+        if unsafe { SetWindowCompositionAttribute::exists() } {
             unsafe {
                 let mut is_dark_mode_bigbool = enable_dark_mode as BOOL;
                 let mut data = WINDOWCOMPOSITIONATTRIBDATA {
@@ -138,62 +92,12 @@ mod platform {
 #[cfg(feature = "windows")]
 mod platform {
     use super::*;
-    use windows::Win32::Foundation::{BOOL, HWND, NTSTATUS};
+    use windows::Win32::Foundation::{BOOL, HWND};
 
-    type DWORD = u32;
-    type ULONG = u32;
-    type WCHAR = u16;
     type PVOID = *mut c_void;
     #[allow(non_camel_case_types)]
     type SIZE_T = usize;
 
-    static WIN10_BUILD: Lazy<Option<DWORD>> = Lazy::new(|| {
-        #[dll(ntdll)]
-        extern "system" {
-            #[allow(non_snake_case)]
-            fn RtlGetVersion(lpVersionInformation: *mut OSVERSIONINFOW) -> NTSTATUS;
-        }
-
-        #[allow(non_snake_case)]
-        #[repr(C)]
-        struct OSVERSIONINFOW {
-            dwOSVersionInfoSize: ULONG,
-            dwMajorVersion: ULONG,
-            dwMinorVersion: ULONG,
-            dwBuildNumber: ULONG,
-            dwPlatformId: ULONG,
-            szCSDVersion: [WCHAR; 128],
-        }
-        unsafe {
-            if !RtlGetVersion::exists() {
-                return None;
-            }
-            let mut version_info = OSVERSIONINFOW {
-                dwOSVersionInfoSize: 0,
-                dwMajorVersion: 0,
-                dwMinorVersion: 0,
-                dwBuildNumber: 0,
-                dwPlatformId: 0,
-                szCSDVersion: [0; 128],
-            };
-            let status = RtlGetVersion(&mut version_info);
-
-            if status.is_ok()
-                && version_info.dwMajorVersion == 10
-                && version_info.dwMinorVersion == 0
-            {
-                Some(version_info.dwBuildNumber)
-            } else {
-                None
-            }
-        }
-    });
-
-    static DARK_MODE_SUPPORTED: Lazy<bool> = Lazy::new(|| match *WIN10_BUILD {
-        Some(build) => build >= 17763,
-        None => false,
-    });
-
     pub fn dark_dwm_decorations(minifb_hwnd: *mut c_void, enable_dark_mode: bool) -> bool {
         let hwnd = HWND(minifb_hwnd as _);
 
@@ -212,13 +116,15 @@ mod platform {
         #[dll(user32)]
         extern "system" {
             #[allow(non_snake_case)]
+            #[min_os(major = 10, minor = 0, build = 17763)]
             fn SetWindowCompositionAttribute(
                 h_wnd: HWND,
                 data: *mut WINDOWCOMPOSITIONATTRIBDATA,
             ) -> BOOL;
         }
 
-        if *DARK_MODE_SUPPORTED && unsafe { SetWindowCompositionAttribute::exists() } {
+        // `exists` is false before Windows 10 1809, without loading user32
+        if unsafe { SetWindowCompositionAttribute::exists() } {
             unsafe {
                 let mut is_dark_mode_bigbool = BOOL::from(enable_dark_mode);
                 let mut data = WINDOWCOMPOSITIONATTRIBDATA {
@@ -0,0 +1,166 @@
+//! The API set schema, which maps `api-ms-win-*` and `ext-ms-*` dll names to the dlls
+//! implementing them
+//!
+//! The schema is stored in the `.apiset` section of `apisetschema.dll` and mapped into every
+//! process by the loader. Only the format of Windows 10 and later, version 6, is supported.
+
+use crate::{pe::PeFile, Error};
+use std::convert::TryInto;
+
+const SECTION: &str = ".apiset";
+const VERSION: u32 = 6;
+
+const NAMESPACE_SIZE: usize = 28;
+const ENTRY_SIZE: usize = 24;
+const VALUE_SIZE: usize = 20;
+
+/// The API sets of a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiSetSchema {
+    pub api_sets: Vec<ApiSet>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiSet {
+    /// The lowercase name without `.dll`, like `api-ms-win-core-synch-l1-2-0`
+    pub name: String,
+    /// The length of the part of the name compared when resolving, names are matched
+    /// without their last `-` component so any minor version resolves to the same set
+    pub hashed_len: usize,
+    /// Whether the API set is sealed, extension sets (`ext-ms-*`) usually aren't
+    pub sealed: bool,
+    /// The dlls implementing the API set, empty when it isn't implemented on this
+    /// version of Windows
+    pub hosts: Vec<ApiSetHost>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiSetHost {
+    /// The dll importing the API set this host is used for, `None` for the default host
+    pub importer: Option<String>,
+    /// The file name of the dll implementing the API set
+    pub host: String,
+}
+
+/// Whether the dll is an API set rather than an actual file
+pub fn is_api_set(dll: &str) -> bool {
+    let dll = dll.to_ascii_lowercase();
+    dll.starts_with("api-") || dll.starts_with("ext-")
+}
+
+impl ApiSetSchema {
+    /// Read the schema from the `.apiset` section of `apisetschema.dll`
+    pub fn from_pe(pe: &PeFile<'_>) -> Result<Self, Error> {
+        let section = pe
+            .section_by_name(SECTION)
+            .ok_or_else(|| Error::NotFound(SECTION.to_string()))?;
+        Self::parse(pe.section_data(section)?)
+    }
+
+    /// Read a schema from `apisetschema.dll`, or from the bytes of the schema itself, like
+    /// one dumped from the memory of a process
+    pub fn from_file_data(data: &[u8]) -> Result<Self, Error> {
+        if data.starts_with(b"MZ") {
+            Self::from_pe(&PeFile::parse(data)?)
+        } else {
+            Self::parse(data)
+        }
+    }
+
+    /// Parse the `API_SET_NAMESPACE` structure, offsets in it are relative to its start
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < NAMESPACE_SIZE {
+            return Err(Error::ApiSet("truncated header"));
+        }
+        if read_u32(data, 0)? != VERSION {
+            return Err(Error::ApiSet("unsupported schema version"));
+        }
+        let count = read_u32(data, 12)? as usize;
+        let entries = read_u32(data, 16)? as usize;
+
+        let mut api_sets = Vec::with_capacity(count.min(data.len() / ENTRY_SIZE));
+        for i in 0..count {
+            let entry = entries + i * ENTRY_SIZE;
+            let flags = read_u32(data, entry)?;
+            let name = read_utf16(data, read_u32(data, entry + 4)?, read_u32(data, entry + 8)?)?;
+            let hashed_len = read_u32(data, entry + 12)? as usize / 2;
+            let values = read_u32(data, entry + 16)? as usize;
+            let value_count = read_u32(data, entry + 20)? as usize;
+
+            let mut hosts = Vec::new();
+            for j in 0..value_count {
+                let value = values + j * VALUE_SIZE;
+                let importer =
+                    read_utf16(data, read_u32(data, value + 4)?, read_u32(data, value + 8)?)?;
+                let host = read_utf16(
+                    data,
+                    read_u32(data, value + 12)?,
+                    read_u32(data, value + 16)?,
+                )?;
+                // Sets that aren't implemented have a single empty host
+                if host.is_empty() {
+                    continue;
+                }
+                hosts.push(ApiSetHost {
+                    importer: Some(importer).filter(|importer| !importer.is_empty()),
+                    host,
+                });
+            }
+
+            api_sets.push(ApiSet {
+                name: name.to_ascii_lowercase(),
+                hashed_len: hashed_len.min(name.len()),
+                sealed: flags & 1 != 0,
+                hosts,
+            });
+        }
+        Ok(Self { api_sets })
+    }
+
+    /// The API set a dll name refers to, matched like the loader does: case-insensitively,
+    /// with or without `.dll`, and ignoring the last `-` component of the version
+    pub fn api_set(&self, dll: &str) -> Option<&ApiSet> {
+        if !is_api_set(dll) {
+            return None;
+        }
+        let dll = dll.to_ascii_lowercase();
+        let dll = dll.strip_suffix(".dll").unwrap_or(&dll);
+        let (prefix, _) = dll.rsplit_once('-')?;
+        self.api_sets
+            .iter()
+            .find(|api_set| api_set.name.get(..api_set.hashed_len) == Some(prefix))
+    }
+
+    /// The dll implementing an API set when it is imported by `importer`, `None` when the
+    /// dll isn't a known API set or the API set isn't implemented
+    pub fn resolve(&self, dll: &str, importer: Option<&str>) -> Option<&str> {
+        let hosts = &self.api_set(dll)?.hosts;
+        importer
+            .and_then(|importer| {
+                hosts.iter().find(|host| {
+                    matches!(&host.importer, Some(name) if name.eq_ignore_ascii_case(importer))
+                })
+            })
+            .or_else(|| hosts.iter().find(|host| host.importer.is_none()))
+            .or_else(|| hosts.first())
+            .map(|host| host.host.as_str())
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(Error::ApiSet("offset out of bounds"))
+}
+
+/// Read a UTF-16 string whose length is in bytes
+fn read_utf16(data: &[u8], offset: u32, len: u32) -> Result<String, Error> {
+    let bytes = data
+        .get(offset as usize..offset as usize + len as usize)
+        .ok_or(Error::ApiSet("string out of bounds"))?;
+    let units = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect::<Vec<_>>();
+    String::from_utf16(&units).map_err(|_| Error::ApiSet("invalid UTF-16 name"))
+}
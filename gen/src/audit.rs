@@ -0,0 +1,125 @@
+//! The dlls and functions a binary loads at runtime with `windows-dll`
+//!
+//! Binaries built with the `audit` feature of `windows-dll` list every `#[dll]` extern block
+//! that is loaded at runtime in a `.wdll` section, which is read back here.
+
+use crate::{pe::PeFile, rust::Link, Error};
+use std::convert::TryInto;
+
+const SECTION: &str = ".wdll";
+const MAGIC: &[u8] = b"WDLL";
+const VERSION: u8 = 1;
+
+const PROC_NAME: u8 = 0;
+const PROC_ORDINAL: u8 = 1;
+
+/// A dll loaded at runtime, with the functions and statics resolved from it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeDll {
+    pub lib: String,
+    pub procs: Vec<Link>,
+}
+
+/// The dlls listed in the `.wdll` section of a binary
+///
+/// Declarations of the same dll are merged, dlls being compared case-insensitively.
+pub fn runtime_dlls(pe: &PeFile<'_>) -> Result<Vec<RuntimeDll>, Error> {
+    let section = pe
+        .section_by_name(SECTION)
+        .ok_or_else(|| Error::NotFound(SECTION.to_string()))?;
+    parse_records(pe.section_data(section)?)
+}
+
+/// The dlls of the records of a `.wdll` section
+pub fn parse_records(data: &[u8]) -> Result<Vec<RuntimeDll>, Error> {
+    let mut dlls: Vec<RuntimeDll> = Vec::new();
+    let mut reader = Reader { data, pos: 0 };
+    loop {
+        // The linker pads the records of each object file
+        while reader.data.get(reader.pos) == Some(&0) {
+            reader.pos += 1;
+        }
+        if reader.pos == data.len() {
+            break;
+        }
+
+        if reader.bytes(MAGIC.len())? != MAGIC {
+            return Err(Error::Audit("Invalid record"));
+        }
+        if reader.u8()? != VERSION {
+            return Err(Error::Audit("Unsupported record version"));
+        }
+        let lib = reader.str()?;
+        let count = reader.u16()?;
+        let mut procs = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            procs.push(match reader.u8()? {
+                PROC_NAME => Link::Name(reader.str()?),
+                PROC_ORDINAL => Link::Ordinal(reader.u16()?),
+                _ => return Err(Error::Audit("Invalid proc")),
+            });
+        }
+
+        let index = match dlls
+            .iter()
+            .position(|dll| dll.lib.eq_ignore_ascii_case(&lib))
+        {
+            Some(index) => index,
+            None => {
+                dlls.push(RuntimeDll {
+                    lib,
+                    procs: Vec::new(),
+                });
+                dlls.len() - 1
+            }
+        };
+        for proc in procs {
+            if !dlls[index].procs.contains(&proc) {
+                dlls[index].procs.push(proc);
+            }
+        }
+    }
+    Ok(dlls)
+}
+
+/// A manifest listing each dll, followed by its indented functions and `#<ordinal>`s
+pub fn manifest(dlls: &[RuntimeDll]) -> String {
+    let mut manifest = String::new();
+    for dll in dlls {
+        manifest.push_str(&dll.lib);
+        manifest.push('\n');
+        for proc in &dll.procs {
+            match proc {
+                Link::Name(name) => manifest.push_str(&format!("    {}\n", name)),
+                Link::Ordinal(ordinal) => manifest.push_str(&format!("    #{}\n", ordinal)),
+            }
+        }
+    }
+    manifest
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(Error::Audit("Truncated record"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, Error> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+    fn str(&mut self) -> Result<String, Error> {
+        let len = self.u16()?;
+        let bytes = self.bytes(len as usize)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::Audit("Invalid UTF-8 name"))
+    }
+}
@@ -0,0 +1,70 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The input is not a valid PE file, or it is missing a table
+    Pe(&'static str),
+    /// The input is not valid ECMA-335 metadata
+    Metadata(&'static str),
+    /// The input is not a valid C header
+    Header(String),
+    /// The input is not a valid API set schema
+    ApiSet(&'static str),
+    /// A forwarded export can't be followed to its target
+    Forwarder(String),
+    /// The `.wdll` section of a binary is not valid
+    Audit(&'static str),
+    /// The input is not valid Rust source
+    Source(syn::Error),
+    Regex(regex::Error),
+    /// A requested item doesn't exist in the input
+    NotFound(String),
+    /// Invalid command line arguments
+    Usage(String),
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Regex(err) => Some(err),
+            Self::Source(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => err.fmt(f),
+            Self::Pe(message) => write!(f, "Invalid PE file: {}", message),
+            Self::Metadata(message) => write!(f, "Invalid metadata: {}", message),
+            Self::Header(message) => write!(f, "Invalid header: {}", message),
+            Self::ApiSet(message) => write!(f, "Invalid API set schema: {}", message),
+            Self::Forwarder(message) => write!(f, "Unresolvable forwarder: {}", message),
+            Self::Audit(message) => write!(f, "Invalid .wdll section: {}", message),
+            Self::Source(err) => write!(f, "Invalid Rust source: {}", err),
+            Self::Regex(err) => err.fmt(f),
+            Self::NotFound(name) => write!(f, "`{}` was not found", name),
+            Self::Usage(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+impl From<regex::Error> for Error {
+    fn from(err: regex::Error) -> Self {
+        Self::Regex(err)
+    }
+}
+impl From<syn::Error> for Error {
+    fn from(err: syn::Error) -> Self {
+        Self::Source(err)
+    }
+}
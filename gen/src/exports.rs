@@ -0,0 +1,54 @@
+//! `#[dll]` blocks from the export table of a dll
+
+use crate::{
+    pe::{ExportTarget, Exports},
+    rust::{ExternBlock, Item, ItemKind},
+};
+use regex::Regex;
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportsOptions {
+    /// Only include exports matching this regex, ordinal-only exports are matched as `#<ordinal>`
+    pub filter: Option<Regex>,
+    /// The dll name for the `#[dll]` attribute, defaults to the name in the export table
+    pub dll_name: Option<String>,
+}
+
+/// Generate a block listing every export, with placeholder signatures
+pub fn extern_block(exports: &Exports, options: &ExportsOptions) -> ExternBlock {
+    let dll = options
+        .dll_name
+        .clone()
+        .unwrap_or_else(|| exports.dll_name.clone());
+    let mut block = ExternBlock::new(dll);
+
+    for export in &exports.exports {
+        let filter_name = match &export.name {
+            Some(name) => name.clone(),
+            None => format!("#{}", export.ordinal),
+        };
+        if let Some(filter) = &options.filter {
+            if !filter.is_match(&filter_name) {
+                continue;
+            }
+        }
+
+        let link = export.link();
+        let (kind, comment) = match &export.target {
+            ExportTarget::Function(_) => (ItemKind::UnknownFunction, None),
+            ExportTarget::Data(_) => (ItemKind::Static { ty: None }, None),
+            ExportTarget::Forwarder(target) => (
+                ItemKind::UnknownFunction,
+                Some(format!("Forwarded to {}", target)),
+            ),
+        };
+
+        block.items.push(Item {
+            link,
+            kind,
+            comment,
+        });
+    }
+
+    block
+}
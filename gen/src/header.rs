@@ -0,0 +1,995 @@
+//! `#[dll]` blocks from the function prototypes of a C header
+//!
+//! This isn't a C compiler: conditional compilation is ignored, only object-like
+//! `#define`s are expanded, and the declarations that can't be parsed are skipped.
+//! It handles the prototypes of typical dll headers, using primitive types,
+//! pointers, typedefs, function pointers and the common calling conventions.
+
+use crate::{
+    rust::{blocks_by_abi, snake_case, ExternBlock, Item, ItemKind, Link},
+    Error,
+};
+use regex::Regex;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct HeaderOptions {
+    /// Only include functions matching this regex
+    pub filter: Option<Regex>,
+    /// Macros to ignore, like the `FOO_API` export macros the header doesn't define
+    pub ignore: Vec<String>,
+    /// Object-like macros to define before parsing the header, as `(name, value)` pairs
+    pub defines: Vec<(String, String)>,
+}
+
+/// Generate blocks declaring the functions of a header, one for each ABI they use
+///
+/// Functions without a calling convention use the C one. Types without a Rust
+/// equivalent, like the structs of the header, are left for you to define,
+/// and listed in a comment above the block.
+pub fn extern_blocks(
+    header: &str,
+    dll: &str,
+    options: &HeaderOptions,
+) -> Result<Vec<ExternBlock>, Error> {
+    let tokens = preprocess(header, options)?;
+    let declarations = split_declarations(&tokens);
+
+    let mut parser = Parser {
+        ignore: options.ignore.iter().cloned().collect(),
+        typedefs: HashMap::new(),
+        defined_structs: HashSet::new(),
+        struct_names: HashMap::new(),
+    };
+    // Structs without a body are opaque, pointers to them are void pointers
+    for declaration in &declarations {
+        if let [Token::Ident(keyword), Token::Ident(tag), Token::Punct('{'), ..]
+        | [Token::Ident(_), Token::Ident(keyword), Token::Ident(tag), Token::Punct('{'), ..] =
+            &declaration[..]
+        {
+            if keyword == "struct" || keyword == "union" {
+                parser.defined_structs.insert(tag.clone());
+            }
+        }
+    }
+
+    let mut functions = Vec::new();
+    for declaration in &declarations {
+        if let Some(function) = parser.declaration(declaration) {
+            let selected =
+                !matches!(&options.filter, Some(filter) if !filter.is_match(&function.name));
+            if selected {
+                functions.push(function);
+            }
+        }
+    }
+
+    let mut unresolved = BTreeSet::new();
+    let mut items = Vec::new();
+    for function in functions {
+        let params = function
+            .params
+            .iter()
+            .enumerate()
+            .map(|(i, (name, ty))| {
+                let name = match name {
+                    Some(name) => snake_case(name),
+                    None => format!("arg{}", i),
+                };
+                (name, ty.render(&mut unresolved))
+            })
+            .collect();
+        let ret = match function.ret {
+            CType::Void => None,
+            ref ret => Some(ret.render(&mut unresolved)),
+        };
+
+        let item = Item {
+            link: Link::Name(function.name),
+            kind: ItemKind::Function {
+                params,
+                ret,
+                variadic: function.variadic,
+            },
+            comment: None,
+        };
+        items.push((function.abi, item));
+    }
+
+    let todo = (!unresolved.is_empty()).then(|| {
+        format!(
+            "TODO: define {}",
+            unresolved.into_iter().collect::<Vec<_>>().join(", ")
+        )
+    });
+    Ok(blocks_by_abi(dll, items, todo))
+}
+
+/// Generate the declarations of a header from a build script
+///
+/// The file is written to `OUT_DIR`, to be included with
+/// `include!(concat!(env!("OUT_DIR"), "/<out_file>"))`,
+/// and cargo is told to run the script again when the header changes.
+pub fn generate_in_out_dir(
+    header: impl AsRef<Path>,
+    dll: &str,
+    options: &HeaderOptions,
+    out_file: impl AsRef<Path>,
+) -> Result<PathBuf, Error> {
+    let header = header.as_ref();
+    let out_dir = env::var_os("OUT_DIR")
+        .ok_or_else(|| Error::Usage("`OUT_DIR` is only set for build scripts".to_string()))?;
+
+    let source = fs::read_to_string(header)?;
+    let blocks = extern_blocks(&source, dll, options)?;
+    let path = Path::new(&out_dir).join(out_file);
+    fs::write(&path, crate::rust::render_blocks(&blocks))?;
+
+    println!("cargo:rerun-if-changed={}", header.display());
+    Ok(path)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Punct(char),
+    Ellipsis,
+}
+
+/// Tokenize the header, expanding object-like macros and dropping the other directives
+fn preprocess(header: &str, options: &HeaderOptions) -> Result<Vec<Token>, Error> {
+    let source = strip_comments(header)
+        .replace("\\\r\n", " ")
+        .replace("\\\n", " ");
+
+    let mut macros = HashMap::new();
+    for (name, value) in &options.defines {
+        macros.insert(name.clone(), tokenize(value)?);
+    }
+
+    let mut tokens = Vec::new();
+    for line in source.lines() {
+        let line = line.trim();
+        if let Some(directive) = line.strip_prefix('#') {
+            let directive = directive.trim_start();
+            if let Some(define) = directive.strip_prefix("define") {
+                let define = define.trim_start();
+                let name_len = define
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(define.len());
+                let (name, value) = define.split_at(name_len);
+                // Function-like macros can't be expanded without a real preprocessor,
+                // and the defines of the options take precedence
+                let is_option = options.defines.iter().any(|(option, _)| option == name);
+                if !name.is_empty() && !value.starts_with('(') && !is_option {
+                    macros.insert(name.to_string(), tokenize(value)?);
+                }
+            }
+            continue;
+        }
+        tokens.extend(tokenize(line)?);
+    }
+
+    let mut expanded = Vec::with_capacity(tokens.len());
+    for token in tokens {
+        expand(token, &macros, &mut Vec::new(), &mut expanded);
+    }
+    Ok(expanded)
+}
+
+fn expand(
+    token: Token,
+    macros: &HashMap<String, Vec<Token>>,
+    expanding: &mut Vec<String>,
+    out: &mut Vec<Token>,
+) {
+    match &token {
+        Token::Ident(name) if !expanding.contains(name) => {
+            if let Some(value) = macros.get(name) {
+                expanding.push(name.clone());
+                for token in value {
+                    expand(token.clone(), macros, expanding, out);
+                }
+                expanding.pop();
+                return;
+            }
+        }
+        _ => {}
+    }
+    out.push(token);
+}
+
+fn strip_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = None;
+
+    while let Some(c) = chars.next() {
+        match (in_string, c) {
+            (Some(_), '\\') => {
+                out.push(c);
+                out.extend(chars.next());
+            }
+            (Some(quote), c) if c == quote => {
+                in_string = None;
+                out.push(c);
+            }
+            (Some(_), c) => out.push(c),
+            (None, '"') | (None, '\'') => {
+                in_string = Some(c);
+                out.push(c);
+            }
+            (None, '/') if chars.peek() == Some(&'/') => {
+                while matches!(chars.peek(), Some(&c) if c != '\n') {
+                    chars.next();
+                }
+            }
+            (None, '/') if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    // Keep the lines, so that directives stay on their own line
+                    if c == '\n' {
+                        out.push('\n');
+                    }
+                    previous = c;
+                }
+                out.push(' ');
+            }
+            (None, c) => out.push(c),
+        }
+    }
+    out
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let ident = &source[start..end];
+            // Wide and UTF-8 string prefixes
+            if matches!(ident, "L" | "u8" | "u" | "U") && matches!(chars.peek(), Some((_, '"'))) {
+                continue;
+            }
+            tokens.push(Token::Ident(ident.to_string()));
+        } else if c.is_ascii_digit() {
+            let mut end = start + 1;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '.') {
+                    break;
+                }
+                end = i + 1;
+                chars.next();
+            }
+            tokens.push(Token::Number(source[start..end].to_string()));
+        } else if c == '"' || c == '\'' {
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '\\')) => value.extend(chars.next().map(|(_, c)| c)),
+                    Some((_, end)) if end == c => break,
+                    Some((_, c)) => value.push(c),
+                    None => return Err(Error::Header("unterminated string".to_string())),
+                }
+            }
+            tokens.push(Token::Str(value));
+        } else if c == '.' && source[start..].starts_with("...") {
+            chars.next();
+            chars.next();
+            tokens.push(Token::Ellipsis);
+        } else {
+            tokens.push(Token::Punct(c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Split the tokens into top-level declarations, dropping `extern "C" {` blocks
+/// and the bodies of inline functions
+fn split_declarations(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let mut declarations = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0;
+    let mut extern_blocks = 0;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = &tokens[i];
+        i += 1;
+
+        match token {
+            Token::Ident(extern_) if depth == 0 && current.is_empty() && extern_ == "extern" => {
+                if let (Some(Token::Str(_)), Some(Token::Punct('{'))) =
+                    (tokens.get(i), tokens.get(i + 1))
+                {
+                    extern_blocks += 1;
+                    i += 2;
+                    continue;
+                }
+            }
+            Token::Punct('}') if depth == 0 && extern_blocks > 0 => {
+                extern_blocks -= 1;
+                current.clear();
+                continue;
+            }
+            Token::Punct('{') => {
+                // The body of a function definition isn't part of the declaration
+                if depth == 0 && current.last() == Some(&Token::Punct(')')) {
+                    current.clear();
+                    let mut body_depth = 1;
+                    while body_depth > 0 && i < tokens.len() {
+                        match tokens[i] {
+                            Token::Punct('{') => body_depth += 1,
+                            Token::Punct('}') => body_depth -= 1,
+                            _ => {}
+                        }
+                        i += 1;
+                    }
+                    continue;
+                }
+                depth += 1;
+            }
+            Token::Punct('}') => depth -= 1,
+            Token::Punct(';') if depth == 0 => {
+                if !current.is_empty() {
+                    declarations.push(std::mem::take(&mut current));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        current.push(token.clone());
+    }
+
+    declarations
+}
+
+/// A C type, resolved to Rust types where possible
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CType {
+    Void,
+    Rust(&'static str),
+    /// A struct or an unknown typedef
+    Named(String),
+    Ptr {
+        pointee: Box<CType>,
+        is_const: bool,
+    },
+    FnPtr {
+        abi: &'static str,
+        params: Vec<CType>,
+        ret: Box<CType>,
+        variadic: bool,
+    },
+}
+impl CType {
+    fn ptr(self, is_const: bool) -> Self {
+        Self::Ptr {
+            pointee: Box::new(self),
+            is_const,
+        }
+    }
+
+    fn render(&self, unresolved: &mut BTreeSet<String>) -> String {
+        match self {
+            Self::Void => "std::ffi::c_void".to_string(),
+            Self::Rust(ty) => ty.to_string(),
+            Self::Named(name) => {
+                unresolved.insert(name.clone());
+                name.clone()
+            }
+            Self::Ptr { pointee, is_const } => format!(
+                "*{} {}",
+                if *is_const { "const" } else { "mut" },
+                pointee.render(unresolved)
+            ),
+            Self::FnPtr {
+                abi,
+                params,
+                ret,
+                variadic,
+            } => {
+                let mut args: Vec<_> = params.iter().map(|ty| ty.render(unresolved)).collect();
+                if *variadic {
+                    args.push("...".to_string());
+                }
+                let ret = match **ret {
+                    Self::Void => String::new(),
+                    ref ret => format!(" -> {}", ret.render(unresolved)),
+                };
+                format!(
+                    "Option<unsafe extern {:?} fn({}){}>",
+                    abi,
+                    args.join(", "),
+                    ret
+                )
+            }
+        }
+    }
+}
+
+/// Parameters and their names, if they have one
+type Params = Vec<(Option<String>, CType)>;
+
+struct Function {
+    name: String,
+    abi: &'static str,
+    params: Params,
+    ret: CType,
+    variadic: bool,
+}
+
+/// What a declarator declares
+enum Declared {
+    Value(Option<String>, CType),
+    Function {
+        name: Option<String>,
+        abi: &'static str,
+        params: Params,
+        ret: CType,
+        variadic: bool,
+    },
+}
+
+struct Parser {
+    ignore: HashSet<String>,
+    /// Typedefs and whether they are `const`
+    typedefs: HashMap<String, (CType, bool)>,
+    defined_structs: HashSet<String>,
+    /// The typedef names of structs, like `FOO` for `typedef struct _FOO {} FOO`
+    struct_names: HashMap<String, String>,
+}
+
+impl Parser {
+    /// Parse a declaration, returning it if it's a function, typedefs are recorded
+    fn declaration(&mut self, declaration: &[Token]) -> Option<Function> {
+        let tokens = self.clean(declaration);
+        let mut cursor = Cursor {
+            tokens: &tokens,
+            position: 0,
+        };
+        let is_typedef = cursor.eat_ident("typedef");
+
+        let (mut base, base_const) = self.base_type(&mut cursor)?;
+        let mut function = None;
+        loop {
+            let declared = self.declarator(&mut cursor, base.clone(), base_const)?;
+            match declared {
+                Declared::Value(Some(name), ty) if is_typedef => {
+                    // Structs are named after their first typedef, which also names
+                    // anonymous structs
+                    let ty = match (ty, &base) {
+                        (CType::Named(tag), CType::Named(base_tag)) if tag == *base_tag => {
+                            if !tag.is_empty() {
+                                self.struct_names.insert(tag, name.clone());
+                            }
+                            base = CType::Named(name.clone());
+                            base.clone()
+                        }
+                        (ty, _) => ty,
+                    };
+                    let is_const = base_const && !matches!(ty, CType::Ptr { .. });
+                    self.typedefs.insert(name, (ty, is_const));
+                }
+                Declared::Function {
+                    name: Some(name),
+                    abi,
+                    params,
+                    ret,
+                    variadic,
+                } if is_typedef => {
+                    let params = params.into_iter().map(|(_, ty)| ty).collect();
+                    let ty = CType::FnPtr {
+                        abi,
+                        params,
+                        ret: Box::new(ret),
+                        variadic,
+                    };
+                    self.typedefs.insert(name, (ty, false));
+                }
+                Declared::Function {
+                    name: Some(name),
+                    abi,
+                    params,
+                    ret,
+                    variadic,
+                } => {
+                    function = Some(Function {
+                        name,
+                        abi,
+                        params,
+                        ret,
+                        variadic,
+                    })
+                }
+                _ => {}
+            }
+            if !cursor.eat_punct(',') {
+                break;
+            }
+        }
+
+        if cursor.is_empty() {
+            function
+        } else {
+            None
+        }
+    }
+
+    /// Drop the tokens that don't change the signature, like SAL annotations and `__declspec`
+    fn clean(&self, tokens: &[Token]) -> Vec<Token> {
+        let mut cleaned = Vec::with_capacity(tokens.len());
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            i += 1;
+            let ident = match token {
+                Token::Ident(ident) => ident.as_str(),
+                _ => {
+                    cleaned.push(token.clone());
+                    continue;
+                }
+            };
+            if !(self.ignore.contains(ident) || is_ignored(ident)) {
+                cleaned.push(token.clone());
+                continue;
+            }
+            // Skip the arguments of annotations like `_Out_writes_(size)`
+            if tokens.get(i) == Some(&Token::Punct('(')) {
+                let mut depth = 0;
+                while i < tokens.len() {
+                    match tokens[i] {
+                        Token::Punct('(') => depth += 1,
+                        Token::Punct(')') => depth -= 1,
+                        _ => {}
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+        }
+        cleaned
+    }
+
+    /// The type specifiers at the start of a declaration, and whether they are `const`
+    fn base_type(&self, cursor: &mut Cursor<'_>) -> Option<(CType, bool)> {
+        let mut is_const = false;
+        let mut unsigned = false;
+        let mut signed = false;
+        let mut longs = 0;
+        let mut primitive = None;
+        let mut ty = None;
+
+        while let Some(Token::Ident(ident)) = cursor.peek() {
+            match ident.as_str() {
+                "const" => is_const = true,
+                "unsigned" => unsigned = true,
+                "signed" => signed = true,
+                "long" => longs += 1,
+                // `int` is optional after `short` and `long`
+                "int" if primitive.is_some() => {}
+                "short" | "int" | "char" | "float" | "double" | "void" | "_Bool" | "bool"
+                | "__int8" | "__int16" | "__int32" | "__int64" => {
+                    if primitive.replace(ident.clone()).is_some() {
+                        return None;
+                    }
+                }
+                "struct" | "union" | "enum" => {
+                    let keyword = ident.clone();
+                    cursor.next();
+                    let tag = match cursor.peek() {
+                        Some(Token::Ident(tag)) => {
+                            let tag = tag.clone();
+                            cursor.next();
+                            tag
+                        }
+                        _ => String::new(),
+                    };
+                    if cursor.peek() == Some(&Token::Punct('{')) {
+                        cursor.skip_group('{', '}')?;
+                    }
+                    ty = Some(match keyword.as_str() {
+                        // C enums are ints
+                        "enum" => CType::Rust("i32"),
+                        _ => match self.struct_names.get(&tag) {
+                            Some(name) => CType::Named(name.clone()),
+                            None if tag.is_empty() || self.defined_structs.contains(&tag) => {
+                                CType::Named(tag)
+                            }
+                            None => CType::Void,
+                        },
+                    });
+                    continue;
+                }
+                _ if ty.is_none()
+                    && primitive.is_none()
+                    && !unsigned
+                    && !signed
+                    && longs == 0
+                    && abi(ident).is_none() =>
+                {
+                    let (typedef, typedef_const) = self.typedef(ident);
+                    ty = Some(typedef);
+                    is_const |= typedef_const;
+                }
+                _ => break,
+            }
+            cursor.next();
+        }
+
+        if let Some(ty) = ty {
+            return Some((ty, is_const));
+        }
+        let ty = match (primitive.as_deref(), longs, unsigned) {
+            (Some("void"), ..) => CType::Void,
+            (Some("char"), _, false) | (Some("__int8"), _, false) => CType::Rust("i8"),
+            (Some("char"), _, true) | (Some("__int8"), _, true) => CType::Rust("u8"),
+            (Some("short"), _, false) | (Some("__int16"), _, false) => CType::Rust("i16"),
+            (Some("short"), _, true) | (Some("__int16"), _, true) => CType::Rust("u16"),
+            (Some("float"), ..) => CType::Rust("f32"),
+            (Some("double"), ..) => CType::Rust("f64"),
+            (Some("_Bool"), ..) | (Some("bool"), ..) => CType::Rust("bool"),
+            (Some("__int64"), _, false) => CType::Rust("i64"),
+            (Some("__int64"), _, true) => CType::Rust("u64"),
+            // `long` is 32 bits on Windows
+            (_, 2, false) => CType::Rust("i64"),
+            (_, 2, true) => CType::Rust("u64"),
+            (Some("int"), _, false) | (Some("__int32"), _, false) => CType::Rust("i32"),
+            (None, _, false) if signed || longs > 0 => CType::Rust("i32"),
+            (Some("int"), _, true) | (Some("__int32"), _, true) | (None, _, true) => {
+                CType::Rust("u32")
+            }
+            _ => return None,
+        };
+        Some((ty, is_const))
+    }
+
+    fn typedef(&self, name: &str) -> (CType, bool) {
+        match self.typedefs.get(name) {
+            Some(typedef) => typedef.clone(),
+            None => (builtin_type(name), false),
+        }
+    }
+
+    /// Parse a declarator, `abstract` ones without a name included
+    fn declarator(
+        &self,
+        cursor: &mut Cursor<'_>,
+        base: CType,
+        base_const: bool,
+    ) -> Option<Declared> {
+        let mut ty = base;
+        let mut is_const = base_const;
+        let mut call_abi = None;
+
+        loop {
+            match cursor.peek() {
+                Some(Token::Punct('*')) => {
+                    cursor.next();
+                    ty = ty.ptr(is_const);
+                    is_const = false;
+                }
+                Some(Token::Ident(ident)) if ident == "const" => {
+                    cursor.next();
+                    is_const = true;
+                }
+                Some(Token::Ident(ident)) if abi(ident).is_some() => {
+                    call_abi = abi(ident);
+                    cursor.next();
+                }
+                _ => break,
+            }
+        }
+
+        // A function pointer, like `void (WINAPI *callback)(int)`
+        if cursor.peek() == Some(&Token::Punct('(')) && cursor.is_fn_ptr() {
+            cursor.next();
+            let mut fn_abi = None;
+            let mut pointers = 0;
+            let mut name = None;
+            while let Some(token) = cursor.next() {
+                match token {
+                    Token::Punct('*') => pointers += 1,
+                    Token::Punct(')') => break,
+                    Token::Ident(ident) if abi(ident).is_some() => fn_abi = abi(ident),
+                    Token::Ident(ident) if ident == "const" => {}
+                    Token::Ident(ident) => name = Some(ident.clone()),
+                    _ => return None,
+                }
+            }
+            let (params, variadic) = self.params(cursor)?;
+            let mut ty = CType::FnPtr {
+                abi: fn_abi.unwrap_or("C"),
+                params: params.into_iter().map(|(_, ty)| ty).collect(),
+                ret: Box::new(ty),
+                variadic,
+            };
+            for _ in 1..pointers {
+                ty = ty.ptr(false);
+            }
+            return Some(Declared::Value(name, ty));
+        }
+
+        let name = match cursor.peek() {
+            Some(Token::Ident(ident)) => {
+                let ident = ident.clone();
+                cursor.next();
+                Some(ident)
+            }
+            _ => None,
+        };
+
+        // Arrays are passed as pointers
+        while cursor.peek() == Some(&Token::Punct('[')) {
+            cursor.skip_group('[', ']')?;
+            ty = ty.ptr(is_const);
+            is_const = false;
+        }
+
+        if cursor.peek() == Some(&Token::Punct('(')) {
+            let (params, variadic) = self.params(cursor)?;
+            return Some(Declared::Function {
+                name,
+                abi: call_abi.unwrap_or("C"),
+                params,
+                ret: ty,
+                variadic,
+            });
+        }
+
+        Some(Declared::Value(name, ty))
+    }
+
+    /// A parenthesized parameter list
+    fn params(&self, cursor: &mut Cursor<'_>) -> Option<(Params, bool)> {
+        let group = cursor.skip_group('(', ')')?;
+        let inner = &group[1..group.len() - 1];
+        if inner.is_empty() || inner == [Token::Ident("void".to_string())] {
+            return Some((Vec::new(), false));
+        }
+
+        let mut params = Vec::new();
+        let mut variadic = false;
+        for param in split_top_level(inner) {
+            if param == [Token::Ellipsis] {
+                variadic = true;
+                continue;
+            }
+            let mut cursor = Cursor {
+                tokens: param,
+                position: 0,
+            };
+            let (base, base_const) = self.base_type(&mut cursor)?;
+            let (name, ty) = match self.declarator(&mut cursor, base, base_const)? {
+                Declared::Value(name, ty) => (name, ty),
+                // A function parameter is a function pointer
+                Declared::Function {
+                    name,
+                    abi,
+                    params,
+                    ret,
+                    variadic,
+                } => (
+                    name,
+                    CType::FnPtr {
+                        abi,
+                        params: params.into_iter().map(|(_, ty)| ty).collect(),
+                        ret: Box::new(ret),
+                        variadic,
+                    },
+                ),
+            };
+            if !cursor.is_empty() {
+                return None;
+            }
+            params.push((name, ty));
+        }
+        Some((params, variadic))
+    }
+}
+
+struct Cursor<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.position)
+    }
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.peek()?;
+        self.position += 1;
+        Some(token)
+    }
+    fn is_empty(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+    fn eat_ident(&mut self, expected: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(ident)) if ident == expected => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+    fn eat_punct(&mut self, expected: char) -> bool {
+        let eaten = self.peek() == Some(&Token::Punct(expected));
+        if eaten {
+            self.position += 1;
+        }
+        eaten
+    }
+    /// Whether the parenthesis at the cursor starts a function pointer declarator
+    fn is_fn_ptr(&self) -> bool {
+        self.tokens[self.position + 1..]
+            .iter()
+            .find(|token| !matches!(token, Token::Ident(ident) if abi(ident).is_some()))
+            == Some(&Token::Punct('*'))
+    }
+    /// Skip a group of balanced delimiters, returning its tokens
+    fn skip_group(&mut self, open: char, close: char) -> Option<&'a [Token]> {
+        let start = self.position;
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token {
+                Token::Punct(c) if *c == open => depth += 1,
+                Token::Punct(c) if *c == close => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(&self.tokens[start..self.position]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+fn split_top_level(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Punct('(') | Token::Punct('[') => depth += 1,
+            Token::Punct(')') | Token::Punct(']') => depth -= 1,
+            Token::Punct(',') if depth == 0 => {
+                parts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&tokens[start..]);
+    parts
+}
+
+/// The Rust ABI of a calling convention
+fn abi(ident: &str) -> Option<&'static str> {
+    match ident {
+        "__stdcall" | "_stdcall" | "WINAPI" | "CALLBACK" | "APIENTRY" | "APIPRIVATE" | "PASCAL"
+        | "NTAPI" | "STDMETHODCALLTYPE" | "STDAPICALLTYPE" => Some("system"),
+        "__cdecl" | "_cdecl" | "WINAPIV" | "CDECL" => Some("C"),
+        _ => None,
+    }
+}
+
+/// Keywords and macros that don't change the signature
+fn is_ignored(ident: &str) -> bool {
+    const IGNORED: &[&str] = &[
+        "extern",
+        "static",
+        "inline",
+        "__inline",
+        "__forceinline",
+        "volatile",
+        "register",
+        "restrict",
+        "__restrict",
+        "__ptr32",
+        "__ptr64",
+        "__unaligned",
+        "__declspec",
+        "__attribute__",
+        "__extension__",
+        "DECLSPEC_IMPORT",
+        "DECLSPEC_NORETURN",
+        "WINBASEAPI",
+        "WINUSERAPI",
+        "WINGDIAPI",
+        "WINADVAPI",
+        "NTSYSAPI",
+        "NTSYSCALLAPI",
+        "CONST",
+        "IN",
+        "OUT",
+        "OPTIONAL",
+    ];
+    const SAL_PREFIXES: &[&str] = &[
+        "_In",
+        "_Out",
+        "_Inout",
+        "_Ret",
+        "_Check",
+        "_Must",
+        "_Success",
+        "_Reserved",
+        "_Frees",
+        "_Pre",
+        "_Post",
+        "_Deref",
+        "_When",
+        "_Field",
+        "_Null",
+        "_Printf",
+        "_Use_decl",
+    ];
+    IGNORED.contains(&ident) || SAL_PREFIXES.iter().any(|prefix| ident.starts_with(prefix))
+}
+
+/// The types of `windows.h` and `stdint.h`, for headers that don't define them
+fn builtin_type(name: &str) -> CType {
+    let void_ptr = || CType::Void.ptr(false);
+    match name {
+        "BOOL" | "INT" | "LONG" | "HRESULT" | "NTSTATUS" | "INT32" | "LONG32" | "int32_t" => {
+            CType::Rust("i32")
+        }
+        "UINT" | "DWORD" | "ULONG" | "UINT32" | "ULONG32" | "DWORD32" | "COLORREF" | "uint32_t" => {
+            CType::Rust("u32")
+        }
+        "BYTE" | "UCHAR" | "BOOLEAN" | "UINT8" | "uint8_t" => CType::Rust("u8"),
+        "CHAR" | "INT8" | "int8_t" => CType::Rust("i8"),
+        "WORD" | "USHORT" | "WCHAR" | "UINT16" | "ATOM" | "LANGID" | "wchar_t" | "uint16_t"
+        | "char16_t" => CType::Rust("u16"),
+        "SHORT" | "INT16" | "int16_t" => CType::Rust("i16"),
+        "LONGLONG" | "INT64" | "LONG64" | "int64_t" => CType::Rust("i64"),
+        "ULONGLONG" | "UINT64" | "ULONG64" | "DWORD64" | "QWORD" | "uint64_t" => CType::Rust("u64"),
+        "FLOAT" => CType::Rust("f32"),
+        "INT_PTR" | "LONG_PTR" | "SSIZE_T" | "LPARAM" | "LRESULT" | "intptr_t" | "ptrdiff_t"
+        | "ssize_t" => CType::Rust("isize"),
+        "UINT_PTR" | "ULONG_PTR" | "DWORD_PTR" | "SIZE_T" | "WPARAM" | "size_t" | "uintptr_t" => {
+            CType::Rust("usize")
+        }
+        "VOID" => CType::Void,
+        "PVOID" | "LPVOID" | "HANDLE" | "HWND" | "HMODULE" | "HINSTANCE" | "HKEY" | "HDC"
+        | "HICON" | "HMENU" | "HBRUSH" | "HFONT" | "HBITMAP" | "HCURSOR" | "HGLOBAL" | "HLOCAL"
+        | "HMONITOR" | "HRGN" | "HPEN" | "HPALETTE" | "HGDIOBJ" | "HRSRC" | "HTHEME"
+        | "FARPROC" => void_ptr(),
+        "LPCVOID" | "PCVOID" => CType::Void.ptr(true),
+        "LPSTR" | "PSTR" => CType::Rust("i8").ptr(false),
+        "LPCSTR" | "PCSTR" => CType::Rust("i8").ptr(true),
+        "LPWSTR" | "PWSTR" => CType::Rust("u16").ptr(false),
+        "LPCWSTR" | "PCWSTR" => CType::Rust("u16").ptr(true),
+        "LPBYTE" | "PBYTE" => CType::Rust("u8").ptr(false),
+        "LPDWORD" | "PDWORD" | "PULONG" | "PUINT" => CType::Rust("u32").ptr(false),
+        "LPBOOL" | "PBOOL" | "LPLONG" | "PLONG" | "LPINT" | "PINT" => CType::Rust("i32").ptr(false),
+        "PHANDLE" | "LPHANDLE" => void_ptr().ptr(false),
+        _ => CType::Named(name.to_string()),
+    }
+}
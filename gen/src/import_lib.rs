@@ -0,0 +1,413 @@
+//! Module-definition files and import libraries for the imports of `#[dll]` blocks
+//!
+//! The import libraries use the short import format of the PE/COFF specification,
+//! like the ones written by `lib.exe` and `llvm-dlltool`, so they can be linked by
+//! `link.exe`, `lld-link` and the MinGW linkers.
+
+use crate::source::{DllImports, Import};
+
+/// The target architecture of an import library
+///
+/// 32-bit x86 isn't supported, since its symbols are decorated with the calling
+/// convention and the size of the arguments, which `#[dll]` blocks don't describe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Machine {
+    X64,
+    Arm64,
+}
+
+impl Machine {
+    fn id(self) -> u16 {
+        match self {
+            Self::X64 => 0x8664,
+            Self::Arm64 => 0xaa64,
+        }
+    }
+
+    /// `IMAGE_REL_*_ADDR32NB`
+    fn addr32nb(self) -> u16 {
+        match self {
+            Self::X64 => 0x0003,
+            Self::Arm64 => 0x0002,
+        }
+    }
+}
+
+/// The file name of the dll, `LoadLibrary` appends `.dll` to names without an extension
+pub fn dll_file_name(dll: &str) -> String {
+    if dll.contains('.') {
+        dll.to_string()
+    } else {
+        format!("{}.dll", dll)
+    }
+}
+
+/// A module-definition file exporting the imports, to build an import library with
+/// `lib.exe /def` or `dlltool`
+pub fn def_file(dll: &DllImports) -> String {
+    let mut def = format!("LIBRARY {}\nEXPORTS\n", dll_file_name(&dll.dll));
+    for import in &dll.imports {
+        def.push_str("    ");
+        def.push_str(&import.name);
+        if let Some(ordinal) = import.ordinal {
+            def.push_str(&format!(" @{} NONAME", ordinal));
+        }
+        if import.data {
+            def.push_str(" DATA");
+        }
+        def.push('\n');
+    }
+    def
+}
+
+const IMPORT_OBJECT_CODE: u16 = 0;
+const IMPORT_OBJECT_DATA: u16 = 1;
+const IMPORT_OBJECT_ORDINAL: u16 = 0;
+const IMPORT_OBJECT_NAME: u16 = 1;
+
+const SYM_CLASS_EXTERNAL: u8 = 2;
+const SYM_CLASS_STATIC: u8 = 3;
+const SYM_CLASS_SECTION: u8 = 0x68;
+
+/// `IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ | IMAGE_SCN_MEM_WRITE`
+const DATA_SECTION: u32 = 0xc000_0040;
+const ALIGN_2BYTES: u32 = 0x0020_0000;
+const ALIGN_4BYTES: u32 = 0x0030_0000;
+const ALIGN_8BYTES: u32 = 0x0040_0000;
+
+/// An import library for the imports, in the archive format of `lib.exe`
+pub fn import_library(dll: &DllImports, machine: Machine) -> Vec<u8> {
+    let dll_name = dll_file_name(&dll.dll);
+    let stem = match dll_name.rfind('.') {
+        Some(dot) => &dll_name[..dot],
+        None => &dll_name,
+    };
+    let descriptor = format!("__IMPORT_DESCRIPTOR_{}", stem);
+    let null_descriptor = "__NULL_IMPORT_DESCRIPTOR".to_string();
+    let null_thunk = format!("\x7f{}_NULL_THUNK_DATA", stem);
+
+    let mut members = vec![
+        (
+            import_descriptor(
+                machine,
+                &dll_name,
+                &descriptor,
+                &null_descriptor,
+                &null_thunk,
+            ),
+            vec![descriptor.clone()],
+        ),
+        (
+            null_import_descriptor(machine, &null_descriptor),
+            vec![null_descriptor.clone()],
+        ),
+        (null_thunk_data(machine, &null_thunk), vec![null_thunk]),
+    ];
+    for import in &dll.imports {
+        let mut symbols = vec![format!("__imp_{}", import.name)];
+        if !import.data {
+            symbols.push(import.name.clone());
+        }
+        members.push((short_import(machine, &dll_name, import), symbols));
+    }
+
+    archive(&dll_name, &members)
+}
+
+/// The import directory entry of the dll, referencing the lookup and address tables
+/// its imports are added to
+fn import_descriptor(
+    machine: Machine,
+    dll_name: &str,
+    descriptor: &str,
+    null_descriptor: &str,
+    null_thunk: &str,
+) -> Vec<u8> {
+    let mut object = Coff::new(machine);
+    let directory = object.section(".idata$2", ALIGN_4BYTES, vec![0; 20]);
+    let mut name = dll_name.as_bytes().to_vec();
+    name.push(0);
+    let name = object.section(".idata$6", ALIGN_2BYTES, name);
+
+    object.symbol(descriptor, directory, SYM_CLASS_EXTERNAL);
+    object.symbol(".idata$2", directory, SYM_CLASS_SECTION);
+    let name_symbol = object.symbol(".idata$6", name, SYM_CLASS_STATIC);
+    let lookup_table = object.symbol(".idata$4", 0, SYM_CLASS_SECTION);
+    let address_table = object.symbol(".idata$5", 0, SYM_CLASS_SECTION);
+    object.symbol(null_descriptor, 0, SYM_CLASS_EXTERNAL);
+    object.symbol(null_thunk, 0, SYM_CLASS_EXTERNAL);
+
+    // The Name, OriginalFirstThunk and FirstThunk fields of the directory entry
+    object.relocation(directory, 12, name_symbol);
+    object.relocation(directory, 0, lookup_table);
+    object.relocation(directory, 16, address_table);
+    object.finish()
+}
+
+/// The zeroed entry terminating the import directory
+fn null_import_descriptor(machine: Machine, null_descriptor: &str) -> Vec<u8> {
+    let mut object = Coff::new(machine);
+    let section = object.section(".idata$3", ALIGN_4BYTES, vec![0; 20]);
+    object.symbol(null_descriptor, section, SYM_CLASS_EXTERNAL);
+    object.finish()
+}
+
+/// The zeroed entries terminating the lookup and address tables of the dll
+fn null_thunk_data(machine: Machine, null_thunk: &str) -> Vec<u8> {
+    let mut object = Coff::new(machine);
+    let address_table = object.section(".idata$5", ALIGN_8BYTES, vec![0; 8]);
+    object.section(".idata$4", ALIGN_8BYTES, vec![0; 8]);
+    object.symbol(null_thunk, address_table, SYM_CLASS_EXTERNAL);
+    object.finish()
+}
+
+/// An import object, from which the linker generates the thunk and table entries
+fn short_import(machine: Machine, dll_name: &str, import: &Import) -> Vec<u8> {
+    let (hint, name_type) = match import.ordinal {
+        Some(ordinal) => (ordinal, IMPORT_OBJECT_ORDINAL),
+        None => (0, IMPORT_OBJECT_NAME),
+    };
+    let kind = if import.data {
+        IMPORT_OBJECT_DATA
+    } else {
+        IMPORT_OBJECT_CODE
+    };
+    let size = import.name.len() + 1 + dll_name.len() + 1;
+
+    let mut object = Vec::with_capacity(20 + size);
+    put_u16(&mut object, 0);
+    put_u16(&mut object, 0xffff);
+    put_u16(&mut object, 0);
+    put_u16(&mut object, machine.id());
+    put_u32(&mut object, 0);
+    put_u32(&mut object, size as u32);
+    put_u16(&mut object, hint);
+    put_u16(&mut object, kind | name_type << 2);
+    object.extend_from_slice(import.name.as_bytes());
+    object.push(0);
+    object.extend_from_slice(dll_name.as_bytes());
+    object.push(0);
+    object
+}
+
+/// A COFF object file, sections are laid out after the headers, each followed by
+/// its relocations
+struct Coff {
+    machine: Machine,
+    sections: Vec<Section>,
+    symbols: Vec<(String, u16, u8)>,
+}
+
+struct Section {
+    name: &'static str,
+    characteristics: u32,
+    data: Vec<u8>,
+    relocations: Vec<(u32, u32)>,
+}
+
+impl Coff {
+    fn new(machine: Machine) -> Self {
+        Self {
+            machine,
+            sections: Vec::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Add a section, returns its 1-based number
+    fn section(&mut self, name: &'static str, alignment: u32, data: Vec<u8>) -> u16 {
+        self.sections.push(Section {
+            name,
+            characteristics: DATA_SECTION | alignment,
+            data,
+            relocations: Vec::new(),
+        });
+        self.sections.len() as u16
+    }
+
+    /// Add a symbol at the start of a section, or undefined for section 0,
+    /// returns its index
+    fn symbol(&mut self, name: &str, section: u16, class: u8) -> u32 {
+        self.symbols.push((name.to_string(), section, class));
+        self.symbols.len() as u32 - 1
+    }
+
+    fn relocation(&mut self, section: u16, offset: u32, symbol: u32) {
+        self.sections[section as usize - 1]
+            .relocations
+            .push((offset, symbol));
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let mut object = Vec::new();
+        let headers_size = 20 + 40 * self.sections.len();
+        let symbol_table = headers_size
+            + self
+                .sections
+                .iter()
+                .map(|section| section.data.len() + 10 * section.relocations.len())
+                .sum::<usize>();
+
+        put_u16(&mut object, self.machine.id());
+        put_u16(&mut object, self.sections.len() as u16);
+        put_u32(&mut object, 0);
+        put_u32(&mut object, symbol_table as u32);
+        put_u32(&mut object, self.symbols.len() as u32);
+        put_u16(&mut object, 0);
+        put_u16(&mut object, 0);
+
+        let mut offset = headers_size;
+        for section in &self.sections {
+            let relocations = offset + section.data.len();
+            let mut name = [0; 8];
+            name[..section.name.len()].copy_from_slice(section.name.as_bytes());
+            object.extend_from_slice(&name);
+            put_u32(&mut object, 0);
+            put_u32(&mut object, 0);
+            put_u32(&mut object, section.data.len() as u32);
+            put_u32(&mut object, offset as u32);
+            put_u32(
+                &mut object,
+                if section.relocations.is_empty() {
+                    0
+                } else {
+                    relocations as u32
+                },
+            );
+            put_u32(&mut object, 0);
+            put_u16(&mut object, section.relocations.len() as u16);
+            put_u16(&mut object, 0);
+            put_u32(&mut object, section.characteristics);
+            offset = relocations + 10 * section.relocations.len();
+        }
+        for section in &self.sections {
+            object.extend_from_slice(&section.data);
+            for (offset, symbol) in &section.relocations {
+                put_u32(&mut object, *offset);
+                put_u32(&mut object, *symbol);
+                put_u16(&mut object, self.machine.addr32nb());
+            }
+        }
+
+        // Names longer than 8 bytes are stored in the string table following the symbols
+        let mut strings = Vec::new();
+        for (name, section, class) in &self.symbols {
+            if name.len() <= 8 {
+                let mut short_name = [0; 8];
+                short_name[..name.len()].copy_from_slice(name.as_bytes());
+                object.extend_from_slice(&short_name);
+            } else {
+                put_u32(&mut object, 0);
+                put_u32(&mut object, 4 + strings.len() as u32);
+                strings.extend_from_slice(name.as_bytes());
+                strings.push(0);
+            }
+            put_u32(&mut object, 0);
+            put_u16(&mut object, *section);
+            put_u16(&mut object, 0);
+            object.push(*class);
+            object.push(0);
+        }
+        put_u32(&mut object, 4 + strings.len() as u32);
+        object.extend_from_slice(&strings);
+        object
+    }
+}
+
+/// An archive with the two linker members of `lib.exe` indexing the symbols of its members
+fn archive(member_name: &str, members: &[(Vec<u8>, Vec<String>)]) -> Vec<u8> {
+    // Every member is named after the dll, names longer than 15 bytes go in the
+    // long names member and are referenced by their offset
+    let (name, long_names) = if member_name.len() < 16 {
+        (format!("{}/", member_name), None)
+    } else {
+        let mut long_names = member_name.as_bytes().to_vec();
+        long_names.push(0);
+        ("/0".to_string(), Some(long_names))
+    };
+
+    let symbols = members
+        .iter()
+        .enumerate()
+        .flat_map(|(index, (_, symbols))| symbols.iter().map(move |symbol| (symbol, index)))
+        .collect::<Vec<_>>();
+    let mut sorted = symbols.clone();
+    sorted.sort();
+    let names_size: usize = symbols.iter().map(|(symbol, _)| symbol.len() + 1).sum();
+
+    let first_size = 4 + 4 * symbols.len() + names_size;
+    let second_size = 4 + 4 * members.len() + 4 + 2 * symbols.len() + names_size;
+    let mut offset = 8 + 60 + padded(first_size) + 60 + padded(second_size);
+    if let Some(long_names) = &long_names {
+        offset += 60 + padded(long_names.len());
+    }
+    let mut offsets = Vec::new();
+    for (data, _) in members {
+        offsets.push(offset as u32);
+        offset += 60 + padded(data.len());
+    }
+
+    let mut first = Vec::with_capacity(first_size);
+    first.extend_from_slice(&(symbols.len() as u32).to_be_bytes());
+    for (_, member) in &symbols {
+        first.extend_from_slice(&offsets[*member].to_be_bytes());
+    }
+    for (symbol, _) in &symbols {
+        first.extend_from_slice(symbol.as_bytes());
+        first.push(0);
+    }
+
+    let mut second = Vec::with_capacity(second_size);
+    put_u32(&mut second, members.len() as u32);
+    for offset in &offsets {
+        put_u32(&mut second, *offset);
+    }
+    put_u32(&mut second, sorted.len() as u32);
+    for (_, member) in &sorted {
+        put_u16(&mut second, *member as u16 + 1);
+    }
+    for (symbol, _) in &sorted {
+        second.extend_from_slice(symbol.as_bytes());
+        second.push(0);
+    }
+
+    let mut archive = b"!<arch>\n".to_vec();
+    add_member(&mut archive, "/", &first);
+    add_member(&mut archive, "/", &second);
+    if let Some(long_names) = &long_names {
+        add_member(&mut archive, "//", long_names);
+    }
+    for (data, _) in members {
+        add_member(&mut archive, &name, data);
+    }
+    archive
+}
+
+fn add_member(archive: &mut Vec<u8>, name: &str, data: &[u8]) {
+    let header = format!(
+        "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+        name,
+        0,
+        0,
+        0,
+        0,
+        data.len()
+    );
+    archive.extend_from_slice(header.as_bytes());
+    archive.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        archive.push(b'\n');
+    }
+}
+
+fn padded(size: usize) -> usize {
+    size + size % 2
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
@@ -0,0 +1,17 @@
+//! Generate [`windows-dll`](https://docs.rs/windows-dll) declarations from dlls and
+//! other metadata. The generators don't depend on Windows and run on any platform.
+
+pub mod apiset;
+pub mod audit;
+mod error;
+pub mod exports;
+pub mod header;
+pub mod import_lib;
+pub mod metadata;
+pub mod pe;
+pub mod resolve;
+pub mod rust;
+pub mod source;
+pub mod winmd;
+
+pub use error::Error;
@@ -0,0 +1,330 @@
+use regex::Regex;
+use std::{env, fs, process};
+use windows_dll_gen::{
+    apiset::ApiSetSchema,
+    audit,
+    exports::{self, ExportsOptions},
+    header::{self, HeaderOptions},
+    import_lib::{self, Machine},
+    metadata::Metadata,
+    pe::PeFile,
+    resolve::{self, Resolver},
+    rust,
+    source::{self, DllImports},
+    winmd::{self, Backend, WinmdOptions},
+    Error,
+};
+
+const USAGE: &str = "\
+Generate windows-dll declarations
+
+USAGE:
+    windows-dll-gen <COMMAND> [OPTIONS]
+
+COMMANDS:
+    exports <DLL>                   Generate a #[dll] extern block from the export table
+                                    of a dll
+    winmd <WINMD> --dll <DLL> [FUNCTION...]
+                                    Generate typed #[dll] extern blocks for the functions
+                                    of a dll described in a .winmd file, all of them if
+                                    none are listed
+    header <HEADER> --dll <DLL>     Generate #[dll] extern blocks for the function
+                                    prototypes of a C header
+    def <SOURCE>... [--dll <DLL>]   Generate a module-definition file for the imports of
+                                    the #[dll] blocks of Rust files or directories
+    implib <SOURCE>... [--dll <DLL>] -o <LIB>
+                                    Generate an import library for the imports of the
+                                    #[dll] blocks of Rust files or directories
+    verify <SOURCE>... --dlls <DIR> [--apiset <SCHEMA>]
+                                    Check that the imports of the #[dll] blocks of Rust
+                                    files or directories are exported by the dlls of a
+                                    directory, following forwarded exports and API sets
+    apiset <SCHEMA> [NAME...]       List the dlls implementing API sets, all of them if
+                                    none are listed
+    audit <BINARY>                  List the dlls and functions a binary built with the
+                                    `audit` feature of windows-dll loads at runtime
+
+OPTIONS:
+    --filter <REGEX>       Only include exports or functions matching the regex, ordinal-only
+                           exports are matched as `#<ordinal>`
+    --name <NAME>          The dll name to use in the #[dll] attribute
+    --dll <DLL>            The dll to generate from the .winmd file, the dll of the
+                           functions of a header, or the dll to generate a
+                           module-definition file or import library for, when the
+                           sources declare several
+    --backend <BACKEND>    The crate of the types generated from a .winmd file,
+                           `winapi` (default) or `windows`
+    --ignore <MACRO>       A macro to ignore in the header, like an export macro it
+                           doesn't define, can be repeated
+    --define <NAME[=VALUE]>
+                           A macro to define before parsing the header, can be repeated
+    --dlls <DIR>           The directory of reference dlls to verify the imports against,
+                           like a copy of System32
+    --apiset <SCHEMA>      The apisetschema.dll or dumped API set schema to resolve
+                           API sets with, instead of the one in the `--dlls` directory
+    --machine <MACHINE>    The architecture of the import library, `x64` (default)
+                           or `arm64`
+    -o, --output <FILE>    Write to a file instead of stdout
+    -h, --help             Print this message
+";
+
+fn main() {
+    if let Err(err) = run(env::args().skip(1).collect()) {
+        eprintln!("error: {}", err);
+        if let Error::Usage(_) = err {
+            eprintln!();
+            eprint!("{}", USAGE);
+        }
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), Error> {
+    let args = Args::parse(args)?;
+    if args.help {
+        print!("{}", USAGE);
+        return Ok(());
+    }
+
+    let output = match args.command.as_deref() {
+        Some("exports") => {
+            let file = args.single_input()?;
+            let data = fs::read(file)?;
+            let exports = PeFile::parse(&data)?.exports()?;
+            let options = ExportsOptions {
+                filter: args.filter.as_deref().map(Regex::new).transpose()?,
+                dll_name: args.name.clone(),
+            };
+
+            exports::extern_block(&exports, &options).render()
+        }
+        Some("winmd") => {
+            let (file, names) = args
+                .inputs
+                .split_first()
+                .ok_or_else(|| Error::Usage("Missing input file".to_string()))?;
+            let dll = args
+                .dll
+                .as_deref()
+                .ok_or_else(|| Error::Usage("Missing `--dll`".to_string()))?;
+            let backend = match args.backend.as_deref() {
+                None | Some("winapi") => Backend::Winapi,
+                Some("windows") => Backend::Windows,
+                Some(backend) => {
+                    return Err(Error::Usage(format!("Unknown backend `{}`", backend)))
+                }
+            };
+
+            let data = fs::read(file)?;
+            let metadata = Metadata::parse(&data)?;
+            let options = WinmdOptions {
+                backend,
+                names: names.to_vec(),
+                filter: args.filter.as_deref().map(Regex::new).transpose()?,
+                dll_name: args.name.clone(),
+            };
+
+            rust::render_blocks(&winmd::extern_blocks(&metadata, dll, &options)?)
+        }
+        Some("header") => {
+            let file = args.single_input()?;
+            let dll = args
+                .dll
+                .as_deref()
+                .ok_or_else(|| Error::Usage("Missing `--dll`".to_string()))?;
+
+            let source = fs::read_to_string(file)?;
+            let options = HeaderOptions {
+                filter: args.filter.as_deref().map(Regex::new).transpose()?,
+                ignore: args.ignore.clone(),
+                defines: args
+                    .defines
+                    .iter()
+                    .map(|define| match define.split_once('=') {
+                        Some((name, value)) => (name.to_string(), value.to_string()),
+                        None => (define.clone(), String::new()),
+                    })
+                    .collect(),
+            };
+
+            rust::render_blocks(&header::extern_blocks(&source, dll, &options)?)
+        }
+        Some("audit") => {
+            let file = args.single_input()?;
+            let data = fs::read(file)?;
+            audit::manifest(&audit::runtime_dlls(&PeFile::parse(&data)?)?)
+        }
+        Some("verify") => {
+            if args.inputs.is_empty() {
+                return Err(Error::Usage("Missing input file".to_string()));
+            }
+            let dir = args
+                .dlls
+                .as_deref()
+                .ok_or_else(|| Error::Usage("Missing `--dlls`".to_string()))?;
+
+            let mut resolver = Resolver::from_dir(dir)?;
+            for warning in resolver.warnings() {
+                eprintln!("warning: {}", warning);
+            }
+            if let Some(file) = &args.apiset {
+                resolver.set_api_sets(ApiSetSchema::from_file_data(&fs::read(file)?)?);
+            }
+            let dlls = source::dll_imports_in_files(&args.inputs)?;
+            let verification = resolve::verify(&resolver, &dlls);
+            match &args.output {
+                Some(path) => fs::write(path, verification.report)?,
+                None => print!("{}", verification.report),
+            }
+            if verification.unresolved > 0 {
+                eprintln!(
+                    "error: {} imports couldn't be resolved",
+                    verification.unresolved
+                );
+                process::exit(1);
+            }
+            return Ok(());
+        }
+        Some("apiset") => {
+            let (file, names) = args
+                .inputs
+                .split_first()
+                .ok_or_else(|| Error::Usage("Missing input file".to_string()))?;
+            let schema = ApiSetSchema::from_file_data(&fs::read(file)?)?;
+
+            let mut output = String::new();
+            if names.is_empty() {
+                for api_set in &schema.api_sets {
+                    let hosts = api_set
+                        .hosts
+                        .iter()
+                        .map(|host| match &host.importer {
+                            Some(importer) => format!("{} (from {})", host.host, importer),
+                            None => host.host.clone(),
+                        })
+                        .collect::<Vec<_>>();
+                    output.push_str(&format!("{} -> {}\n", api_set.name, hosts.join(", ")));
+                }
+            }
+            for name in names {
+                let host = schema
+                    .resolve(name, None)
+                    .ok_or_else(|| Error::NotFound(name.clone()))?;
+                output.push_str(&format!("{} -> {}\n", name, host));
+            }
+            output
+        }
+        Some("def") => import_lib::def_file(&args.source_dll()?),
+        Some("implib") => {
+            let machine = match args.machine.as_deref() {
+                None | Some("x64") => Machine::X64,
+                Some("arm64") => Machine::Arm64,
+                Some(machine) => {
+                    return Err(Error::Usage(format!("Unknown machine `{}`", machine)))
+                }
+            };
+            let path = args
+                .output
+                .as_deref()
+                .ok_or_else(|| Error::Usage("Missing `--output`".to_string()))?;
+
+            let dll = args.source_dll()?;
+            fs::write(path, import_lib::import_library(&dll, machine))?;
+            return Ok(());
+        }
+        Some(command) => return Err(Error::Usage(format!("Unknown command `{}`", command))),
+        None => return Err(Error::Usage("Missing command".to_string())),
+    };
+
+    match &args.output {
+        Some(path) => fs::write(path, output)?,
+        None => print!("{}", output),
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Args {
+    command: Option<String>,
+    inputs: Vec<String>,
+    filter: Option<String>,
+    name: Option<String>,
+    dll: Option<String>,
+    backend: Option<String>,
+    ignore: Vec<String>,
+    defines: Vec<String>,
+    machine: Option<String>,
+    dlls: Option<String>,
+    apiset: Option<String>,
+    output: Option<String>,
+    help: bool,
+}
+impl Args {
+    fn parse(args: Vec<String>) -> Result<Self, Error> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = |option: &str| {
+                args.next()
+                    .ok_or_else(|| Error::Usage(format!("Missing value for `{}`", option)))
+            };
+            match arg.as_str() {
+                "-h" | "--help" => parsed.help = true,
+                "--filter" => parsed.filter = Some(value(&arg)?),
+                "--name" => parsed.name = Some(value(&arg)?),
+                "--dll" => parsed.dll = Some(value(&arg)?),
+                "--backend" => parsed.backend = Some(value(&arg)?),
+                "--ignore" => parsed.ignore.push(value(&arg)?),
+                "--define" => parsed.defines.push(value(&arg)?),
+                "--machine" => parsed.machine = Some(value(&arg)?),
+                "--dlls" => parsed.dlls = Some(value(&arg)?),
+                "--apiset" => parsed.apiset = Some(value(&arg)?),
+                "-o" | "--output" => parsed.output = Some(value(&arg)?),
+                option if option.starts_with('-') => {
+                    return Err(Error::Usage(format!("Unknown option `{}`", option)))
+                }
+                _ if parsed.command.is_none() => parsed.command = Some(arg),
+                _ => parsed.inputs.push(arg),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    fn single_input(&self) -> Result<&str, Error> {
+        match &self.inputs[..] {
+            [input] => Ok(input),
+            [] => Err(Error::Usage("Missing input file".to_string())),
+            _ => Err(Error::Usage("Expected a single input file".to_string())),
+        }
+    }
+
+    /// The imports of the `--dll` declared in the input sources, which can be left out
+    /// when they only declare one
+    fn source_dll(&self) -> Result<DllImports, Error> {
+        if self.inputs.is_empty() {
+            return Err(Error::Usage("Missing input file".to_string()));
+        }
+        let mut dlls = source::dll_imports_in_files(&self.inputs)?;
+        match &self.dll {
+            Some(dll) => {
+                let dll_name = import_lib::dll_file_name(dll);
+                dlls.into_iter()
+                    .find(|imports| {
+                        import_lib::dll_file_name(&imports.dll).eq_ignore_ascii_case(&dll_name)
+                    })
+                    .ok_or_else(|| Error::NotFound(dll.clone()))
+            }
+            None if dlls.len() == 1 => Ok(dlls.remove(0)),
+            None if dlls.is_empty() => Err(Error::NotFound("#[dll] block".to_string())),
+            None => Err(Error::Usage(format!(
+                "The sources declare several dlls, pass one of them with `--dll`: {}",
+                dlls.iter()
+                    .map(|imports| imports.dll.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+}
@@ -0,0 +1,786 @@
+//! Minimal reader for ECMA-335 metadata, as found in `.winmd` files
+//!
+//! Only the parts needed to describe the functions imported with `DllImport` are read:
+//! the `ImplMap` table, the methods it points to, their parameters and signatures.
+
+use crate::{pe::PeFile, Error};
+use std::convert::TryInto;
+
+const IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR: usize = 14;
+const METADATA_SIGNATURE: u32 = 0x424a_5342;
+
+// Calling conventions of `ImplMap` rows
+const PINVOKE_CALL_CONV_MASK: u16 = 0x0700;
+const PINVOKE_CALL_CONV_CDECL: u16 = 0x0200;
+
+// `Param` flags
+const PARAM_IN: u16 = 0x0001;
+const PARAM_OUT: u16 = 0x0002;
+
+// Signature calling conventions
+const SIG_VARARG: u8 = 0x05;
+const SIG_GENERIC: u8 = 0x10;
+
+// Deeper than any real type, so a crafted signature can't overflow the stack
+const MAX_TYPE_DEPTH: u32 = 64;
+
+/// Metadata tables, numbered as in ECMA-335 II.22
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Table {
+    Module = 0x00,
+    TypeRef = 0x01,
+    TypeDef = 0x02,
+    FieldPtr = 0x03,
+    Field = 0x04,
+    MethodPtr = 0x05,
+    MethodDef = 0x06,
+    ParamPtr = 0x07,
+    Param = 0x08,
+    InterfaceImpl = 0x09,
+    MemberRef = 0x0a,
+    Constant = 0x0b,
+    CustomAttribute = 0x0c,
+    FieldMarshal = 0x0d,
+    DeclSecurity = 0x0e,
+    ClassLayout = 0x0f,
+    FieldLayout = 0x10,
+    StandAloneSig = 0x11,
+    EventMap = 0x12,
+    EventPtr = 0x13,
+    Event = 0x14,
+    PropertyMap = 0x15,
+    PropertyPtr = 0x16,
+    Property = 0x17,
+    MethodSemantics = 0x18,
+    MethodImpl = 0x19,
+    ModuleRef = 0x1a,
+    TypeSpec = 0x1b,
+    ImplMap = 0x1c,
+    FieldRva = 0x1d,
+    EncLog = 0x1e,
+    EncMap = 0x1f,
+    Assembly = 0x20,
+    AssemblyProcessor = 0x21,
+    AssemblyOs = 0x22,
+    AssemblyRef = 0x23,
+    AssemblyRefProcessor = 0x24,
+    AssemblyRefOs = 0x25,
+    File = 0x26,
+    ExportedType = 0x27,
+    ManifestResource = 0x28,
+    NestedClass = 0x29,
+    GenericParam = 0x2a,
+    MethodSpec = 0x2b,
+    GenericParamConstraint = 0x2c,
+}
+
+/// Coded indexes, with the tables they can point to in the order of their tags
+#[derive(Debug, Clone, Copy)]
+enum Coded {
+    TypeDefOrRef,
+    HasConstant,
+    HasCustomAttribute,
+    HasFieldMarshal,
+    HasDeclSecurity,
+    MemberRefParent,
+    HasSemantics,
+    MethodDefOrRef,
+    MemberForwarded,
+    Implementation,
+    CustomAttributeType,
+    ResolutionScope,
+    TypeOrMethodDef,
+}
+impl Coded {
+    fn tables(self) -> &'static [Option<Table>] {
+        use Table::*;
+        match self {
+            Self::TypeDefOrRef => &[Some(TypeDef), Some(TypeRef), Some(TypeSpec)],
+            Self::HasConstant => &[Some(Field), Some(Param), Some(Property)],
+            Self::HasCustomAttribute => &[
+                Some(MethodDef),
+                Some(Field),
+                Some(TypeRef),
+                Some(TypeDef),
+                Some(Param),
+                Some(InterfaceImpl),
+                Some(MemberRef),
+                Some(Module),
+                Some(DeclSecurity),
+                Some(Property),
+                Some(Event),
+                Some(StandAloneSig),
+                Some(ModuleRef),
+                Some(TypeSpec),
+                Some(Assembly),
+                Some(AssemblyRef),
+                Some(File),
+                Some(ExportedType),
+                Some(ManifestResource),
+                Some(GenericParam),
+                Some(GenericParamConstraint),
+                Some(MethodSpec),
+            ],
+            Self::HasFieldMarshal => &[Some(Field), Some(Param)],
+            Self::HasDeclSecurity => &[Some(TypeDef), Some(MethodDef), Some(Assembly)],
+            Self::MemberRefParent => &[
+                Some(TypeDef),
+                Some(TypeRef),
+                Some(ModuleRef),
+                Some(MethodDef),
+                Some(TypeSpec),
+            ],
+            Self::HasSemantics => &[Some(Event), Some(Property)],
+            Self::MethodDefOrRef => &[Some(MethodDef), Some(MemberRef)],
+            Self::MemberForwarded => &[Some(Field), Some(MethodDef)],
+            Self::Implementation => &[Some(File), Some(AssemblyRef), Some(ExportedType)],
+            Self::CustomAttributeType => &[None, None, Some(MethodDef), Some(MemberRef), None],
+            Self::ResolutionScope => &[
+                Some(Module),
+                Some(ModuleRef),
+                Some(AssemblyRef),
+                Some(TypeRef),
+            ],
+            Self::TypeOrMethodDef => &[Some(TypeDef), Some(MethodDef)],
+        }
+    }
+    fn tag_bits(self) -> u32 {
+        let tables = self.tables().len() as u32;
+        32 - (tables - 1).leading_zeros()
+    }
+    /// The table and the 1-based row a coded index points to
+    fn decode(self, value: u32) -> Option<(Table, u32)> {
+        let bits = self.tag_bits();
+        let tag = value & ((1 << bits) - 1);
+        let table = (*self.tables().get(tag as usize)?)?;
+        Some((table, value >> bits))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Column {
+    U16,
+    U32,
+    String,
+    Guid,
+    Blob,
+    Index(Table),
+    CodedIndex(Coded),
+}
+
+fn columns(table: Table) -> &'static [Column] {
+    use Column::*;
+    use Table as T;
+    match table {
+        T::Module => &[U16, String, Guid, Guid, Guid],
+        T::TypeRef => &[CodedIndex(Coded::ResolutionScope), String, String],
+        T::TypeDef => &[
+            U32,
+            String,
+            String,
+            CodedIndex(Coded::TypeDefOrRef),
+            Index(T::Field),
+            Index(T::MethodDef),
+        ],
+        T::FieldPtr => &[Index(T::Field)],
+        T::Field => &[U16, String, Blob],
+        T::MethodPtr => &[Index(T::MethodDef)],
+        T::MethodDef => &[U32, U16, U16, String, Blob, Index(T::Param)],
+        T::ParamPtr => &[Index(T::Param)],
+        T::Param => &[U16, U16, String],
+        T::InterfaceImpl => &[Index(T::TypeDef), CodedIndex(Coded::TypeDefOrRef)],
+        T::MemberRef => &[CodedIndex(Coded::MemberRefParent), String, Blob],
+        T::Constant => &[U16, CodedIndex(Coded::HasConstant), Blob],
+        T::CustomAttribute => &[
+            CodedIndex(Coded::HasCustomAttribute),
+            CodedIndex(Coded::CustomAttributeType),
+            Blob,
+        ],
+        T::FieldMarshal => &[CodedIndex(Coded::HasFieldMarshal), Blob],
+        T::DeclSecurity => &[U16, CodedIndex(Coded::HasDeclSecurity), Blob],
+        T::ClassLayout => &[U16, U32, Index(T::TypeDef)],
+        T::FieldLayout => &[U32, Index(T::Field)],
+        T::StandAloneSig => &[Blob],
+        T::EventMap => &[Index(T::TypeDef), Index(T::Event)],
+        T::EventPtr => &[Index(T::Event)],
+        T::Event => &[U16, String, CodedIndex(Coded::TypeDefOrRef)],
+        T::PropertyMap => &[Index(T::TypeDef), Index(T::Property)],
+        T::PropertyPtr => &[Index(T::Property)],
+        T::Property => &[U16, String, Blob],
+        T::MethodSemantics => &[U16, Index(T::MethodDef), CodedIndex(Coded::HasSemantics)],
+        T::MethodImpl => &[
+            Index(T::TypeDef),
+            CodedIndex(Coded::MethodDefOrRef),
+            CodedIndex(Coded::MethodDefOrRef),
+        ],
+        T::ModuleRef => &[String],
+        T::TypeSpec => &[Blob],
+        T::ImplMap => &[
+            U16,
+            CodedIndex(Coded::MemberForwarded),
+            String,
+            Index(T::ModuleRef),
+        ],
+        T::FieldRva => &[U32, Index(T::Field)],
+        T::EncLog => &[U32, U32],
+        T::EncMap => &[U32],
+        T::Assembly => &[U32, U16, U16, U16, U16, U32, Blob, String, String],
+        T::AssemblyProcessor => &[U32],
+        T::AssemblyOs => &[U32, U32, U32],
+        T::AssemblyRef => &[U16, U16, U16, U16, U32, Blob, String, String, Blob],
+        T::AssemblyRefProcessor => &[U32, Index(T::AssemblyRef)],
+        T::AssemblyRefOs => &[U32, U32, U32, Index(T::AssemblyRef)],
+        T::File => &[U32, String, Blob],
+        T::ExportedType => &[U32, U32, String, String, CodedIndex(Coded::Implementation)],
+        T::ManifestResource => &[U32, U32, String, CodedIndex(Coded::Implementation)],
+        T::NestedClass => &[Index(T::TypeDef), Index(T::TypeDef)],
+        T::GenericParam => &[U16, U16, CodedIndex(Coded::TypeOrMethodDef), String],
+        T::MethodSpec => &[CodedIndex(Coded::MethodDefOrRef), Blob],
+        T::GenericParamConstraint => &[Index(T::GenericParam), CodedIndex(Coded::TypeDefOrRef)],
+    }
+}
+
+const TABLES: [Table; 45] = {
+    use Table::*;
+    [
+        Module,
+        TypeRef,
+        TypeDef,
+        FieldPtr,
+        Field,
+        MethodPtr,
+        MethodDef,
+        ParamPtr,
+        Param,
+        InterfaceImpl,
+        MemberRef,
+        Constant,
+        CustomAttribute,
+        FieldMarshal,
+        DeclSecurity,
+        ClassLayout,
+        FieldLayout,
+        StandAloneSig,
+        EventMap,
+        EventPtr,
+        Event,
+        PropertyMap,
+        PropertyPtr,
+        Property,
+        MethodSemantics,
+        MethodImpl,
+        ModuleRef,
+        TypeSpec,
+        ImplMap,
+        FieldRva,
+        EncLog,
+        EncMap,
+        Assembly,
+        AssemblyProcessor,
+        AssemblyOs,
+        AssemblyRef,
+        AssemblyRefProcessor,
+        AssemblyRefOs,
+        File,
+        ExportedType,
+        ManifestResource,
+        NestedClass,
+        GenericParam,
+        MethodSpec,
+        GenericParamConstraint,
+    ]
+};
+
+#[derive(Debug, Clone, Default)]
+struct TableLayout {
+    offset: usize,
+    rows: u32,
+    row_size: usize,
+    /// The offset and size of each column in a row
+    columns: Vec<(usize, usize)>,
+}
+
+/// The metadata of a `.winmd` file, borrowing its bytes
+pub struct Metadata<'a> {
+    tables: &'a [u8],
+    strings: &'a [u8],
+    blobs: &'a [u8],
+    layouts: Vec<TableLayout>,
+}
+
+/// A function imported from a dll with `DllImport`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedFunction {
+    /// The dll, as written in the metadata, e.g. `USER32.dll`
+    pub dll: String,
+    /// The name of the export
+    pub name: String,
+    /// Whether the function uses the `cdecl` calling convention instead of `system`
+    pub cdecl: bool,
+    pub params: Vec<Param>,
+    pub ret: Type,
+    pub variadic: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub name: String,
+    pub ty: Type,
+    /// The parameter is only read by the function
+    pub is_in: bool,
+    /// The parameter is written by the function
+    pub is_out: bool,
+}
+
+/// The type of a parameter or of a return value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Void,
+    Bool,
+    /// A UTF-16 code unit
+    Char,
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+    ISize,
+    USize,
+    Ptr(Box<Type>),
+    /// A struct, enum or delegate
+    Value(TypeName),
+    /// A class or an interface, passed as a pointer
+    Class(TypeName),
+    /// A type that can't be used in a dll import, like generics or arrays
+    Unsupported,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeName {
+    pub namespace: String,
+    pub name: String,
+    pub kind: TypeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeKind {
+    Struct,
+    /// An enum and the integer type it wraps
+    Enum(Box<Type>),
+    /// A function pointer
+    Delegate,
+    /// A type defined in another metadata file
+    Unknown,
+}
+
+impl<'a> Metadata<'a> {
+    /// Read the metadata of a `.winmd` file
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        Self::from_pe(&PeFile::parse(data)?)
+    }
+
+    pub fn from_pe(pe: &PeFile<'a>) -> Result<Self, Error> {
+        let cli = pe
+            .data_directory(IMAGE_DIRECTORY_ENTRY_COM_DESCRIPTOR)
+            .ok_or(Error::Metadata("the file has no CLI header"))?;
+        let field = |offset: u32| {
+            cli.rva
+                .checked_add(offset)
+                .ok_or(Error::Metadata("CLI header out of range"))
+        };
+        let root_rva = pe.read_u32_at_rva(field(8)?)?;
+        let root_size = pe.read_u32_at_rva(field(12)?)?;
+        let root = pe.read_bytes_at_rva(root_rva, root_size)?;
+
+        if read_u32(root, 0)? != METADATA_SIGNATURE {
+            return Err(Error::Metadata("missing metadata signature"));
+        }
+        let version_len = read_u32(root, 12)? as usize;
+        let streams_header = 16 + version_len;
+        let number_of_streams = read_u16(root, streams_header + 2)?;
+
+        let (mut tables, mut strings, mut blobs) = (None, &[][..], &[][..]);
+        let mut offset = streams_header + 4;
+        for _ in 0..number_of_streams {
+            let stream_offset = read_u32(root, offset)? as usize;
+            let stream_size = read_u32(root, offset + 4)? as usize;
+            let name = read_str(root, offset + 8)?;
+            let stream = read_bytes(root, stream_offset, stream_size)?;
+            match name {
+                "#~" => tables = Some(stream),
+                "#Strings" => strings = stream,
+                "#Blob" => blobs = stream,
+                _ => {}
+            }
+            // Stream names are padded to 4 bytes
+            offset += 8 + (name.len() + 4) / 4 * 4;
+        }
+        let tables = tables.ok_or(Error::Metadata("missing #~ stream"))?;
+
+        let heap_sizes = *tables
+            .get(6)
+            .ok_or(Error::Metadata("truncated #~ stream"))?;
+        let valid = read_u64(tables, 8)?;
+        let mut rows = [0; 64];
+        let mut offset = 24;
+        for (table, count) in rows.iter_mut().enumerate() {
+            if valid & (1 << table) != 0 {
+                *count = read_u32(tables, offset)?;
+                offset += 4;
+            }
+        }
+        if valid >> TABLES.len() != 0 {
+            return Err(Error::Metadata("unknown metadata table"));
+        }
+
+        let index_size = |large: bool| if large { 4 } else { 2 };
+        let column_size = |column: Column| match column {
+            Column::U16 => 2,
+            Column::U32 => 4,
+            Column::String => index_size(heap_sizes & 0x01 != 0),
+            Column::Guid => index_size(heap_sizes & 0x02 != 0),
+            Column::Blob => index_size(heap_sizes & 0x04 != 0),
+            Column::Index(table) => index_size(rows[table as usize] > 0xffff),
+            Column::CodedIndex(coded) => {
+                let max_rows = coded
+                    .tables()
+                    .iter()
+                    .flatten()
+                    .map(|&table| rows[table as usize])
+                    .max()
+                    .unwrap_or(0);
+                index_size(max_rows >= 1 << (16 - coded.tag_bits()))
+            }
+        };
+
+        let mut layouts = Vec::with_capacity(TABLES.len());
+        for &table in &TABLES {
+            let mut layout = TableLayout {
+                offset,
+                rows: rows[table as usize],
+                ..TableLayout::default()
+            };
+            for &column in columns(table) {
+                let size = column_size(column);
+                layout.columns.push((layout.row_size, size));
+                layout.row_size += size;
+            }
+            offset += layout.row_size * layout.rows as usize;
+            layouts.push(layout);
+        }
+        if offset > tables.len() {
+            return Err(Error::Metadata("truncated #~ stream"));
+        }
+
+        Ok(Self {
+            tables,
+            strings,
+            blobs,
+            layouts,
+        })
+    }
+
+    /// Every function imported from a dll, in the order of the `ImplMap` table
+    pub fn imported_functions(&self) -> Result<Vec<ImportedFunction>, Error> {
+        let mut functions = Vec::new();
+
+        for row in 1..=self.rows(Table::ImplMap) {
+            let flags = self.read(Table::ImplMap, row, 0)? as u16;
+            let member = self.read(Table::ImplMap, row, 1)?;
+            let name = self.string(self.read(Table::ImplMap, row, 2)?)?;
+            let module = self.read(Table::ImplMap, row, 3)?;
+
+            let method = match Coded::MemberForwarded.decode(member) {
+                Some((Table::MethodDef, method)) => method,
+                _ => continue,
+            };
+            let dll = self.string(self.read(Table::ModuleRef, module, 0)?)?;
+            let (params, ret, variadic) = self.method(method)?;
+
+            functions.push(ImportedFunction {
+                dll: dll.to_string(),
+                name: name.to_string(),
+                cdecl: flags & PINVOKE_CALL_CONV_MASK == PINVOKE_CALL_CONV_CDECL,
+                params,
+                ret,
+                variadic,
+            });
+        }
+
+        Ok(functions)
+    }
+
+    fn method(&self, method: u32) -> Result<(Vec<Param>, Type, bool), Error> {
+        let mut signature = Blob(self.blob(self.read(Table::MethodDef, method, 4)?)?);
+        let calling_convention = signature.byte()?;
+        if calling_convention & SIG_GENERIC != 0 {
+            signature.compressed()?;
+        }
+        let count = signature.compressed()?;
+        let ret = self.parse_type(&mut signature)?;
+        let types = (0..count)
+            .map(|_| self.parse_type(&mut signature))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The `Param` rows of a method go up to the rows of the next one
+        let first = self.read(Table::MethodDef, method, 5)?;
+        let last = if method < self.rows(Table::MethodDef) {
+            self.read(Table::MethodDef, method + 1, 5)?
+        } else {
+            self.rows(Table::Param) + 1
+        };
+
+        let mut params: Vec<_> = types
+            .into_iter()
+            .enumerate()
+            .map(|(i, ty)| Param {
+                name: format!("arg{}", i),
+                ty,
+                is_in: false,
+                is_out: false,
+            })
+            .collect();
+        for row in first..last {
+            let flags = self.read(Table::Param, row, 0)? as u16;
+            let sequence = self.read(Table::Param, row, 1)? as usize;
+            let name = self.string(self.read(Table::Param, row, 2)?)?;
+            // The sequence 0 is the return value
+            if let Some(param) = sequence.checked_sub(1).and_then(|i| params.get_mut(i)) {
+                if !name.is_empty() {
+                    param.name = name.to_string();
+                }
+                param.is_in = flags & PARAM_IN != 0;
+                param.is_out = flags & PARAM_OUT != 0;
+            }
+        }
+
+        Ok((params, ret, calling_convention & 0x0f == SIG_VARARG))
+    }
+
+    fn parse_type(&self, blob: &mut Blob<'_>) -> Result<Type, Error> {
+        self.parse_nested_type(blob, 0)
+    }
+
+    fn parse_nested_type(&self, blob: &mut Blob<'_>, depth: u32) -> Result<Type, Error> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(Error::Metadata("type signature nested too deeply"));
+        }
+        let depth = depth + 1;
+        Ok(match blob.byte()? {
+            // Custom modifiers, like `const`, precede the type
+            0x1f | 0x20 => {
+                blob.compressed()?;
+                return self.parse_nested_type(blob, depth);
+            }
+            0x01 => Type::Void,
+            0x02 => Type::Bool,
+            0x03 => Type::Char,
+            0x04 => Type::I8,
+            0x05 => Type::U8,
+            0x06 => Type::I16,
+            0x07 => Type::U16,
+            0x08 => Type::I32,
+            0x09 => Type::U32,
+            0x0a => Type::I64,
+            0x0b => Type::U64,
+            0x0c => Type::F32,
+            0x0d => Type::F64,
+            0x18 => Type::ISize,
+            0x19 => Type::USize,
+            0x0f | 0x10 => Type::Ptr(Box::new(self.parse_nested_type(blob, depth)?)),
+            0x11 => self
+                .type_name(blob.compressed()?)?
+                .map_or(Type::Unsupported, Type::Value),
+            0x12 => self
+                .type_name(blob.compressed()?)?
+                .map_or(Type::Unsupported, Type::Class),
+            // Types with no equivalent in a dll import, skipped to read the next ones
+            0x0e | 0x16 | 0x1c => Type::Unsupported,
+            0x13 | 0x1e => {
+                blob.compressed()?;
+                Type::Unsupported
+            }
+            0x1d => {
+                self.parse_nested_type(blob, depth)?;
+                Type::Unsupported
+            }
+            0x14 => {
+                self.parse_nested_type(blob, depth)?;
+                blob.compressed()?;
+                for _ in 0..blob.compressed()? {
+                    blob.compressed()?;
+                }
+                for _ in 0..blob.compressed()? {
+                    blob.compressed()?;
+                }
+                Type::Unsupported
+            }
+            0x15 => {
+                self.parse_nested_type(blob, depth)?;
+                for _ in 0..blob.compressed()? {
+                    self.parse_nested_type(blob, depth)?;
+                }
+                Type::Unsupported
+            }
+            0x1b => {
+                let calling_convention = blob.byte()?;
+                if calling_convention & SIG_GENERIC != 0 {
+                    blob.compressed()?;
+                }
+                for _ in 0..=blob.compressed()? {
+                    self.parse_nested_type(blob, depth)?;
+                }
+                Type::Unsupported
+            }
+            _ => return Err(Error::Metadata("unknown element type in signature")),
+        })
+    }
+
+    /// The type of a `TypeDefOrRef` coded index from a signature, `None` for type specs
+    fn type_name(&self, coded: u32) -> Result<Option<TypeName>, Error> {
+        // Signatures encode the tag in 2 bits whatever the size of the tables
+        let (table, row) = match coded & 0b11 {
+            0 => (Table::TypeDef, coded >> 2),
+            1 => (Table::TypeRef, coded >> 2),
+            _ => return Ok(None),
+        };
+
+        // Both tables have the name and namespace in their second and third columns
+        let name = self.string(self.read(table, row, 1)?)?.to_string();
+        let namespace = self.string(self.read(table, row, 2)?)?.to_string();
+        let kind = match table {
+            Table::TypeDef => self.type_kind(row)?,
+            _ => TypeKind::Unknown,
+        };
+
+        Ok(Some(TypeName {
+            namespace,
+            name,
+            kind,
+        }))
+    }
+
+    fn type_kind(&self, type_def: u32) -> Result<TypeKind, Error> {
+        let extends = self.read(Table::TypeDef, type_def, 3)?;
+        let base = match Coded::TypeDefOrRef.decode(extends) {
+            Some((table @ Table::TypeRef, row)) | Some((table @ Table::TypeDef, row)) => {
+                let namespace = self.string(self.read(table, row, 2)?)?;
+                let name = self.string(self.read(table, row, 1)?)?;
+                (namespace == "System").then_some(name)
+            }
+            _ => None,
+        };
+
+        Ok(match base {
+            Some("Enum") => {
+                // The first field of an enum, `value__`, has the integer type
+                let field = self.read(Table::TypeDef, type_def, 4)?;
+                let mut signature = Blob(self.blob(self.read(Table::Field, field, 2)?)?);
+                signature.byte()?;
+                TypeKind::Enum(Box::new(self.parse_type(&mut signature)?))
+            }
+            Some("MulticastDelegate") => TypeKind::Delegate,
+            _ => TypeKind::Struct,
+        })
+    }
+
+    fn rows(&self, table: Table) -> u32 {
+        self.layouts[table as usize].rows
+    }
+
+    /// Read a column of a 1-based row
+    fn read(&self, table: Table, row: u32, column: usize) -> Result<u32, Error> {
+        let layout = &self.layouts[table as usize];
+        if row == 0 || row > layout.rows {
+            return Err(Error::Metadata("row index out of range"));
+        }
+        let (column_offset, size) = layout.columns[column];
+        let offset = layout.offset + (row as usize - 1) * layout.row_size + column_offset;
+        match size {
+            2 => read_u16(self.tables, offset).map(u32::from),
+            _ => read_u32(self.tables, offset),
+        }
+    }
+
+    fn string(&self, index: u32) -> Result<&'a str, Error> {
+        read_str(self.strings, index as usize)
+    }
+
+    fn blob(&self, index: u32) -> Result<&'a [u8], Error> {
+        let mut blob = Blob(
+            self.blobs
+                .get(index as usize..)
+                .ok_or(Error::Metadata("blob index out of range"))?,
+        );
+        let len = blob.compressed()? as usize;
+        blob.0
+            .get(..len)
+            .ok_or(Error::Metadata("blob out of range"))
+    }
+}
+
+/// A cursor in a blob
+struct Blob<'a>(&'a [u8]);
+impl Blob<'_> {
+    fn byte(&mut self) -> Result<u8, Error> {
+        let (&byte, rest) = self
+            .0
+            .split_first()
+            .ok_or(Error::Metadata("unexpected end of signature"))?;
+        self.0 = rest;
+        Ok(byte)
+    }
+
+    /// A compressed unsigned integer, see ECMA-335 II.23.2
+    fn compressed(&mut self) -> Result<u32, Error> {
+        let first = self.byte()? as u32;
+        Ok(if first & 0x80 == 0 {
+            first
+        } else if first & 0x40 == 0 {
+            (first & 0x3f) << 8 | self.byte()? as u32
+        } else {
+            let mut value = first & 0x1f;
+            for _ in 0..3 {
+                value = value << 8 | self.byte()? as u32;
+            }
+            value
+        })
+    }
+}
+
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8], Error> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(Error::Metadata("unexpected end of metadata"))
+}
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    Ok(u16::from_le_bytes(
+        read_bytes(data, offset, 2)?.try_into().unwrap(),
+    ))
+}
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    Ok(u32::from_le_bytes(
+        read_bytes(data, offset, 4)?.try_into().unwrap(),
+    ))
+}
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, Error> {
+    Ok(u64::from_le_bytes(
+        read_bytes(data, offset, 8)?.try_into().unwrap(),
+    ))
+}
+fn read_str(data: &[u8], offset: usize) -> Result<&str, Error> {
+    let bytes = data
+        .get(offset..)
+        .ok_or(Error::Metadata("string index out of range"))?;
+    let len = bytes
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or(Error::Metadata("unterminated string"))?;
+    std::str::from_utf8(&bytes[..len]).map_err(|_| Error::Metadata("invalid UTF-8 string"))
+}
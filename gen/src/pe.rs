@@ -0,0 +1,331 @@
+//! Minimal reader for the parts of PE files needed by the generators
+
+use crate::{rust::Link, Error};
+use std::convert::TryInto;
+
+const IMAGE_DIRECTORY_ENTRY_EXPORT: usize = 0;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+
+/// A parsed PE image, borrowing the bytes of the file
+pub struct PeFile<'a> {
+    data: &'a [u8],
+    machine: u16,
+    is_64: bool,
+    data_directories: Vec<DataDirectory>,
+    sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DataDirectory {
+    pub rva: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: String,
+    pub virtual_address: u32,
+    pub virtual_size: u32,
+    pub raw_offset: u32,
+    pub raw_size: u32,
+    pub characteristics: u32,
+}
+impl Section {
+    fn contains(&self, rva: u32) -> bool {
+        let size = self.virtual_size.max(self.raw_size);
+        rva >= self.virtual_address && rva - self.virtual_address < size
+    }
+    pub fn is_executable(&self) -> bool {
+        self.characteristics & IMAGE_SCN_MEM_EXECUTE != 0
+    }
+}
+
+/// The export table of a dll
+#[derive(Debug, Clone)]
+pub struct Exports {
+    /// The name the dll was linked with
+    pub dll_name: String,
+    pub exports: Vec<Export>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Export {
+    pub ordinal: u16,
+    /// `None` for exports that can only be imported by ordinal
+    pub name: Option<String>,
+    pub target: ExportTarget,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportTarget {
+    /// Code in the dll
+    Function(u32),
+    /// Data in the dll, the export is in a section that isn't executable
+    Data(u32),
+    /// Forwarded to an export of another dll, e.g. `NTDLL.RtlAllocateHeap` or `NTDLL.#12`
+    Forwarder(String),
+}
+
+impl Export {
+    /// How the export is imported, by name when it has one
+    pub fn link(&self) -> Link {
+        match &self.name {
+            Some(name) => Link::Name(name.clone()),
+            None => Link::Ordinal(self.ordinal),
+        }
+    }
+}
+
+impl Exports {
+    /// The export imported by a name or ordinal
+    pub fn find(&self, link: &Link) -> Option<&Export> {
+        self.exports.iter().find(|export| match link {
+            Link::Name(name) => export.name.as_ref() == Some(name),
+            Link::Ordinal(ordinal) => export.ordinal == *ordinal,
+        })
+    }
+}
+
+/// A parsed forwarder string, the dll and export an export is forwarded to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Forwarder {
+    /// The dll, usually without the `.dll` extension
+    pub dll: String,
+    pub link: Link,
+}
+
+impl Forwarder {
+    /// Parse `DLL.Name` or `DLL.#ordinal`, the dll name can contain dots itself
+    pub fn parse(forwarder: &str) -> Result<Self, Error> {
+        let (dll, export) = forwarder
+            .rsplit_once('.')
+            .filter(|(dll, export)| !dll.is_empty() && !export.is_empty())
+            .ok_or(Error::Pe("invalid forwarder"))?;
+        let link = match export.strip_prefix('#') {
+            Some(ordinal) => Link::Ordinal(
+                ordinal
+                    .parse()
+                    .map_err(|_| Error::Pe("invalid forwarder ordinal"))?,
+            ),
+            None => Link::Name(export.to_string()),
+        };
+        Ok(Self {
+            dll: dll.to_string(),
+            link,
+        })
+    }
+}
+
+impl<'a> PeFile<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        if data.get(..2) != Some(b"MZ") {
+            return Err(Error::Pe("missing MZ signature"));
+        }
+        let pe_offset = read_u32(data, 0x3c)? as usize;
+        if data.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0") {
+            return Err(Error::Pe("missing PE signature"));
+        }
+
+        let coff = pe_offset + 4;
+        let machine = read_u16(data, coff)?;
+        let number_of_sections = read_u16(data, coff + 2)? as usize;
+        let size_of_optional_header = read_u16(data, coff + 16)? as usize;
+
+        let optional = coff + 20;
+        let (is_64, directories) = match read_u16(data, optional)? {
+            0x10b => (false, optional + 92),
+            0x20b => (true, optional + 108),
+            _ => return Err(Error::Pe("unknown optional header magic")),
+        };
+        let number_of_directories = read_u32(data, directories)? as usize;
+        let data_directories = (0..number_of_directories.min(16))
+            .map(|i| {
+                let offset = directories + 4 + i * 8;
+                Ok(DataDirectory {
+                    rva: read_u32(data, offset)?,
+                    size: read_u32(data, offset + 4)?,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let section_table = optional + size_of_optional_header;
+        let sections = (0..number_of_sections)
+            .map(|i| {
+                let offset = section_table + i * 40;
+                let name = read_bytes(data, offset, 8)?;
+                let name_len = name.iter().position(|&b| b == 0).unwrap_or(8);
+                Ok(Section {
+                    name: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+                    virtual_size: read_u32(data, offset + 8)?,
+                    virtual_address: read_u32(data, offset + 12)?,
+                    raw_size: read_u32(data, offset + 16)?,
+                    raw_offset: read_u32(data, offset + 20)?,
+                    characteristics: read_u32(data, offset + 36)?,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self {
+            data,
+            machine,
+            is_64,
+            data_directories,
+            sections,
+        })
+    }
+
+    /// The `IMAGE_FILE_MACHINE_*` constant of the image
+    pub fn machine(&self) -> u16 {
+        self.machine
+    }
+    pub fn is_64(&self) -> bool {
+        self.is_64
+    }
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+    pub fn data_directory(&self, index: usize) -> Option<DataDirectory> {
+        self.data_directories
+            .get(index)
+            .copied()
+            .filter(|directory| directory.rva != 0)
+    }
+    pub fn section_by_rva(&self, rva: u32) -> Option<&Section> {
+        self.sections.iter().find(|section| section.contains(rva))
+    }
+    pub fn section_by_name(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+    /// The raw bytes of a section in the file
+    pub fn section_data(&self, section: &Section) -> Result<&'a [u8], Error> {
+        let size = section.raw_size.min(section.virtual_size.max(1)) as usize;
+        read_bytes(self.data, section.raw_offset as usize, size)
+    }
+
+    fn rva_to_offset(&self, rva: u32) -> Result<usize, Error> {
+        let section = self
+            .section_by_rva(rva)
+            .ok_or(Error::Pe("RVA outside of any section"))?;
+        (rva - section.virtual_address)
+            .checked_add(section.raw_offset)
+            .map(|offset| offset as usize)
+            .ok_or(Error::Pe("RVA outside of the file"))
+    }
+    pub fn read_bytes_at_rva(&self, rva: u32, len: u32) -> Result<&'a [u8], Error> {
+        read_bytes(self.data, self.rva_to_offset(rva)?, len as usize)
+    }
+    pub fn read_u16_at_rva(&self, rva: u32) -> Result<u16, Error> {
+        read_u16(self.data, self.rva_to_offset(rva)?)
+    }
+    pub fn read_u32_at_rva(&self, rva: u32) -> Result<u32, Error> {
+        read_u32(self.data, self.rva_to_offset(rva)?)
+    }
+    /// Read a null-terminated string
+    pub fn read_str_at_rva(&self, rva: u32) -> Result<String, Error> {
+        let start = self.rva_to_offset(rva)?;
+        let bytes = self
+            .data
+            .get(start..)
+            .ok_or(Error::Pe("string out of bounds"))?;
+        let len = bytes
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(Error::Pe("unterminated string"))?;
+        Ok(String::from_utf8_lossy(&bytes[..len]).into_owned())
+    }
+
+    // Whether a table of `count` entries at `rva` fits in its section, so indexing it with
+    // `i * entry_size` can't overflow
+    fn check_table(&self, rva: u32, count: u32, entry_size: u32) -> Result<(), Error> {
+        let section = self
+            .section_by_rva(rva)
+            .ok_or(Error::Pe("RVA outside of any section"))?;
+        // The data in the file, a crafted virtual size could be anything
+        let section_size = self.section_data(section)?.len() as u32;
+        let size = count
+            .checked_mul(entry_size)
+            .ok_or(Error::Pe("table size out of range"))?;
+        match (rva - section.virtual_address).checked_add(size) {
+            Some(end) if end <= section_size => Ok(()),
+            _ => Err(Error::Pe("table extends past its section")),
+        }
+    }
+
+    pub fn exports(&self) -> Result<Exports, Error> {
+        let directory = self
+            .data_directory(IMAGE_DIRECTORY_ENTRY_EXPORT)
+            .ok_or(Error::Pe("the file has no export table"))?;
+        let rva = directory.rva;
+        let dll_name = self.read_str_at_rva(self.read_u32_at_rva(rva_add(rva, 12)?)?)?;
+        let ordinal_base = self.read_u32_at_rva(rva_add(rva, 16)?)?;
+        let number_of_functions = self.read_u32_at_rva(rva_add(rva, 20)?)?;
+        let number_of_names = self.read_u32_at_rva(rva_add(rva, 24)?)?;
+        let address_of_functions = self.read_u32_at_rva(rva_add(rva, 28)?)?;
+        let address_of_names = self.read_u32_at_rva(rva_add(rva, 32)?)?;
+        let address_of_name_ordinals = self.read_u32_at_rva(rva_add(rva, 36)?)?;
+
+        // The counts are bounded by the sections of the tables before allocating
+        self.check_table(address_of_functions, number_of_functions, 4)?;
+        self.check_table(address_of_names, number_of_names, 4)?;
+        self.check_table(address_of_name_ordinals, number_of_names, 2)?;
+
+        let mut names = vec![None; number_of_functions as usize];
+        for i in 0..number_of_names {
+            let name =
+                self.read_str_at_rva(self.read_u32_at_rva(rva_add(address_of_names, i * 4)?)?)?;
+            let index = self.read_u16_at_rva(rva_add(address_of_name_ordinals, i * 2)?)? as usize;
+            if let Some(slot) = names.get_mut(index) {
+                *slot = Some(name);
+            }
+        }
+
+        let mut exports = Vec::new();
+        for (index, name) in names.into_iter().enumerate() {
+            let function_rva =
+                self.read_u32_at_rva(rva_add(address_of_functions, index as u32 * 4)?)?;
+            if function_rva == 0 {
+                continue;
+            }
+
+            let target = if function_rva >= rva && function_rva - rva < directory.size {
+                ExportTarget::Forwarder(self.read_str_at_rva(function_rva)?)
+            } else {
+                match self.section_by_rva(function_rva) {
+                    Some(section) if !section.is_executable() => ExportTarget::Data(function_rva),
+                    _ => ExportTarget::Function(function_rva),
+                }
+            };
+            let ordinal = ordinal_base
+                .checked_add(index as u32)
+                .and_then(|ordinal| ordinal.try_into().ok())
+                .ok_or(Error::Pe("ordinal out of range"))?;
+
+            exports.push(Export {
+                ordinal,
+                name,
+                target,
+            });
+        }
+
+        Ok(Exports { dll_name, exports })
+    }
+}
+
+fn rva_add(rva: u32, offset: u32) -> Result<u32, Error> {
+    rva.checked_add(offset).ok_or(Error::Pe("RVA out of range"))
+}
+
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8], Error> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or(Error::Pe("unexpected end of file"))
+}
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    let bytes = read_bytes(data, offset, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    let bytes = read_bytes(data, offset, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
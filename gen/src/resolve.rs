@@ -0,0 +1,226 @@
+//! Resolution of forwarded exports across a set of reference dlls
+//!
+//! Exports like `kernel32!HeapAlloc` are forwarded to an export of another dll,
+//! `NTDLL.RtlAllocateHeap`, which can be forwarded again. The resolver follows these
+//! chains through the export tables of reference dlls, usually a copy of `System32`,
+//! to check the imports of `#[dll]` blocks without running on Windows.
+//!
+//! Dlls named after an API set, like `api-ms-win-core-synch-l1-2-0`, are resolved to the
+//! dll implementing them with the API set schema, read from the `apisetschema.dll` of the
+//! directory or set with [`Resolver::set_api_sets`].
+
+use crate::{
+    apiset::ApiSetSchema,
+    import_lib::dll_file_name,
+    pe::{Export, ExportTarget, Exports, Forwarder, PeFile},
+    rust::Link,
+    source::DllImports,
+    Error,
+};
+use std::{fs, path::Path};
+
+/// The export tables of the reference dlls, by file name
+#[derive(Debug, Default)]
+pub struct Resolver {
+    dlls: Vec<(String, Exports)>,
+    api_sets: Option<ApiSetSchema>,
+    warnings: Vec<String>,
+}
+
+/// The export an import ends up at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    /// The file name of the dll exporting the code or data
+    pub dll: String,
+    /// The export, its target is a function or data
+    pub export: Export,
+    /// The forwarders followed to reach the export, in order
+    pub forwarders: Vec<Forwarder>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the export tables of the `.dll` files of a directory, skipping those without one,
+    /// and the API set schema of its `apisetschema.dll`
+    ///
+    /// The files that aren't valid dlls are skipped, and the API sets left unresolved when the
+    /// schema can't be read, with a message in [`Resolver::warnings`].
+    pub fn from_dir<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut resolver = Self::new();
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        for entry in entries {
+            let is_dll = matches!(
+                entry.extension().and_then(|ext| ext.to_str()),
+                Some(ext) if ext.eq_ignore_ascii_case("dll")
+            );
+            let file_name = match entry.file_name().and_then(|name| name.to_str()) {
+                Some(file_name) if is_dll && entry.is_file() => file_name.to_string(),
+                _ => continue,
+            };
+
+            let data = fs::read(&entry)?;
+            let pe = match PeFile::parse(&data) {
+                Ok(pe) => pe,
+                Err(err) => {
+                    resolver
+                        .warnings
+                        .push(format!("skipped {}: {}", entry.display(), err));
+                    continue;
+                }
+            };
+            if file_name.eq_ignore_ascii_case("apisetschema.dll") {
+                match ApiSetSchema::from_pe(&pe) {
+                    Ok(api_sets) => resolver.set_api_sets(api_sets),
+                    Err(err) => resolver.warnings.push(format!(
+                        "API sets are left unresolved, {}: {}",
+                        entry.display(),
+                        err
+                    )),
+                }
+            }
+            match pe.exports() {
+                Ok(exports) => resolver.add(&file_name, exports),
+                Err(Error::Pe(_)) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(resolver)
+    }
+
+    /// Add the export table of a dll, `file_name` is the name it is loaded with
+    pub fn add(&mut self, file_name: &str, exports: Exports) {
+        self.dlls.push((file_name.to_string(), exports));
+    }
+
+    /// The problems found loading the reference dlls, which didn't stop the resolver from being
+    /// created
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Resolve the API sets imported by the `#[dll]` blocks and forwarded to by exports
+    pub fn set_api_sets(&mut self, api_sets: ApiSetSchema) {
+        self.api_sets = Some(api_sets);
+    }
+
+    /// The file name and exports of a dll, compared case-insensitively with or without
+    /// the `.dll` extension, and with any path left out
+    pub fn dll(&self, dll: &str) -> Option<(&str, &Exports)> {
+        let file_name = dll_file_name(dll.rsplit(&['/', '\\'][..]).next().unwrap_or(dll));
+        self.dlls
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&file_name))
+            .map(|(name, exports)| (name.as_str(), exports))
+    }
+
+    /// Follow the forwarders of an export of a dll to the export of the code or data
+    pub fn resolve(&self, dll: &str, link: &Link) -> Result<Resolved, Error> {
+        let mut dll = dll.to_string();
+        let mut link = link.clone();
+        let mut forwarders = Vec::new();
+        let mut importer = None;
+        loop {
+            if let Some(host) = self
+                .api_sets
+                .as_ref()
+                .and_then(|api_sets| api_sets.resolve(&dll, importer))
+            {
+                dll = host.to_string();
+            }
+            let (file_name, exports) = self
+                .dll(&dll)
+                .ok_or_else(|| Error::NotFound(dll_file_name(&dll)))?;
+            let export = exports
+                .find(&link)
+                .ok_or_else(|| Error::NotFound(format!("{}!{}", file_name, display_link(&link))))?;
+
+            let forwarder = match &export.target {
+                ExportTarget::Forwarder(forwarder) => Forwarder::parse(forwarder)?,
+                _ => {
+                    return Ok(Resolved {
+                        dll: file_name.to_string(),
+                        export: export.clone(),
+                        forwarders,
+                    })
+                }
+            };
+            let is_cycle = forwarders.iter().any(|followed| {
+                followed.link == forwarder.link
+                    && dll_file_name(&followed.dll)
+                        .eq_ignore_ascii_case(&dll_file_name(&forwarder.dll))
+            });
+            if is_cycle {
+                return Err(Error::Forwarder(format!(
+                    "`{}!{}` is forwarded in a cycle",
+                    file_name,
+                    display_link(&link)
+                )));
+            }
+
+            importer = Some(file_name);
+            dll = forwarder.dll.clone();
+            link = forwarder.link.clone();
+            forwarders.push(forwarder);
+        }
+    }
+}
+
+/// A report of the imports of `#[dll]` blocks resolved against the reference dlls
+#[derive(Debug, Clone)]
+pub struct Verification {
+    /// Each dll followed by its indented imports, with the final target of forwarded
+    /// imports or the reason an import couldn't be resolved
+    pub report: String,
+    /// The number of imports that couldn't be resolved
+    pub unresolved: usize,
+}
+
+/// Resolve every import of the `#[dll]` blocks
+pub fn verify(resolver: &Resolver, dlls: &[DllImports]) -> Verification {
+    let mut report = String::new();
+    let mut unresolved = 0;
+    for dll in dlls {
+        report.push_str(&dll.dll);
+        report.push('\n');
+        for import in &dll.imports {
+            let link = match import.ordinal {
+                Some(ordinal) => Link::Ordinal(ordinal),
+                None => Link::Name(import.name.clone()),
+            };
+            report.push_str(&format!("    {}", display_link(&link)));
+            match resolver.resolve(&dll.dll, &link) {
+                // Imported from the declared dll itself
+                Ok(resolved)
+                    if resolved.forwarders.is_empty()
+                        && resolver.dll(&dll.dll).map(|(file_name, _)| file_name)
+                            == Some(resolved.dll.as_str()) => {}
+                Ok(resolved) => {
+                    report.push_str(&format!(
+                        " -> {}!{}",
+                        resolved.dll,
+                        display_link(&resolved.export.link())
+                    ));
+                }
+                Err(err) => {
+                    unresolved += 1;
+                    report.push_str(&format!(": {}", err));
+                }
+            }
+            report.push('\n');
+        }
+    }
+    Verification { report, unresolved }
+}
+
+fn display_link(link: &Link) -> String {
+    match link {
+        Link::Name(name) => name.clone(),
+        Link::Ordinal(ordinal) => format!("#{}", ordinal),
+    }
+}
@@ -0,0 +1,309 @@
+//! Rendering of `#[dll]` extern blocks as Rust source
+
+use std::{collections::HashSet, fmt::Write};
+
+/// A `#[dll]` extern block to generate
+#[derive(Debug, Clone)]
+pub struct ExternBlock {
+    /// The dll name passed to the `#[dll]` attribute
+    pub dll: String,
+    /// The ABI of the block, usually `system`
+    pub abi: String,
+    pub items: Vec<Item>,
+    /// A comment written above the block
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub link: Link,
+    pub kind: ItemKind,
+    /// A comment written above the item
+    pub comment: Option<String>,
+}
+
+/// How the item is imported from the dll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Link {
+    Name(String),
+    Ordinal(u16),
+}
+
+#[derive(Debug, Clone)]
+pub enum ItemKind {
+    /// A function with a known signature, parameters are `(name, type)` pairs
+    Function {
+        params: Vec<(String, String)>,
+        ret: Option<String>,
+        variadic: bool,
+    },
+    /// A function to be completed by hand
+    UnknownFunction,
+    /// An exported variable
+    Static { ty: Option<String> },
+}
+
+impl ExternBlock {
+    pub fn new(dll: impl Into<String>) -> Self {
+        Self {
+            dll: dll.into(),
+            abi: "system".to_string(),
+            items: Vec::new(),
+            comment: None,
+        }
+    }
+
+    /// Render the block, with the `use` of the macro
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "use windows_dll::dll;").unwrap();
+        writeln!(out).unwrap();
+        self.render_block(&mut out);
+        out
+    }
+
+    /// Render only the block, without any `use`
+    pub fn render_block(&self, out: &mut String) {
+        write_comment(out, "", self.comment.as_deref());
+        writeln!(out, "#[dll({})]", dll_arg(&self.dll)).unwrap();
+        self.write_extern(out, "", "");
+    }
+
+    fn write_extern(&self, out: &mut String, indent: &str, vis: &str) {
+        writeln!(out, "{}extern {:?} {{", indent, self.abi).unwrap();
+
+        let mut used = HashSet::new();
+        for item in &self.items {
+            let (ident, link_name) = item_ident(&item.link, &mut used);
+
+            write_comment(out, &format!("{}    ", indent), item.comment.as_deref());
+            match &item.link {
+                Link::Ordinal(ordinal) => {
+                    writeln!(out, "{}    #[link_ordinal = {}]", indent, ordinal).unwrap()
+                }
+                Link::Name(name) if link_name => {
+                    writeln!(out, "{}    #[link_name = {:?}]", indent, name).unwrap()
+                }
+                Link::Name(_) => {}
+            }
+            // Statics are turned into functions by the macro, which allows any case
+            let is_static = matches!(item.kind, ItemKind::Static { .. });
+            if !is_static && ident.chars().any(|c| c.is_ascii_uppercase()) {
+                writeln!(out, "{}    #[allow(non_snake_case)]", indent).unwrap();
+            }
+
+            match &item.kind {
+                ItemKind::Function {
+                    params,
+                    ret,
+                    variadic,
+                } => {
+                    let mut args: Vec<_> = params
+                        .iter()
+                        .map(|(name, ty)| format!("{}: {}", param_ident(name), ty))
+                        .collect();
+                    if *variadic {
+                        args.push("...".to_string());
+                    }
+                    let ret = ret
+                        .as_ref()
+                        .map(|ret| format!(" -> {}", ret))
+                        .unwrap_or_default();
+                    writeln!(
+                        out,
+                        "{}    {}fn {}({}){};",
+                        indent,
+                        vis,
+                        ident,
+                        args.join(", "),
+                        ret
+                    )
+                    .unwrap();
+                }
+                ItemKind::UnknownFunction => {
+                    writeln!(out, "{}    {}fn {}(/* TODO */);", indent, vis, ident).unwrap();
+                }
+                ItemKind::Static { ty } => {
+                    let ty = ty.as_deref().unwrap_or("u8 /* TODO */");
+                    writeln!(out, "{}    {}static {}: {};", indent, vis, ident, ty).unwrap();
+                }
+            }
+        }
+
+        writeln!(out, "{}}}", indent).unwrap();
+    }
+}
+
+/// Render blocks of the same dll, with the `use` of the macro
+///
+/// Blocks with different ABIs are put in a `#[dll]` module, so that they share the dll type.
+pub fn render_blocks(blocks: &[ExternBlock]) -> String {
+    let (first, rest) = match blocks {
+        [] => return String::new(),
+        [block] => return block.render(),
+        [first, rest @ ..] => (first, rest),
+    };
+    debug_assert!(rest.iter().all(|block| block.dll == first.dll));
+
+    let mut out = String::new();
+    writeln!(out, "use windows_dll::dll;").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "#[dll({})]", dll_arg(&first.dll)).unwrap();
+    writeln!(out, "pub mod {} {{", module_ident(&first.dll)).unwrap();
+    for (i, block) in blocks.iter().enumerate() {
+        if i > 0 {
+            writeln!(out).unwrap();
+        }
+        write_comment(&mut out, "    ", block.comment.as_deref());
+        block.write_extern(&mut out, "    ", "pub ");
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// Group items in one block per ABI, in the order of their first item
+///
+/// `todo` is written above the first block, to list the types left for the user.
+pub(crate) fn blocks_by_abi(
+    dll: &str,
+    items: Vec<(&str, Item)>,
+    todo: Option<String>,
+) -> Vec<ExternBlock> {
+    let mut blocks: Vec<ExternBlock> = Vec::new();
+    for (abi, item) in items {
+        match blocks.iter_mut().find(|block| block.abi == abi) {
+            Some(block) => block.items.push(item),
+            None => {
+                let mut block = ExternBlock::new(dll);
+                block.abi = abi.to_string();
+                block.items.push(item);
+                blocks.push(block);
+            }
+        }
+    }
+    if let Some(block) = blocks.first_mut() {
+        block.comment = todo;
+    }
+    blocks
+}
+
+fn write_comment(out: &mut String, indent: &str, comment: Option<&str>) {
+    for line in comment.into_iter().flat_map(str::lines) {
+        writeln!(out, "{}// {}", indent, line).unwrap();
+    }
+}
+
+/// The argument for the `#[dll]` attribute, unquoted if possible
+fn dll_arg(dll: &str) -> String {
+    let stem = dll_stem(dll);
+    if is_ident(stem) && !is_keyword(stem) {
+        stem.to_string()
+    } else {
+        format!("{:?}", dll)
+    }
+}
+
+fn dll_stem(dll: &str) -> &str {
+    dll.strip_suffix(".dll")
+        .or_else(|| dll.strip_suffix(".DLL"))
+        .unwrap_or(dll)
+}
+
+fn module_ident(dll: &str) -> String {
+    param_ident(&dll_stem(dll).to_ascii_lowercase())
+}
+
+/// The identifier of an item, and whether `#[link_name]` is needed to import it
+fn item_ident(link: &Link, used: &mut HashSet<String>) -> (String, bool) {
+    let (base, link_name) = match link {
+        Link::Name(name) if is_ident(name) && !is_keyword(name) => (name.clone(), false),
+        Link::Name(name) => {
+            let mut ident = sanitize(name);
+            if is_keyword(&ident) {
+                ident.push('_');
+            }
+            (ident, true)
+        }
+        Link::Ordinal(ordinal) => (format!("ordinal_{}", ordinal), false),
+    };
+
+    let mut ident = base.clone();
+    let mut suffix = 1;
+    while !used.insert(ident.clone()) {
+        suffix += 1;
+        ident = format!("{}_{}", base, suffix);
+    }
+    let renamed = ident != base;
+
+    (
+        ident,
+        link_name || (renamed && matches!(link, Link::Name(_))),
+    )
+}
+
+fn param_ident(name: &str) -> String {
+    let mut ident = sanitize(name);
+    if is_keyword(&ident) {
+        ident.push('_');
+    }
+    ident
+}
+
+/// Convert `camelCase` parameter names, like `hWnd` to `h_wnd`
+pub(crate) fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let previous = chars[i - 1];
+            let next_lower = matches!(chars.get(i + 1), Some(next) if next.is_ascii_lowercase());
+            if previous.is_ascii_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_ascii_uppercase() && next_lower)
+            {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+fn sanitize(name: &str) -> String {
+    let mut ident = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            ident.push(c);
+        } else if !ident.ends_with('_') {
+            ident.push('_');
+        }
+    }
+    let ident_trimmed = ident.trim_matches('_');
+    if !ident_trimmed.is_empty() {
+        ident = ident_trimmed.to_string();
+    }
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    ident
+}
+
+fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    name != "_" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+pub(crate) fn is_keyword(name: &str) -> bool {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else",
+        "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+        "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+        "trait", "true", "try", "type", "unsafe", "use", "where", "while", "yield", "abstract",
+        "become", "do", "final", "macro", "override", "priv", "typeof", "unsized", "virtual",
+    ];
+    KEYWORDS.contains(&name)
+}
@@ -0,0 +1,294 @@
+//! The imports declared by the `#[dll]` blocks of Rust source files
+
+use crate::Error;
+use std::{fs, path::Path};
+use syn::{
+    visit::{self, Visit},
+    Attribute, Expr, ExprLit, ForeignItem, Item, ItemForeignMod, ItemMod, Lit, Meta, NestedMeta,
+    Token,
+};
+
+/// The items imported from a dll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DllImports {
+    /// The dll name, as passed to the `#[dll]` attribute
+    pub dll: String,
+    pub imports: Vec<Import>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Import {
+    /// The symbol of the item, its `#[link_name]` or its Rust name
+    pub name: String,
+    /// The `#[link_ordinal]` of the item, it's imported by name without one
+    pub ordinal: Option<u16>,
+    /// Whether the item is a static rather than a function
+    pub data: bool,
+}
+
+/// Collect the imports of every `#[dll]` block of a source file, grouped by dll
+///
+/// Blocks without a dll name, which add to the dll type of another block with
+/// `type_name`, are merged into that block's dll when it is in the same source.
+pub fn dll_imports(source: &str) -> Result<Vec<DllImports>, Error> {
+    dll_imports_in_sources(&[source])
+}
+
+/// Collect the imports of the `#[dll]` blocks of `.rs` files, searching directories recursively
+pub fn dll_imports_in_files<P: AsRef<Path>>(paths: &[P]) -> Result<Vec<DllImports>, Error> {
+    let mut sources = Vec::new();
+    for path in paths {
+        read_sources(path.as_ref(), &mut sources)?;
+    }
+    dll_imports_in_sources(&sources)
+}
+
+fn read_sources(path: &Path, sources: &mut Vec<String>) -> Result<(), Error> {
+    if path.is_dir() {
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort();
+        for entry in entries {
+            if entry.is_dir() || matches!(entry.extension(), Some(ext) if ext == "rs") {
+                read_sources(&entry, sources)?;
+            }
+        }
+    } else {
+        sources.push(fs::read_to_string(path)?);
+    }
+    Ok(())
+}
+
+fn dll_imports_in_sources<S: AsRef<str>>(sources: &[S]) -> Result<Vec<DllImports>, Error> {
+    let mut visitor = DllVisitor::default();
+    for source in sources {
+        let file = syn::parse_file(source.as_ref())?;
+        visitor.visit_file(&file);
+        if let Some(err) = visitor.error.take() {
+            return Err(err.into());
+        }
+    }
+
+    let mut dlls: Vec<DllImports> = Vec::new();
+    for block in &visitor.blocks {
+        let dll = match &block.args.name {
+            Some(name) => name,
+            None => {
+                let type_name = block.args.type_name.as_deref();
+                match visitor.blocks.iter().find(|other| {
+                    other.args.name.is_some() && other.args.type_name.as_deref() == type_name
+                }) {
+                    Some(other) => other.args.name.as_ref().unwrap(),
+                    None => {
+                        return Err(Error::NotFound(format!(
+                            "#[dll] block with type_name `{}`",
+                            type_name.unwrap_or_default()
+                        )))
+                    }
+                }
+            }
+        };
+
+        let index = match dlls.iter().position(|dlls| same_dll(&dlls.dll, dll)) {
+            Some(index) => index,
+            None => {
+                dlls.push(DllImports {
+                    dll: dll.clone(),
+                    imports: Vec::new(),
+                });
+                dlls.len() - 1
+            }
+        };
+        let imports = &mut dlls[index].imports;
+        for import in &block.imports {
+            if !imports.iter().any(|other| other.name == import.name) {
+                imports.push(import.clone());
+            }
+        }
+    }
+    Ok(dlls)
+}
+
+/// Dll names are case-insensitive, and `.dll` is appended to names without an extension
+fn same_dll(a: &str, b: &str) -> bool {
+    let stem = |name: &str| {
+        let name = name.to_ascii_lowercase();
+        match name.strip_suffix(".dll") {
+            Some(stem) => stem.to_string(),
+            None => name,
+        }
+    };
+    stem(a) == stem(b)
+}
+
+struct DllBlock {
+    args: DllArgs,
+    imports: Vec<Import>,
+}
+
+#[derive(Default)]
+struct DllArgs {
+    name: Option<String>,
+    type_name: Option<String>,
+}
+
+#[derive(Default)]
+struct DllVisitor {
+    blocks: Vec<DllBlock>,
+    error: Option<syn::Error>,
+}
+
+impl DllVisitor {
+    fn add_block(&mut self, attrs: &[Attribute], blocks: &[&ItemForeignMod]) {
+        let attr = match attrs.iter().find(|attr| is_dll_attr(attr)) {
+            Some(attr) => attr,
+            None => return,
+        };
+        let result = dll_args(attr).and_then(|args| {
+            let mut imports = Vec::new();
+            for block in blocks {
+                for item in &block.items {
+                    imports.push(import(item)?);
+                }
+            }
+            Ok(DllBlock { args, imports })
+        });
+        match result {
+            Ok(block) => self.blocks.push(block),
+            Err(err) => {
+                self.error.get_or_insert(err);
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for DllVisitor {
+    fn visit_item_foreign_mod(&mut self, block: &'ast ItemForeignMod) {
+        self.add_block(&block.attrs, &[block]);
+    }
+
+    fn visit_item_mod(&mut self, module: &'ast ItemMod) {
+        if let Some((_, items)) = &module.content {
+            let blocks = items
+                .iter()
+                .filter_map(|item| match item {
+                    Item::ForeignMod(block) => Some(block),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            if module.attrs.iter().any(is_dll_attr) {
+                self.add_block(&module.attrs, &blocks);
+                // The extern blocks belong to the module's dll, only visit the other items
+                for item in items {
+                    if !matches!(item, Item::ForeignMod(_)) {
+                        self.visit_item(item);
+                    }
+                }
+                return;
+            }
+        }
+        visit::visit_item_mod(self, module);
+    }
+}
+
+/// `#[dll(...)]`, or `#[windows_dll::dll(...)]`
+fn is_dll_attr(attr: &Attribute) -> bool {
+    matches!(attr.path.segments.last(), Some(segment) if segment.ident == "dll")
+}
+
+fn dll_args(attr: &Attribute) -> syn::Result<DllArgs> {
+    let args =
+        attr.parse_args_with(syn::punctuated::Punctuated::<Expr, Token![,]>::parse_terminated)?;
+    let mut parsed = DllArgs::default();
+    for (index, arg) in args.iter().enumerate() {
+        match arg {
+            Expr::Assign(assign) => match expr_ident(&assign.left).as_deref() {
+                Some("name") => parsed.name = Some(dll_name(&assign.right)?),
+                Some("type_name") => parsed.type_name = Some(expr_path(&assign.right)?),
+                _ => {}
+            },
+            arg if index == 0 => parsed.name = Some(dll_name(arg)?),
+            _ => {}
+        }
+    }
+    Ok(parsed)
+}
+
+fn dll_name(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(name),
+            ..
+        }) => Ok(name.value()),
+        expr => expr_ident(expr).ok_or_else(|| {
+            syn::Error::new_spanned(expr, "DLL name must be a string or identifier")
+        }),
+    }
+}
+
+fn expr_ident(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+        _ => None,
+    }
+}
+
+fn expr_path(expr: &Expr) -> syn::Result<String> {
+    match expr {
+        Expr::Path(path) => Ok(path
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>()
+            .join("::")),
+        expr => Err(syn::Error::new_spanned(expr, "Expected a path")),
+    }
+}
+
+fn import(item: &ForeignItem) -> syn::Result<Import> {
+    let (attrs, ident, data) = match item {
+        ForeignItem::Fn(function) => (&function.attrs, &function.sig.ident, false),
+        ForeignItem::Static(item) => (&item.attrs, &item.ident, true),
+        item => {
+            return Err(syn::Error::new_spanned(
+                item,
+                "Only functions and statics are supported in a dll extern block.",
+            ))
+        }
+    };
+
+    let mut import = Import {
+        name: ident.to_string(),
+        ordinal: None,
+        data,
+    };
+    for attr in attrs {
+        if attr.path.is_ident("link_ordinal") {
+            match meta_value(attr)? {
+                Lit::Int(ordinal) => import.ordinal = Some(ordinal.base10_parse()?),
+                lit => return Err(syn::Error::new_spanned(lit, "Expected an integer ordinal.")),
+            }
+        } else if attr.path.is_ident("link_name") {
+            match meta_value(attr)? {
+                Lit::Str(name) => import.name = name.value(),
+                lit => return Err(syn::Error::new_spanned(lit, "Expected a string.")),
+            }
+        }
+    }
+    Ok(import)
+}
+
+/// The value of `#[attr = value]` or `#[attr(value)]`
+fn meta_value(attr: &Attribute) -> syn::Result<Lit> {
+    let value = match attr.parse_meta()? {
+        Meta::NameValue(name_value) => Some(name_value.lit),
+        Meta::List(list) if list.nested.len() == 1 => match list.nested.into_iter().next() {
+            Some(NestedMeta::Lit(lit)) => Some(lit),
+            _ => None,
+        },
+        _ => None,
+    };
+    value.ok_or_else(|| syn::Error::new_spanned(attr, "Expected a single literal value."))
+}
@@ -0,0 +1,227 @@
+//! Typed `#[dll]` blocks from the functions described in a `.winmd` file
+
+use crate::{
+    metadata::{ImportedFunction, Metadata, Type, TypeKind, TypeName},
+    rust::{blocks_by_abi, snake_case, ExternBlock, Item, ItemKind, Link},
+    Error,
+};
+use regex::Regex;
+use std::collections::BTreeSet;
+
+/// The crate providing the Windows types, matching the features of `windows-dll`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    #[default]
+    Winapi,
+    Windows,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WinmdOptions {
+    pub backend: Backend,
+    /// The functions to include, all the functions of the dll if empty
+    pub names: Vec<String>,
+    /// Only include functions matching this regex
+    pub filter: Option<Regex>,
+    /// The dll name for the `#[dll]` attribute, defaults to the dll that was looked up
+    pub dll_name: Option<String>,
+}
+
+/// Generate blocks declaring the functions of `dll`, one for each ABI they use
+///
+/// The dll is matched case-insensitively, with or without its `.dll` extension.
+/// Types the `winapi` backend doesn't have a path for are left unqualified,
+/// and listed in a comment above the block.
+pub fn extern_blocks(
+    metadata: &Metadata<'_>,
+    dll: &str,
+    options: &WinmdOptions,
+) -> Result<Vec<ExternBlock>, Error> {
+    let dll_stem = strip_dll(dll);
+    let mut functions: Vec<ImportedFunction> = Vec::new();
+    for function in metadata.imported_functions()? {
+        let selected = strip_dll(&function.dll).eq_ignore_ascii_case(dll_stem)
+            && (options.names.is_empty() || options.names.contains(&function.name))
+            && !matches!(&options.filter, Some(filter) if !filter.is_match(&function.name));
+        // Functions declared once per architecture are only generated once
+        if selected && !functions.iter().any(|f| f.name == function.name) {
+            functions.push(function);
+        }
+    }
+    if let Some(missing) = options
+        .names
+        .iter()
+        .find(|name| !functions.iter().any(|f| &f.name == *name))
+    {
+        return Err(Error::NotFound(format!("{}!{}", dll, missing)));
+    }
+
+    let mut unresolved = BTreeSet::new();
+    let mut types = TypeRenderer {
+        backend: options.backend,
+        unresolved: &mut unresolved,
+    };
+    let mut items = Vec::new();
+    for function in functions {
+        let params = function
+            .params
+            .iter()
+            .map(|param| {
+                let ty = match &param.ty {
+                    // Pointers only read by the function are const
+                    Type::Ptr(pointee) if param.is_in && !param.is_out => {
+                        format!("*const {}", types.render(pointee))
+                    }
+                    ty => types.render(ty),
+                };
+                (snake_case(&param.name), ty)
+            })
+            .collect();
+        let ret = match function.ret {
+            Type::Void => None,
+            ref ret => Some(types.render(ret)),
+        };
+
+        let abi = if function.cdecl { "C" } else { "system" };
+        let item = Item {
+            link: Link::Name(function.name),
+            kind: ItemKind::Function {
+                params,
+                ret,
+                variadic: function.variadic,
+            },
+            comment: None,
+        };
+        items.push((abi, item));
+    }
+
+    let dll_name = options.dll_name.as_deref().unwrap_or(dll);
+    let todo = (!unresolved.is_empty()).then(|| {
+        format!(
+            "TODO: import {}",
+            unresolved.into_iter().collect::<Vec<_>>().join(", ")
+        )
+    });
+    Ok(blocks_by_abi(dll_name, items, todo))
+}
+
+struct TypeRenderer<'a> {
+    backend: Backend,
+    unresolved: &'a mut BTreeSet<String>,
+}
+impl TypeRenderer<'_> {
+    fn render(&mut self, ty: &Type) -> String {
+        match ty {
+            Type::Void => "std::ffi::c_void".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::Char | Type::U16 => "u16".to_string(),
+            Type::I8 => "i8".to_string(),
+            Type::U8 => "u8".to_string(),
+            Type::I16 => "i16".to_string(),
+            Type::I32 => "i32".to_string(),
+            Type::U32 => "u32".to_string(),
+            Type::I64 => "i64".to_string(),
+            Type::U64 => "u64".to_string(),
+            Type::F32 => "f32".to_string(),
+            Type::F64 => "f64".to_string(),
+            Type::ISize => "isize".to_string(),
+            Type::USize => "usize".to_string(),
+            Type::Ptr(pointee) => format!("*mut {}", self.render(pointee)),
+            // COM interfaces are passed as raw pointers
+            Type::Class(_) => "*mut std::ffi::c_void".to_string(),
+            Type::Value(name) => self.render_name(name),
+            Type::Unsupported => "() /* TODO */".to_string(),
+        }
+    }
+
+    fn render_name(&mut self, ty: &TypeName) -> String {
+        match (self.backend, &ty.kind) {
+            (Backend::Windows, _) if ty.namespace == "System" && ty.name == "Guid" => {
+                "windows::core::GUID".to_string()
+            }
+            (Backend::Windows, _) => {
+                let namespace = ty
+                    .namespace
+                    .strip_prefix("Windows.")
+                    .map(|namespace| format!("windows::{}", namespace.replace('.', "::")));
+                match namespace {
+                    Some(namespace) => format!("{}::{}", namespace, ty.name),
+                    None => self.unresolved(&ty.name),
+                }
+            }
+            (Backend::Winapi, _) if ty.namespace == "System" && ty.name == "Guid" => {
+                "winapi::shared::guiddef::GUID".to_string()
+            }
+            (Backend::Winapi, kind) => match winapi_path(&ty.name) {
+                Some(path) => path.to_string(),
+                // winapi uses the integer types for enums
+                None => match kind {
+                    TypeKind::Enum(underlying) => self.render(underlying),
+                    _ => self.unresolved(&ty.name),
+                },
+            },
+        }
+    }
+
+    fn unresolved(&mut self, name: &str) -> String {
+        self.unresolved.insert(name.to_string());
+        name.to_string()
+    }
+}
+
+/// The `winapi` path of common types from `Windows.Win32.Foundation` and friends
+fn winapi_path(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "BOOL" => "winapi::shared::minwindef::BOOL",
+        "BOOLEAN" => "winapi::shared::ntdef::BOOLEAN",
+        "HINSTANCE" => "winapi::shared::minwindef::HINSTANCE",
+        "HMODULE" => "winapi::shared::minwindef::HMODULE",
+        "HKEY" => "winapi::shared::minwindef::HKEY",
+        "HGLOBAL" => "winapi::shared::minwindef::HGLOBAL",
+        "HLOCAL" => "winapi::shared::minwindef::HLOCAL",
+        "FARPROC" => "winapi::shared::minwindef::FARPROC",
+        "LPARAM" => "winapi::shared::minwindef::LPARAM",
+        "WPARAM" => "winapi::shared::minwindef::WPARAM",
+        "LRESULT" => "winapi::shared::minwindef::LRESULT",
+        "FILETIME" => "winapi::shared::minwindef::FILETIME",
+        "HWND" => "winapi::shared::windef::HWND",
+        "HDC" => "winapi::shared::windef::HDC",
+        "HMENU" => "winapi::shared::windef::HMENU",
+        "HICON" => "winapi::shared::windef::HICON",
+        "HCURSOR" => "winapi::shared::windef::HCURSOR",
+        "HBRUSH" => "winapi::shared::windef::HBRUSH",
+        "HBITMAP" => "winapi::shared::windef::HBITMAP",
+        "HFONT" => "winapi::shared::windef::HFONT",
+        "HMONITOR" => "winapi::shared::windef::HMONITOR",
+        "COLORREF" => "winapi::shared::windef::COLORREF",
+        "RECT" => "winapi::shared::windef::RECT",
+        "POINT" => "winapi::shared::windef::POINT",
+        "SIZE" => "winapi::shared::windef::SIZE",
+        "NTSTATUS" => "winapi::shared::ntdef::NTSTATUS",
+        "UNICODE_STRING" => "winapi::shared::ntdef::UNICODE_STRING",
+        "HRESULT" => "winapi::shared::winerror::HRESULT",
+        "HANDLE" => "winapi::um::winnt::HANDLE",
+        "PSTR" => "winapi::um::winnt::LPSTR",
+        "PWSTR" => "winapi::um::winnt::LPWSTR",
+        "PCSTR" => "winapi::um::winnt::LPCSTR",
+        "PCWSTR" => "winapi::um::winnt::LPCWSTR",
+        "PSID" => "winapi::um::winnt::PSID",
+        "LUID" => "winapi::um::winnt::LUID",
+        "OSVERSIONINFOW" => "winapi::um::winnt::OSVERSIONINFOW",
+        "OSVERSIONINFOEXW" => "winapi::um::winnt::OSVERSIONINFOEXW",
+        "SYSTEMTIME" => "winapi::um::minwinbase::SYSTEMTIME",
+        "SECURITY_ATTRIBUTES" => "winapi::um::minwinbase::SECURITY_ATTRIBUTES",
+        "WNDPROC" => "winapi::um::winuser::WNDPROC",
+        "MSG" => "winapi::um::winuser::MSG",
+        _ => return None,
+    })
+}
+
+fn strip_dll(dll: &str) -> &str {
+    match dll.len().checked_sub(4) {
+        Some(stem) if dll.is_char_boundary(stem) && dll[stem..].eq_ignore_ascii_case(".dll") => {
+            &dll[..stem]
+        }
+        _ => dll,
+    }
+}
@@ -0,0 +1,222 @@
+mod common;
+
+use common::{build_dll, build_image_with_section, Target};
+use windows_dll_gen::{
+    apiset::{is_api_set, ApiSetHost, ApiSetSchema},
+    pe::PeFile,
+    resolve::{verify, Resolver},
+    rust::Link,
+    source::dll_imports,
+    Error,
+};
+
+/// An `API_SET_NAMESPACE` of version 6, without the hash table the parser doesn't use
+fn build_schema(api_sets: &[(&str, &[(&str, &str)])]) -> Vec<u8> {
+    let entries = 28;
+    let values = entries + api_sets.len() * 24;
+    let value_count: usize = api_sets.iter().map(|(_, hosts)| hosts.len()).sum();
+    let mut data = vec![0; values + value_count * 20];
+
+    put_u32(&mut data, 0, 6);
+    put_u32(&mut data, 12, api_sets.len() as u32);
+    put_u32(&mut data, 16, entries as u32);
+    put_u32(&mut data, 24, 0x1f);
+
+    let mut value = values;
+    for (i, (name, hosts)) in api_sets.iter().enumerate() {
+        let entry = entries + i * 24;
+        let hashed_len = name.rfind('-').unwrap() * 2;
+        put_u32(&mut data, entry, 1);
+        let (offset, len) = push_utf16(&mut data, name);
+        put_u32(&mut data, entry + 4, offset);
+        put_u32(&mut data, entry + 8, len);
+        put_u32(&mut data, entry + 12, hashed_len as u32);
+        put_u32(&mut data, entry + 16, value as u32);
+        put_u32(&mut data, entry + 20, hosts.len() as u32);
+
+        for (importer, host) in hosts.iter() {
+            let (offset, len) = push_utf16(&mut data, importer);
+            put_u32(&mut data, value + 4, offset);
+            put_u32(&mut data, value + 8, len);
+            let (offset, len) = push_utf16(&mut data, host);
+            put_u32(&mut data, value + 12, offset);
+            put_u32(&mut data, value + 16, len);
+            value += 20;
+        }
+    }
+    let size = data.len() as u32;
+    put_u32(&mut data, 4, size);
+    data
+}
+
+fn push_utf16(data: &mut Vec<u8>, s: &str) -> (u32, u32) {
+    let offset = data.len() as u32;
+    for unit in s.encode_utf16() {
+        data.extend_from_slice(&unit.to_le_bytes());
+    }
+    (offset, data.len() as u32 - offset)
+}
+
+fn put_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn test_schema() -> Vec<u8> {
+    build_schema(&[
+        ("api-ms-win-core-synch-l1-2-0", &[("", "kernelbase.dll")]),
+        (
+            "api-ms-win-core-heap-l1-2-0",
+            &[("", "kernelbase.dll"), ("kernelbase.dll", "ntdll.dll")],
+        ),
+        ("ext-ms-win-gdi-draw-l1-1-2", &[("", "")]),
+    ])
+}
+
+#[test]
+fn parse_schema() {
+    let schema = ApiSetSchema::parse(&test_schema()).unwrap();
+
+    assert_eq!(schema.api_sets.len(), 3);
+    let heap = &schema.api_sets[1];
+    assert_eq!(heap.name, "api-ms-win-core-heap-l1-2-0");
+    assert_eq!(heap.hashed_len, "api-ms-win-core-heap-l1-2".len());
+    assert!(heap.sealed);
+    assert_eq!(
+        heap.hosts,
+        vec![
+            ApiSetHost {
+                importer: None,
+                host: "kernelbase.dll".to_string(),
+            },
+            ApiSetHost {
+                importer: Some("kernelbase.dll".to_string()),
+                host: "ntdll.dll".to_string(),
+            },
+        ]
+    );
+    assert!(schema.api_sets[2].hosts.is_empty());
+}
+
+#[test]
+fn parse_invalid() {
+    let mut data = test_schema();
+    data[0] = 4;
+    assert!(matches!(ApiSetSchema::parse(&data), Err(Error::ApiSet(_))));
+
+    let mut data = test_schema();
+    data.truncate(100);
+    assert!(matches!(ApiSetSchema::parse(&data), Err(Error::ApiSet(_))));
+}
+
+#[test]
+fn resolve_api_sets() {
+    let image = build_image_with_section(b".apiset", &test_schema(), &[]);
+    let schema = ApiSetSchema::from_file_data(&image).unwrap();
+    assert_eq!(schema, ApiSetSchema::parse(&test_schema()).unwrap());
+
+    assert!(is_api_set("API-MS-Win-Core-Synch-L1-2-0.dll"));
+    assert!(!is_api_set("kernel32.dll"));
+
+    // Any minor version, with or without `.dll`
+    assert_eq!(
+        schema.resolve("API-MS-WIN-CORE-SYNCH-L1-2-0.dll", None),
+        Some("kernelbase.dll")
+    );
+    assert_eq!(
+        schema.resolve("api-ms-win-core-synch-l1-2-1", None),
+        Some("kernelbase.dll")
+    );
+    assert_eq!(schema.resolve("api-ms-win-core-synch-l1-3-0", None), None);
+
+    // Imported by the host itself
+    assert_eq!(
+        schema.resolve("api-ms-win-core-heap-l1-2-0", Some("KernelBase.dll")),
+        Some("ntdll.dll")
+    );
+    assert_eq!(
+        schema.resolve("api-ms-win-core-heap-l1-2-0", Some("user32.dll")),
+        Some("kernelbase.dll")
+    );
+
+    // Not implemented
+    assert_eq!(schema.resolve("ext-ms-win-gdi-draw-l1-1-2", None), None);
+}
+
+#[test]
+fn verify_api_sets() {
+    let mut resolver = Resolver::new();
+    let kernelbase = build_dll(
+        "KERNELBASE.dll",
+        &[
+            (Some("WaitOnAddress"), Target::Function),
+            (
+                Some("HeapAlloc"),
+                Target::Forwarder("api-ms-win-core-heap-l1-2-0.HeapAlloc"),
+            ),
+        ],
+    );
+    let ntdll = build_dll("ntdll.dll", &[(Some("HeapAlloc"), Target::Function)]);
+    resolver.add(
+        "KernelBase.dll",
+        PeFile::parse(&kernelbase).unwrap().exports().unwrap(),
+    );
+    resolver.add(
+        "ntdll.dll",
+        PeFile::parse(&ntdll).unwrap().exports().unwrap(),
+    );
+
+    let link = Link::Name("WaitOnAddress".to_string());
+    assert!(matches!(
+        resolver.resolve("api-ms-win-core-synch-l1-2-0", &link),
+        Err(Error::NotFound(_))
+    ));
+
+    resolver.set_api_sets(ApiSetSchema::parse(&test_schema()).unwrap());
+    let source = r#"
+        #[dll("api-ms-win-core-synch-l1-2-0")]
+        extern "system" {
+            fn WaitOnAddress(address: *const c_void, compare: *const c_void, size: usize, ms: u32) -> BOOL;
+        }
+        #[dll(kernelbase)]
+        extern "system" {
+            fn HeapAlloc(heap: HANDLE, flags: u32, size: usize) -> *mut c_void;
+        }
+    "#;
+    let verification = verify(&resolver, &dll_imports(source).unwrap());
+
+    assert_eq!(verification.unresolved, 0);
+    assert_eq!(
+        verification.report,
+        "api-ms-win-core-synch-l1-2-0
+    WaitOnAddress -> KernelBase.dll!WaitOnAddress
+kernelbase
+    HeapAlloc -> ntdll.dll!HeapAlloc
+"
+    );
+}
+
+#[test]
+fn unsupported_schema() {
+    let dir = std::env::temp_dir().join(format!("windows-dll-gen-apiset-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // A dll without an `.apiset` section, like the schemas before Windows 10
+    let schema = build_dll("apisetschema.dll", &[(Some("Unused"), Target::Function)]);
+    std::fs::write(dir.join("apisetschema.dll"), schema).unwrap();
+    let kernelbase = build_dll(
+        "KERNELBASE.dll",
+        &[(Some("WaitOnAddress"), Target::Function)],
+    );
+    std::fs::write(dir.join("KERNELBASE.dll"), kernelbase).unwrap();
+
+    let resolver = Resolver::from_dir(&dir).unwrap();
+    assert_eq!(resolver.warnings().len(), 1);
+    assert!(resolver.warnings()[0].starts_with("API sets are left unresolved"));
+    let link = Link::Name("WaitOnAddress".to_string());
+    assert!(resolver.resolve("kernelbase", &link).is_ok());
+    assert!(matches!(
+        resolver.resolve("api-ms-win-core-synch-l1-2-0", &link),
+        Err(Error::NotFound(_))
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
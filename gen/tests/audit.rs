@@ -0,0 +1,98 @@
+mod common;
+
+use common::{build_image, build_image_with_section};
+use windows_dll_gen::{
+    audit::{manifest, parse_records, runtime_dlls, RuntimeDll},
+    pe::PeFile,
+    rust::Link,
+    Error,
+};
+
+/// A record as written by the `audit` feature of windows-dll
+fn record(lib: &str, procs: &[Link]) -> Vec<u8> {
+    let mut record = b"WDLL\x01".to_vec();
+    record.extend_from_slice(&(lib.len() as u16).to_le_bytes());
+    record.extend_from_slice(lib.as_bytes());
+    record.extend_from_slice(&(procs.len() as u16).to_le_bytes());
+    for proc in procs {
+        match proc {
+            Link::Name(name) => {
+                record.push(0);
+                record.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                record.extend_from_slice(name.as_bytes());
+            }
+            Link::Ordinal(ordinal) => {
+                record.push(1);
+                record.extend_from_slice(&ordinal.to_le_bytes());
+            }
+        }
+    }
+    record
+}
+
+fn name(name: &str) -> Link {
+    Link::Name(name.to_string())
+}
+
+#[test]
+fn binary_runtime_dlls() {
+    let mut section = record("user32", &[name("GetDesktopWindow"), Link::Ordinal(2)]);
+    // Padding between the records of different object files
+    section.extend_from_slice(&[0; 11]);
+    section.extend(record("bcrypt.dll", &[name("BCryptGenRandom")]));
+    section.extend(record(
+        "USER32",
+        &[name("GetShellWindow"), Link::Ordinal(2)],
+    ));
+
+    let image = build_image_with_section(b".wdll", &section, &[]);
+    let dlls = runtime_dlls(&PeFile::parse(&image).unwrap()).unwrap();
+
+    assert_eq!(
+        dlls,
+        vec![
+            RuntimeDll {
+                lib: "user32".to_string(),
+                procs: vec![
+                    name("GetDesktopWindow"),
+                    Link::Ordinal(2),
+                    name("GetShellWindow")
+                ],
+            },
+            RuntimeDll {
+                lib: "bcrypt.dll".to_string(),
+                procs: vec![name("BCryptGenRandom")],
+            },
+        ]
+    );
+    assert_eq!(
+        manifest(&dlls),
+        "user32
+    GetDesktopWindow
+    #2
+    GetShellWindow
+bcrypt.dll
+    BCryptGenRandom
+"
+    );
+}
+
+#[test]
+fn binary_without_records() {
+    let image = build_image(&[0; 16], &[]);
+
+    assert!(matches!(
+        runtime_dlls(&PeFile::parse(&image).unwrap()),
+        Err(Error::NotFound(section)) if section == ".wdll"
+    ));
+}
+
+#[test]
+fn truncated_record() {
+    let section = record("user32", &[name("GetDesktopWindow")]);
+
+    assert!(matches!(
+        parse_records(&section[..section.len() - 3]),
+        Err(Error::Audit(_))
+    ));
+}
@@ -0,0 +1,234 @@
+// Writes minimal ECMA-335 metadata, with only the tables read by the generator
+
+use super::{build_image, put_u32, RDATA_RVA};
+
+// Element types used in signatures
+pub const CHAR: u8 = 0x03;
+pub const I4: u8 = 0x08;
+pub const U4: u8 = 0x09;
+pub const PTR: u8 = 0x0f;
+pub const VALUETYPE: u8 = 0x11;
+pub const CLASS: u8 = 0x12;
+pub const I: u8 = 0x18;
+
+pub const PARAM_IN: u16 = 0x0001;
+pub const PARAM_OUT: u16 = 0x0002;
+
+pub const CALL_CONV_WINAPI: u16 = 0x0100;
+pub const CALL_CONV_CDECL: u16 = 0x0200;
+
+const MODULE: usize = 0x00;
+const TYPE_REF: usize = 0x01;
+const TYPE_DEF: usize = 0x02;
+const FIELD: usize = 0x04;
+const METHOD_DEF: usize = 0x06;
+const PARAM: usize = 0x08;
+const MODULE_REF: usize = 0x1a;
+const IMPL_MAP: usize = 0x1c;
+
+enum Cell {
+    U16(u16),
+    U32(u32),
+}
+
+pub struct MetadataBuilder {
+    strings: Vec<u8>,
+    blobs: Vec<u8>,
+    tables: Vec<Vec<Vec<Cell>>>,
+}
+
+impl MetadataBuilder {
+    pub fn new() -> Self {
+        let mut builder = Self {
+            strings: vec![0],
+            blobs: vec![0],
+            tables: (0..64).map(|_| Vec::new()).collect(),
+        };
+        let name = builder.string("test.winmd");
+        builder.tables[MODULE].push(vec![
+            Cell::U16(0),
+            Cell::U16(name),
+            Cell::U16(0),
+            Cell::U16(0),
+            Cell::U16(0),
+        ]);
+        builder
+    }
+
+    fn string(&mut self, s: &str) -> u16 {
+        let index = self.strings.len() as u16;
+        self.strings.extend_from_slice(s.as_bytes());
+        self.strings.push(0);
+        index
+    }
+
+    fn blob(&mut self, blob: &[u8]) -> u16 {
+        let index = self.blobs.len() as u16;
+        self.blobs.push(blob.len() as u8);
+        self.blobs.extend_from_slice(blob);
+        index
+    }
+
+    fn push(&mut self, table: usize, row: Vec<Cell>) -> u16 {
+        self.tables[table].push(row);
+        self.tables[table].len() as u16
+    }
+
+    /// A type from another metadata file, returns its `TypeDefOrRef` token for signatures
+    pub fn type_ref(&mut self, namespace: &str, name: &str) -> u8 {
+        let name = self.string(name);
+        let namespace = self.string(namespace);
+        // The resolution scope is the module
+        let row = self.push(
+            TYPE_REF,
+            vec![Cell::U16(1 << 2), Cell::U16(name), Cell::U16(namespace)],
+        );
+        (row << 2 | 1) as u8
+    }
+
+    /// A type extending `extends`, with fields of the given signatures
+    pub fn type_def(
+        &mut self,
+        namespace: &str,
+        name: &str,
+        extends: Option<u8>,
+        fields: &[(&str, &[u8])],
+    ) -> u8 {
+        let field_list = self.tables[FIELD].len() as u16 + 1;
+        for (field, signature) in fields {
+            let field = self.string(field);
+            let signature = self.blob(signature);
+            self.push(
+                FIELD,
+                vec![Cell::U16(0x0006), Cell::U16(field), Cell::U16(signature)],
+            );
+        }
+
+        let name = self.string(name);
+        let namespace = self.string(namespace);
+        let method_list = self.tables[METHOD_DEF].len() as u16 + 1;
+        let row = self.push(
+            TYPE_DEF,
+            vec![
+                Cell::U32(0),
+                Cell::U16(name),
+                Cell::U16(namespace),
+                Cell::U16(extends.map_or(0, u16::from)),
+                Cell::U16(field_list),
+                Cell::U16(method_list),
+            ],
+        );
+        (row << 2) as u8
+    }
+
+    /// A function imported from `dll`, added to the last type
+    pub fn function(
+        &mut self,
+        dll: &str,
+        name: &str,
+        call_conv: u16,
+        signature: &[u8],
+        params: &[(&str, u16)],
+    ) {
+        let param_list = self.tables[PARAM].len() as u16 + 1;
+        for (sequence, (param, flags)) in params.iter().enumerate() {
+            let param = self.string(param);
+            self.push(
+                PARAM,
+                vec![
+                    Cell::U16(*flags),
+                    Cell::U16(sequence as u16 + 1),
+                    Cell::U16(param),
+                ],
+            );
+        }
+
+        let name = self.string(name);
+        let signature = self.blob(signature);
+        let method = self.push(
+            METHOD_DEF,
+            vec![
+                Cell::U32(0),
+                Cell::U16(0x0080),
+                Cell::U16(0x2096),
+                Cell::U16(name),
+                Cell::U16(signature),
+                Cell::U16(param_list),
+            ],
+        );
+
+        let dll = self.string(dll);
+        let module = self.push(MODULE_REF, vec![Cell::U16(dll)]);
+        self.push(
+            IMPL_MAP,
+            vec![
+                Cell::U16(call_conv),
+                Cell::U16(method << 1 | 1),
+                Cell::U16(name),
+                Cell::U16(module),
+            ],
+        );
+    }
+
+    /// A `.winmd` file with the metadata
+    pub fn build(self) -> Vec<u8> {
+        let mut tables = vec![0, 0, 0, 0, 2, 0, 0, 1];
+        let valid = self
+            .tables
+            .iter()
+            .enumerate()
+            .filter(|(_, rows)| !rows.is_empty())
+            .fold(0u64, |valid, (table, _)| valid | 1 << table);
+        tables.extend_from_slice(&valid.to_le_bytes());
+        tables.extend_from_slice(&0u64.to_le_bytes());
+        for rows in self.tables.iter().filter(|rows| !rows.is_empty()) {
+            tables.extend_from_slice(&(rows.len() as u32).to_le_bytes());
+        }
+        for cell in self.tables.iter().flatten().flatten() {
+            match cell {
+                Cell::U16(value) => tables.extend_from_slice(&value.to_le_bytes()),
+                Cell::U32(value) => tables.extend_from_slice(&value.to_le_bytes()),
+            }
+        }
+
+        let streams: [(&str, Vec<u8>); 3] = [
+            ("#~", tables),
+            ("#Strings", self.strings),
+            ("#Blob", self.blobs),
+        ];
+        let version = b"v4.0.30319\0\0";
+        let mut root = Vec::new();
+        root.extend_from_slice(&0x424a_5342u32.to_le_bytes());
+        root.extend_from_slice(&[1, 0, 1, 0, 0, 0, 0, 0]);
+        root.extend_from_slice(&(version.len() as u32).to_le_bytes());
+        root.extend_from_slice(version);
+        root.extend_from_slice(&[0, 0, streams.len() as u8, 0]);
+
+        let headers_size: usize = streams
+            .iter()
+            .map(|(name, _)| 8 + (name.len() + 4) / 4 * 4)
+            .sum();
+        let mut offset = root.len() + headers_size;
+        for (name, stream) in &streams {
+            let size = stream.len().div_ceil(4) * 4;
+            root.extend_from_slice(&(offset as u32).to_le_bytes());
+            root.extend_from_slice(&(size as u32).to_le_bytes());
+            root.extend_from_slice(name.as_bytes());
+            root.resize(root.len() + 4 - name.len() % 4, 0);
+            offset += size;
+        }
+        for (_, stream) in &streams {
+            root.extend_from_slice(stream);
+            root.resize(root.len().div_ceil(4) * 4, 0);
+        }
+
+        // The CLI header, followed by the metadata
+        let mut rdata = vec![0; 72];
+        put_u32(&mut rdata, 0, 72);
+        put_u32(&mut rdata, 8, RDATA_RVA + 72);
+        put_u32(&mut rdata, 12, root.len() as u32);
+        rdata.extend_from_slice(&root);
+
+        build_image(&rdata, &[(14, RDATA_RVA, 72)])
+    }
+}
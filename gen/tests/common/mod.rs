@@ -0,0 +1,174 @@
+// Builds minimal PE32+ dlls, so the tests don't depend on files from a Windows install
+
+#![allow(dead_code)]
+
+pub mod metadata;
+
+pub enum Target {
+    Function,
+    Data,
+    Forwarder(&'static str),
+}
+
+const TEXT_RVA: u32 = 0x1000;
+pub const RDATA_RVA: u32 = 0x2000;
+const FILE_ALIGNMENT: usize = 0x200;
+
+/// A dll exporting `exports` from ordinal 1, with `None` names for ordinal-only exports
+pub fn build_dll(dll_name: &str, exports: &[(Option<&str>, Target)]) -> Vec<u8> {
+    let (rdata, export_directory_size) = export_section(dll_name, exports);
+    build_image(&rdata, &[(0, RDATA_RVA, export_directory_size)])
+}
+
+/// An image with some code and `rdata` in a .rdata section, the data directories are
+/// `(index, rva, size)` triples
+pub fn build_image(rdata: &[u8], directories: &[(usize, u32, u32)]) -> Vec<u8> {
+    build_image_with_section(b".rdata", rdata, directories)
+}
+
+/// The same image, with the data section named `name`
+pub fn build_image_with_section(
+    name: &[u8],
+    rdata: &[u8],
+    directories: &[(usize, u32, u32)],
+) -> Vec<u8> {
+    let mut file = vec![0; FILE_ALIGNMENT];
+    file[..2].copy_from_slice(b"MZ");
+    put_u32(&mut file, 0x3c, 0x40);
+
+    let pe = 0x40;
+    file[pe..pe + 4].copy_from_slice(b"PE\0\0");
+    let coff = pe + 4;
+    put_u16(&mut file, coff, 0x8664);
+    put_u16(&mut file, coff + 2, 2);
+    put_u16(&mut file, coff + 16, 112 + 16 * 8);
+    put_u16(&mut file, coff + 18, 0x2022);
+
+    let optional = coff + 20;
+    put_u16(&mut file, optional, 0x20b);
+    put_u32(&mut file, optional + 108, 16);
+    for &(index, rva, size) in directories {
+        put_u32(&mut file, optional + 112 + index * 8, rva);
+        put_u32(&mut file, optional + 116 + index * 8, size);
+    }
+
+    let sections = optional + 112 + 16 * 8;
+    let text_size = FILE_ALIGNMENT;
+    let rdata_size = align(rdata.len());
+    section(
+        &mut file,
+        sections,
+        b".text",
+        TEXT_RVA,
+        text_size,
+        text_size,
+        0x6000_0020,
+    );
+    section(
+        &mut file,
+        sections + 40,
+        name,
+        RDATA_RVA,
+        rdata_size,
+        2 * FILE_ALIGNMENT,
+        0x4000_0040,
+    );
+
+    file.resize(2 * FILE_ALIGNMENT, 0xcc);
+    file.extend_from_slice(rdata);
+    file.resize(2 * FILE_ALIGNMENT + rdata_size, 0);
+    file
+}
+
+// The contents of the .rdata section and the size of the export directory in it
+fn export_section(dll_name: &str, exports: &[(Option<&str>, Target)]) -> (Vec<u8>, u32) {
+    let mut names: Vec<(&str, u16)> = exports
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (name, _))| name.map(|name| (name, index as u16)))
+        .collect();
+    names.sort();
+
+    let functions = 40;
+    let name_pointers = functions + exports.len() * 4;
+    let name_ordinals = name_pointers + names.len() * 4;
+    let mut data = vec![0; name_ordinals + names.len() * 2];
+
+    let dll_name_rva = push_str(&mut data, dll_name);
+    put_u32(&mut data, 12, dll_name_rva);
+    put_u32(&mut data, 16, 1);
+    put_u32(&mut data, 20, exports.len() as u32);
+    put_u32(&mut data, 24, names.len() as u32);
+    put_u32(&mut data, 28, RDATA_RVA + functions as u32);
+    put_u32(&mut data, 32, RDATA_RVA + name_pointers as u32);
+    put_u32(&mut data, 36, RDATA_RVA + name_ordinals as u32);
+
+    for (i, (name, index)) in names.iter().enumerate() {
+        let rva = push_str(&mut data, name);
+        put_u32(&mut data, name_pointers + i * 4, rva);
+        put_u16(&mut data, name_ordinals + i * 2, *index);
+    }
+
+    let mut forwarders = Vec::new();
+    for (index, (_, target)) in exports.iter().enumerate() {
+        if let Target::Forwarder(forwarder) = target {
+            forwarders.push((index, push_str(&mut data, forwarder)));
+        }
+    }
+    let directory_size = data.len() as u32;
+
+    for (index, (_, target)) in exports.iter().enumerate() {
+        let rva = match target {
+            Target::Function => TEXT_RVA + index as u32 * 16,
+            Target::Data => {
+                let rva = RDATA_RVA + data.len() as u32;
+                data.extend_from_slice(&[0; 8]);
+                rva
+            }
+            Target::Forwarder(_) => {
+                forwarders
+                    .iter()
+                    .find(|(forwarded, _)| *forwarded == index)
+                    .unwrap()
+                    .1
+            }
+        };
+        put_u32(&mut data, functions + index * 4, rva);
+    }
+
+    (data, directory_size)
+}
+
+fn push_str(data: &mut Vec<u8>, s: &str) -> u32 {
+    let rva = RDATA_RVA + data.len() as u32;
+    data.extend_from_slice(s.as_bytes());
+    data.push(0);
+    rva
+}
+
+fn section(
+    file: &mut [u8],
+    offset: usize,
+    name: &[u8],
+    rva: u32,
+    size: usize,
+    raw_offset: usize,
+    characteristics: u32,
+) {
+    file[offset..offset + name.len()].copy_from_slice(name);
+    put_u32(file, offset + 8, size as u32);
+    put_u32(file, offset + 12, rva);
+    put_u32(file, offset + 16, size as u32);
+    put_u32(file, offset + 20, raw_offset as u32);
+    put_u32(file, offset + 36, characteristics);
+}
+
+fn align(size: usize) -> usize {
+    size.div_ceil(FILE_ALIGNMENT) * FILE_ALIGNMENT
+}
+pub fn put_u16(data: &mut [u8], offset: usize, value: u16) {
+    data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+pub fn put_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use common::{build_dll, put_u32, Target};
+use regex::Regex;
+use std::{fs, process::Command};
+use windows_dll_gen::{
+    exports::{extern_block, ExportsOptions},
+    pe::{Export, ExportTarget, PeFile},
+    Error,
+};
+
+fn test_dll() -> Vec<u8> {
+    build_dll(
+        "test.dll",
+        &[
+            (Some("CreateWidget"), Target::Function),
+            (Some("destroy_widget"), Target::Function),
+            (None, Target::Function),
+            (Some("WidgetCount"), Target::Data),
+            (
+                Some("HeapAlloc"),
+                Target::Forwarder("NTDLL.RtlAllocateHeap"),
+            ),
+            (Some("?create@Widget@@QEAAXXZ"), Target::Function),
+            (Some("type"), Target::Function),
+        ],
+    )
+}
+
+#[test]
+fn parse_exports() {
+    let data = test_dll();
+    let pe = PeFile::parse(&data).unwrap();
+    assert!(pe.is_64());
+    assert_eq!(pe.machine(), 0x8664);
+
+    let exports = pe.exports().unwrap();
+    assert_eq!(exports.dll_name, "test.dll");
+    assert_eq!(exports.exports.len(), 7);
+    assert_eq!(
+        exports.exports[0],
+        Export {
+            ordinal: 1,
+            name: Some("CreateWidget".to_string()),
+            target: ExportTarget::Function(0x1000),
+        }
+    );
+    assert_eq!(exports.exports[2].ordinal, 3);
+    assert_eq!(exports.exports[2].name, None);
+    assert!(matches!(exports.exports[3].target, ExportTarget::Data(_)));
+    assert_eq!(
+        exports.exports[4].target,
+        ExportTarget::Forwarder("NTDLL.RtlAllocateHeap".to_string())
+    );
+}
+
+#[test]
+fn parse_invalid() {
+    assert!(PeFile::parse(b"not a dll").is_err());
+
+    let mut data = test_dll();
+    data.truncate(0x100);
+    assert!(PeFile::parse(&data).is_err());
+}
+
+#[test]
+fn crafted_export_directory() {
+    // The export directory is at the start of the .rdata section, at 0x400 in the file
+    let exports = |offset: usize, value| {
+        let mut data = test_dll();
+        put_u32(&mut data, 0x400 + offset, value);
+        PeFile::parse(&data).unwrap().exports()
+    };
+
+    // `NumberOfFunctions` and `NumberOfNames` far beyond the section
+    assert!(matches!(exports(20, u32::MAX), Err(Error::Pe(_))));
+    assert!(matches!(exports(20, 0x4000_0000), Err(Error::Pe(_))));
+    assert!(matches!(exports(24, 0x1000_0000), Err(Error::Pe(_))));
+    // `AddressOfNames` past the end of the address space
+    assert!(matches!(exports(32, u32::MAX - 2), Err(Error::Pe(_))));
+    // `Base` overflowing the ordinals
+    assert!(matches!(exports(16, u32::MAX), Err(Error::Pe(_))));
+}
+
+#[test]
+fn render_exports() {
+    let data = test_dll();
+    let exports = PeFile::parse(&data).unwrap().exports().unwrap();
+    let block = extern_block(&exports, &ExportsOptions::default());
+
+    assert_eq!(
+        block.render(),
+        r#"use windows_dll::dll;
+
+#[dll(test)]
+extern "system" {
+    #[allow(non_snake_case)]
+    fn CreateWidget(/* TODO */);
+    fn destroy_widget(/* TODO */);
+    #[link_ordinal = 3]
+    fn ordinal_3(/* TODO */);
+    static WidgetCount: u8 /* TODO */;
+    // Forwarded to NTDLL.RtlAllocateHeap
+    #[allow(non_snake_case)]
+    fn HeapAlloc(/* TODO */);
+    #[link_name = "?create@Widget@@QEAAXXZ"]
+    #[allow(non_snake_case)]
+    fn create_Widget_QEAAXXZ(/* TODO */);
+    #[link_name = "type"]
+    fn type_(/* TODO */);
+}
+"#
+    );
+}
+
+#[test]
+fn filter_exports() {
+    let data = test_dll();
+    let exports = PeFile::parse(&data).unwrap().exports().unwrap();
+    let options = ExportsOptions {
+        filter: Some(Regex::new("^(destroy_|#3$)").unwrap()),
+        dll_name: Some("widgets.dll".to_string()),
+    };
+
+    assert_eq!(
+        extern_block(&exports, &options).render(),
+        r#"use windows_dll::dll;
+
+#[dll(widgets)]
+extern "system" {
+    fn destroy_widget(/* TODO */);
+    #[link_ordinal = 3]
+    fn ordinal_3(/* TODO */);
+}
+"#
+    );
+}
+
+#[test]
+fn cli() {
+    let dir = std::env::temp_dir().join(format!("windows-dll-gen-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("test.dll");
+    let output = dir.join("test.rs");
+    fs::write(&input, test_dll()).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_windows-dll-gen"))
+        .arg("exports")
+        .arg(&input)
+        .args(["--filter", "^destroy_", "-o"])
+        .arg(&output)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(fs::read_to_string(&output)
+        .unwrap()
+        .contains("fn destroy_widget(/* TODO */);"));
+
+    let status = Command::new(env!("CARGO_BIN_EXE_windows-dll-gen"))
+        .arg("imports")
+        .output()
+        .unwrap();
+    assert!(!status.status.success());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,113 @@
+use regex::Regex;
+use windows_dll_gen::{
+    header::{extern_blocks, HeaderOptions},
+    rust::render_blocks,
+};
+
+const WIDGETS_H: &str = include_str!("headers/widgets.h");
+
+#[test]
+fn widgets_header() {
+    let blocks = extern_blocks(WIDGETS_H, "widgets.dll", &HeaderOptions::default()).unwrap();
+
+    assert_eq!(
+        render_blocks(&blocks),
+        r#"use windows_dll::dll;
+
+#[dll(widgets)]
+pub mod widgets {
+    // TODO: define WIDGET_INFO
+    extern "system" {
+        pub fn widget_create(name: *const i8, color: i32) -> *mut std::ffi::c_void;
+        pub fn widget_destroy(widget: *mut std::ffi::c_void);
+        #[allow(non_snake_case)]
+        pub fn WidgetGetInfo(widget: *mut std::ffi::c_void, info: *mut WIDGET_INFO) -> i32;
+        pub fn widget_on_click(arg0: *mut std::ffi::c_void, callback: Option<unsafe extern "system" fn(*mut std::ffi::c_void, *mut std::ffi::c_void)>, user_data: *mut std::ffi::c_void) -> i32;
+    }
+
+    extern "C" {
+        pub fn widget_printf(widget: *mut std::ffi::c_void, format: *const u16, ...) -> i32;
+        pub fn widget_count() -> u64;
+        pub fn widget_default_info() -> *const WIDGET_INFO;
+        pub fn widget_set_ids(ids: *mut u32, count: usize, flags: u8) -> i16;
+    }
+}
+"#
+    );
+}
+
+#[test]
+fn filter_and_defines() {
+    let options = HeaderOptions {
+        filter: Some(Regex::new("^widget_(create|count)$").unwrap()),
+        defines: vec![("WIDGETS_CALL".to_string(), "__cdecl".to_string())],
+        ..HeaderOptions::default()
+    };
+    let blocks = extern_blocks(WIDGETS_H, "widgets.dll", &options).unwrap();
+
+    assert_eq!(
+        render_blocks(&blocks),
+        r#"use windows_dll::dll;
+
+#[dll(widgets)]
+extern "C" {
+    fn widget_create(name: *const i8, color: i32) -> *mut std::ffi::c_void;
+    fn widget_count() -> u64;
+}
+"#
+    );
+}
+
+#[test]
+fn ignored_macros() {
+    let header = "
+        typedef struct { int x, y; } Point;
+        EXPORT Point * STDCALL point_new(int x, int y);
+        EXPORT void STDCALL point_free(Point *);
+    ";
+
+    // Unknown macros make the declarations unparseable
+    let blocks = extern_blocks(header, "points", &HeaderOptions::default()).unwrap();
+    assert!(blocks.is_empty());
+
+    let options = HeaderOptions {
+        ignore: vec!["EXPORT".to_string()],
+        defines: vec![("STDCALL".to_string(), "__stdcall".to_string())],
+        ..HeaderOptions::default()
+    };
+    let blocks = extern_blocks(header, "points", &options).unwrap();
+    assert_eq!(
+        render_blocks(&blocks),
+        r#"use windows_dll::dll;
+
+// TODO: define Point
+#[dll(points)]
+extern "system" {
+    fn point_new(x: i32, y: i32) -> *mut Point;
+    fn point_free(arg0: *mut Point);
+}
+"#
+    );
+}
+
+#[test]
+fn build_script() {
+    let out_dir = std::env::temp_dir().join(format!("windows-dll-gen-out-{}", std::process::id()));
+    std::fs::create_dir_all(&out_dir).unwrap();
+    std::env::set_var("OUT_DIR", &out_dir);
+
+    let header = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/headers/widgets.h");
+    let path = windows_dll_gen::header::generate_in_out_dir(
+        header,
+        "widgets.dll",
+        &HeaderOptions::default(),
+        "widgets.rs",
+    )
+    .unwrap();
+
+    assert_eq!(path, out_dir.join("widgets.rs"));
+    let generated = std::fs::read_to_string(&path).unwrap();
+    assert!(generated.contains("pub fn widget_destroy(widget: *mut std::ffi::c_void);"));
+
+    std::fs::remove_dir_all(&out_dir).unwrap();
+}
@@ -0,0 +1,55 @@
+/* Widgets SDK 2.1 */
+#ifndef WIDGETS_H
+#define WIDGETS_H
+
+#include <windows.h>
+
+#ifdef WIDGETS_EXPORTS
+#define WIDGETS_API __declspec(dllexport)
+#else
+#define WIDGETS_API __declspec(dllimport)
+#endif
+#define WIDGETS_CALL __stdcall
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct widget_s *widget_t;
+typedef unsigned int widget_id;
+
+typedef enum {
+    WIDGET_RED,
+    WIDGET_BLUE = 2,
+} widget_color;
+
+typedef struct _WIDGET_INFO {
+    DWORD size;
+    widget_color color;
+    WCHAR name[64];
+} WIDGET_INFO, *PWIDGET_INFO;
+
+typedef void (CALLBACK *widget_callback)(widget_t widget, void *user_data);
+
+// Create a widget, returns NULL on failure
+WIDGETS_API widget_t WIDGETS_CALL widget_create(const char *name, widget_color color);
+WIDGETS_API void WIDGETS_CALL widget_destroy(widget_t widget);
+WIDGETS_API BOOL WIDGETS_CALL WidgetGetInfo(
+    _In_ widget_t widget,
+    _Out_writes_bytes_(size) PWIDGET_INFO info
+);
+WIDGETS_API int __cdecl widget_printf(widget_t widget, const wchar_t *format, ...);
+WIDGETS_API unsigned long long widget_count(void);
+WIDGETS_API HRESULT WINAPI widget_on_click(widget_t, widget_callback callback, LPVOID user_data);
+WIDGETS_API const struct _WIDGET_INFO *widget_default_info(void);
+WIDGETS_API short int widget_set_ids(widget_id ids[], size_t count, unsigned char flags);
+
+static inline int widget_is_valid(widget_t widget) {
+    return widget != NULL;
+}
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
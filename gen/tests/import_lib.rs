@@ -0,0 +1,196 @@
+use std::convert::TryInto;
+use windows_dll_gen::{
+    import_lib::{def_file, import_library, Machine},
+    source::{dll_imports, DllImports, Import},
+};
+
+const SOURCE: &str = r#"
+use windows_dll::dll;
+
+#[dll(user32)]
+extern "system" {
+    fn MessageBoxW(hwnd: HWND, text: *const u16, caption: *const u16, kind: u32) -> i32;
+    #[link_name = "GetDpiForWindow"]
+    fn dpi_for_window(hwnd: HWND) -> u32;
+}
+
+#[dll("comctl32.dll", type_name = ComCtl)]
+mod comctl {
+    extern "system" {
+        #[link_ordinal(345)]
+        fn TaskDialogIndirect();
+    }
+    extern "C" {
+        static mut ComCtlVersion: u32;
+    }
+}
+
+fn main() {
+    #[dll(type_name = ComCtl)]
+    extern "system" {
+        #[link_ordinal = 17]
+        fn InitCommonControls();
+    }
+}
+"#;
+
+fn import(name: &str, ordinal: Option<u16>, data: bool) -> Import {
+    Import {
+        name: name.to_string(),
+        ordinal,
+        data,
+    }
+}
+
+#[test]
+fn source_imports() {
+    assert_eq!(
+        dll_imports(SOURCE).unwrap(),
+        vec![
+            DllImports {
+                dll: "user32".to_string(),
+                imports: vec![
+                    import("MessageBoxW", None, false),
+                    import("GetDpiForWindow", None, false),
+                ],
+            },
+            DllImports {
+                dll: "comctl32.dll".to_string(),
+                imports: vec![
+                    import("TaskDialogIndirect", Some(345), false),
+                    import("ComCtlVersion", None, true),
+                    import("InitCommonControls", Some(17), false),
+                ],
+            },
+        ]
+    );
+}
+
+#[test]
+fn module_definition() {
+    let dlls = dll_imports(SOURCE).unwrap();
+
+    assert_eq!(
+        def_file(&dlls[0]),
+        "LIBRARY user32.dll\nEXPORTS\n    MessageBoxW\n    GetDpiForWindow\n"
+    );
+    assert_eq!(
+        def_file(&dlls[1]),
+        "LIBRARY comctl32.dll
+EXPORTS
+    TaskDialogIndirect @345 NONAME
+    ComCtlVersion DATA
+    InitCommonControls @17 NONAME
+"
+    );
+}
+
+/// The name and data of the members of an archive
+fn members(archive: &[u8]) -> Vec<(String, &[u8])> {
+    assert_eq!(&archive[..8], b"!<arch>\n");
+    let mut members = Vec::new();
+    let mut offset = 8;
+    while offset < archive.len() {
+        let header = std::str::from_utf8(&archive[offset..offset + 60]).unwrap();
+        assert!(header.ends_with("`\n"));
+        let size: usize = header[48..58].trim().parse().unwrap();
+        let data = &archive[offset + 60..offset + 60 + size];
+        members.push((header[..16].trim_end().to_string(), data));
+        offset += 60 + size + size % 2;
+    }
+    members
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// The symbols of the second linker member, with the index of the member defining them
+fn symbols(linker_member: &[u8]) -> Vec<(String, u16)> {
+    let u32_at =
+        |offset: usize| u32::from_le_bytes(linker_member[offset..offset + 4].try_into().unwrap());
+    let indices = 4 + 4 * u32_at(0) as usize;
+    let count = u32_at(indices) as usize;
+    let names = linker_member[indices + 4 + 2 * count..]
+        .split(|byte| *byte == 0)
+        .map(|name| String::from_utf8_lossy(name).into_owned());
+    (0..count)
+        .map(|index| u16_at(linker_member, indices + 4 + 2 * index))
+        .zip(names)
+        .map(|(member, name)| (name, member))
+        .collect()
+}
+
+#[test]
+fn import_library_members() {
+    let dlls = dll_imports(SOURCE).unwrap();
+    let library = import_library(&dlls[1], Machine::X64);
+    let members = members(&library);
+
+    let names = members
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec![
+            "/",
+            "/",
+            "comctl32.dll/",
+            "comctl32.dll/",
+            "comctl32.dll/",
+            "comctl32.dll/",
+            "comctl32.dll/",
+            "comctl32.dll/",
+        ]
+    );
+
+    assert_eq!(
+        symbols(members[1].1),
+        vec![
+            ("InitCommonControls".to_string(), 6),
+            ("TaskDialogIndirect".to_string(), 4),
+            ("__IMPORT_DESCRIPTOR_comctl32".to_string(), 1),
+            ("__NULL_IMPORT_DESCRIPTOR".to_string(), 2),
+            ("__imp_ComCtlVersion".to_string(), 5),
+            ("__imp_InitCommonControls".to_string(), 6),
+            ("__imp_TaskDialogIndirect".to_string(), 4),
+            ("\x7fcomctl32_NULL_THUNK_DATA".to_string(), 3),
+        ]
+    );
+
+    // The import descriptor is an object file with relocations to the import tables
+    let descriptor = members[2].1;
+    assert_eq!(u16_at(descriptor, 0), 0x8664);
+    assert_eq!(u16_at(descriptor, 2), 2);
+    assert_eq!(&descriptor[20..28], b".idata$2");
+    assert_eq!(&descriptor[60..68], b".idata$6");
+
+    // Short import objects, for a function by ordinal and a static by name
+    let function = members[7].1;
+    assert_eq!(u16_at(function, 2), 0xffff);
+    assert_eq!(u16_at(function, 6), 0x8664);
+    assert_eq!(u16_at(function, 16), 17);
+    assert_eq!(u16_at(function, 18), 0);
+    assert_eq!(&function[20..], b"InitCommonControls\0comctl32.dll\0");
+
+    let data = members[6].1;
+    assert_eq!(u16_at(data, 18), 1 | 1 << 2);
+    assert_eq!(&data[20..], b"ComCtlVersion\0comctl32.dll\0");
+}
+
+#[test]
+fn long_dll_names() {
+    let dll = DllImports {
+        dll: "api-ms-win-core-path-l1-1-0.dll".to_string(),
+        imports: vec![import("PathCchCombine", None, false)],
+    };
+    let library = import_library(&dll, Machine::Arm64);
+    let members = members(&library);
+
+    assert_eq!(members[2].0, "//");
+    assert_eq!(members[2].1, b"api-ms-win-core-path-l1-1-0.dll\0");
+    assert!(members[3..].iter().all(|(name, _)| name == "/0"));
+    assert_eq!(u16_at(members[3].1, 0), 0xaa64);
+    assert_eq!(u16_at(members[6].1, 6), 0xaa64);
+}
@@ -0,0 +1,221 @@
+mod common;
+
+use common::{build_dll, Target};
+use std::{fs, process::Command};
+use windows_dll_gen::{
+    pe::{ExportTarget, Forwarder, PeFile},
+    resolve::{verify, Resolver},
+    rust::Link,
+    source::dll_imports,
+    Error,
+};
+
+/// `kernel32` forwarding to `kernelbase` and `ntdll`, by name and by ordinal
+fn reference_dlls() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        (
+            "KERNEL32.dll",
+            build_dll(
+                "KERNEL32.dll",
+                &[
+                    (Some("GetTickCount"), Target::Function),
+                    (
+                        Some("HeapAlloc"),
+                        Target::Forwarder("NTDLL.RtlAllocateHeap"),
+                    ),
+                    (
+                        Some("GetSystemTime"),
+                        Target::Forwarder("api-ms-win-core-sysinfo-l1-1-0.GetSystemTime"),
+                    ),
+                    (Some("Sleep"), Target::Forwarder("KERNELBASE.#2")),
+                    (Some("Loop"), Target::Forwarder("KERNELBASE.Loop")),
+                ],
+            ),
+        ),
+        (
+            "KernelBase.dll",
+            build_dll(
+                "KERNELBASE.dll",
+                &[
+                    (Some("Loop"), Target::Forwarder("kernel32.Loop")),
+                    (None, Target::Forwarder("ntdll.NtDelayExecution")),
+                ],
+            ),
+        ),
+        (
+            "ntdll.dll",
+            build_dll(
+                "ntdll.dll",
+                &[
+                    (Some("RtlAllocateHeap"), Target::Function),
+                    (Some("NtDelayExecution"), Target::Function),
+                ],
+            ),
+        ),
+    ]
+}
+
+fn resolver() -> Resolver {
+    let mut resolver = Resolver::new();
+    for (file_name, data) in reference_dlls() {
+        resolver.add(file_name, PeFile::parse(&data).unwrap().exports().unwrap());
+    }
+    resolver
+}
+
+fn name(name: &str) -> Link {
+    Link::Name(name.to_string())
+}
+
+#[test]
+fn parse_forwarders() {
+    assert_eq!(
+        Forwarder::parse("NTDLL.RtlAllocateHeap").unwrap(),
+        Forwarder {
+            dll: "NTDLL".to_string(),
+            link: name("RtlAllocateHeap"),
+        }
+    );
+    assert_eq!(
+        Forwarder::parse("api-ms-win-core-heap-l1-1-0.dll.#12").unwrap(),
+        Forwarder {
+            dll: "api-ms-win-core-heap-l1-1-0.dll".to_string(),
+            link: Link::Ordinal(12),
+        }
+    );
+    assert!(Forwarder::parse("NTDLL").is_err());
+    assert!(Forwarder::parse("NTDLL.").is_err());
+    assert!(Forwarder::parse("NTDLL.#x").is_err());
+}
+
+#[test]
+fn resolve_forwarders() {
+    let resolver = resolver();
+
+    let resolved = resolver.resolve("kernel32", &name("GetTickCount")).unwrap();
+    assert_eq!(resolved.dll, "KERNEL32.dll");
+    assert!(resolved.forwarders.is_empty());
+
+    let resolved = resolver
+        .resolve("kernel32.dll", &name("HeapAlloc"))
+        .unwrap();
+    assert_eq!(resolved.dll, "ntdll.dll");
+    assert_eq!(resolved.export.name.as_deref(), Some("RtlAllocateHeap"));
+    assert!(matches!(resolved.export.target, ExportTarget::Function(_)));
+
+    // Through an ordinal-only export of kernelbase
+    let resolved = resolver
+        .resolve(r"C:\Windows\System32\KERNEL32.DLL", &name("Sleep"))
+        .unwrap();
+    assert_eq!(resolved.dll, "ntdll.dll");
+    assert_eq!(resolved.export.name.as_deref(), Some("NtDelayExecution"));
+    assert_eq!(
+        resolved.forwarders,
+        vec![
+            Forwarder {
+                dll: "KERNELBASE".to_string(),
+                link: Link::Ordinal(2),
+            },
+            Forwarder {
+                dll: "ntdll".to_string(),
+                link: name("NtDelayExecution"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn unresolved_forwarders() {
+    let resolver = resolver();
+
+    assert!(matches!(
+        resolver.resolve("kernel32", &name("Loop")),
+        Err(Error::Forwarder(_))
+    ));
+    assert!(matches!(
+        resolver.resolve("kernel32", &name("GetSystemTime")),
+        Err(Error::NotFound(dll)) if dll == "api-ms-win-core-sysinfo-l1-1-0.dll"
+    ));
+    assert!(matches!(
+        resolver.resolve("kernel32", &Link::Ordinal(42)),
+        Err(Error::NotFound(export)) if export == "KERNEL32.dll!#42"
+    ));
+    assert!(matches!(
+        resolver.resolve("user32", &name("MessageBoxW")),
+        Err(Error::NotFound(_))
+    ));
+}
+
+const SOURCE: &str = r#"
+#[dll(kernel32)]
+extern "system" {
+    fn GetTickCount() -> u32;
+    #[link_name = "HeapAlloc"]
+    fn heap_alloc(heap: HANDLE, flags: u32, size: usize) -> *mut c_void;
+    #[link_ordinal = 7]
+    fn missing();
+}
+"#;
+
+#[test]
+fn verify_imports() {
+    let verification = verify(&resolver(), &dll_imports(SOURCE).unwrap());
+
+    assert_eq!(verification.unresolved, 1);
+    assert_eq!(
+        verification.report,
+        "kernel32
+    GetTickCount
+    HeapAlloc -> ntdll.dll!RtlAllocateHeap
+    #7: `KERNEL32.dll!#7` was not found
+"
+    );
+}
+
+#[test]
+fn cli() {
+    let dir = std::env::temp_dir().join(format!("windows-dll-gen-resolve-{}", std::process::id()));
+    let dlls = dir.join("System32");
+    fs::create_dir_all(&dlls).unwrap();
+    for (file_name, data) in reference_dlls() {
+        fs::write(dlls.join(file_name), data).unwrap();
+    }
+    fs::write(dlls.join("readme.txt"), "not a dll").unwrap();
+    fs::write(dlls.join("broken.dll"), "not a dll either").unwrap();
+    let resolver = Resolver::from_dir(&dlls).unwrap();
+    assert!(resolver.dll("kernel32").is_some());
+    assert_eq!(resolver.warnings().len(), 1);
+    assert!(resolver.warnings()[0].contains("broken.dll"));
+    let source = dir.join("lib.rs");
+    fs::write(&source, SOURCE.replace("#[link_ordinal = 7]", "")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_windows-dll-gen"))
+        .arg("verify")
+        .arg(&source)
+        .arg("--dlls")
+        .arg(&dlls)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("    HeapAlloc -> ntdll.dll!RtlAllocateHeap\n"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 imports couldn't be resolved"));
+    assert!(stderr.contains("warning: skipped "));
+
+    fs::write(
+        &source,
+        SOURCE.replace("#[link_ordinal = 7]\n    fn missing();", ""),
+    )
+    .unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_windows-dll-gen"))
+        .arg("verify")
+        .arg(&source)
+        .arg("--dlls")
+        .arg(&dlls)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
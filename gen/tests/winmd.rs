@@ -0,0 +1,275 @@
+mod common;
+
+use common::metadata::*;
+use regex::Regex;
+use windows_dll_gen::{
+    metadata::{Metadata, Type},
+    rust::render_blocks,
+    winmd::{extern_blocks, Backend, WinmdOptions},
+    Error,
+};
+
+fn test_winmd() -> Vec<u8> {
+    let mut metadata = MetadataBuilder::new();
+    let value_type = metadata.type_ref("System", "ValueType");
+    let enum_type = metadata.type_ref("System", "Enum");
+    let guid = metadata.type_ref("System", "Guid");
+    let malloc_spy = metadata.type_ref("Windows.Win32.System.Com", "IMallocSpy");
+
+    let foundation = "Windows.Win32.Foundation";
+    let bool_type = metadata.type_def(foundation, "BOOL", Some(value_type), &[("Value", &[6, I4])]);
+    let hresult = metadata.type_def(
+        foundation,
+        "HRESULT",
+        Some(value_type),
+        &[("Value", &[6, I4])],
+    );
+    let hwnd = metadata.type_def(foundation, "HWND", Some(value_type), &[("Value", &[6, I])]);
+    let pwstr = metadata.type_def(
+        foundation,
+        "PWSTR",
+        Some(value_type),
+        &[("Value", &[6, PTR, CHAR])],
+    );
+
+    let messaging = "Windows.Win32.UI.WindowsAndMessaging";
+    let index = metadata.type_def(
+        messaging,
+        "WINDOW_LONG_PTR_INDEX",
+        Some(enum_type),
+        &[("value__", &[6, I4])],
+    );
+    let style = metadata.type_def(
+        messaging,
+        "MESSAGEBOX_STYLE",
+        Some(enum_type),
+        &[("value__", &[6, U4])],
+    );
+    let placement = metadata.type_def(
+        messaging,
+        "WINDOWPLACEMENT",
+        Some(value_type),
+        &[("length", &[6, U4])],
+    );
+    metadata.type_def(messaging, "Apis", None, &[]);
+
+    let in_out = PARAM_IN | PARAM_OUT;
+    metadata.function(
+        "USER32.dll",
+        "GetWindowLongPtrW",
+        CALL_CONV_WINAPI,
+        &[0, 2, I, VALUETYPE, hwnd, VALUETYPE, index],
+        &[("hWnd", PARAM_IN), ("nIndex", PARAM_IN)],
+    );
+    // The 32-bit declaration of the same function
+    metadata.function(
+        "USER32.dll",
+        "GetWindowLongPtrW",
+        CALL_CONV_WINAPI,
+        &[0, 2, I4, VALUETYPE, hwnd, VALUETYPE, index],
+        &[("hWnd", PARAM_IN), ("nIndex", PARAM_IN)],
+    );
+    metadata.function(
+        "USER32.dll",
+        "MessageBoxW",
+        CALL_CONV_WINAPI,
+        &[
+            0, 4, I4, VALUETYPE, hwnd, PTR, CHAR, PTR, CHAR, VALUETYPE, style,
+        ],
+        &[
+            ("hWnd", PARAM_IN),
+            ("lpText", PARAM_IN),
+            ("lpCaption", PARAM_IN),
+            ("uType", PARAM_IN),
+        ],
+    );
+    metadata.function(
+        "USER32.dll",
+        "wsprintfW",
+        CALL_CONV_CDECL,
+        &[5, 2, I4, VALUETYPE, pwstr, PTR, CHAR],
+        &[("param0", PARAM_OUT), ("param1", PARAM_IN)],
+    );
+    metadata.function(
+        "USER32.dll",
+        "GetWindowPlacement",
+        CALL_CONV_WINAPI,
+        &[
+            0, 2, VALUETYPE, bool_type, VALUETYPE, hwnd, PTR, VALUETYPE, placement,
+        ],
+        &[("hWnd", PARAM_IN), ("lpwndpl", in_out)],
+    );
+    metadata.function(
+        "OLE32.dll",
+        "CoCreateGuid",
+        CALL_CONV_WINAPI,
+        &[0, 1, VALUETYPE, hresult, PTR, VALUETYPE, guid],
+        &[("pguid", PARAM_OUT)],
+    );
+    metadata.function(
+        "OLE32.dll",
+        "CoRegisterMallocSpy",
+        CALL_CONV_WINAPI,
+        &[0, 1, VALUETYPE, hresult, CLASS, malloc_spy],
+        &[("pMallocSpy", PARAM_IN)],
+    );
+    metadata.function(
+        "KERNEL32.dll",
+        "GetTickCount",
+        CALL_CONV_WINAPI,
+        &[0, 0, U4],
+        &[],
+    );
+
+    metadata.build()
+}
+
+#[test]
+fn imported_functions() {
+    let data = test_winmd();
+    let metadata = Metadata::parse(&data).unwrap();
+    let functions = metadata.imported_functions().unwrap();
+
+    assert_eq!(functions.len(), 8);
+    let message_box = &functions[2];
+    assert_eq!(message_box.dll, "USER32.dll");
+    assert_eq!(message_box.name, "MessageBoxW");
+    assert!(!message_box.cdecl);
+    assert_eq!(message_box.ret, Type::I32);
+    assert_eq!(message_box.params[1].name, "lpText");
+    assert_eq!(message_box.params[1].ty, Type::Ptr(Box::new(Type::Char)));
+    assert!(message_box.params[1].is_in);
+
+    let sprintf = &functions[3];
+    assert!(sprintf.cdecl);
+    assert!(sprintf.variadic);
+
+    let tick_count = &functions[7];
+    assert_eq!(tick_count.dll, "KERNEL32.dll");
+    assert!(tick_count.params.is_empty());
+}
+
+#[test]
+fn winapi_types() {
+    let data = test_winmd();
+    let metadata = Metadata::parse(&data).unwrap();
+    let blocks = extern_blocks(&metadata, "user32", &WinmdOptions::default()).unwrap();
+
+    assert_eq!(
+        render_blocks(&blocks),
+        r#"use windows_dll::dll;
+
+#[dll(user32)]
+pub mod user32 {
+    // TODO: import WINDOWPLACEMENT
+    extern "system" {
+        #[allow(non_snake_case)]
+        pub fn GetWindowLongPtrW(h_wnd: winapi::shared::windef::HWND, n_index: i32) -> isize;
+        #[allow(non_snake_case)]
+        pub fn MessageBoxW(h_wnd: winapi::shared::windef::HWND, lp_text: *const u16, lp_caption: *const u16, u_type: u32) -> i32;
+        #[allow(non_snake_case)]
+        pub fn GetWindowPlacement(h_wnd: winapi::shared::windef::HWND, lpwndpl: *mut WINDOWPLACEMENT) -> winapi::shared::minwindef::BOOL;
+    }
+
+    extern "C" {
+        #[allow(non_snake_case)]
+        pub fn wsprintfW(param0: winapi::um::winnt::LPWSTR, param1: *const u16, ...) -> i32;
+    }
+}
+"#
+    );
+}
+
+#[test]
+fn windows_types() {
+    let data = test_winmd();
+    let metadata = Metadata::parse(&data).unwrap();
+    let options = WinmdOptions {
+        backend: Backend::Windows,
+        ..WinmdOptions::default()
+    };
+    let blocks = extern_blocks(&metadata, "ole32.dll", &options).unwrap();
+
+    assert_eq!(
+        render_blocks(&blocks),
+        r#"use windows_dll::dll;
+
+#[dll(ole32)]
+extern "system" {
+    #[allow(non_snake_case)]
+    fn CoCreateGuid(pguid: *mut windows::core::GUID) -> windows::Win32::Foundation::HRESULT;
+    #[allow(non_snake_case)]
+    fn CoRegisterMallocSpy(p_malloc_spy: *mut std::ffi::c_void) -> windows::Win32::Foundation::HRESULT;
+}
+"#
+    );
+}
+
+#[test]
+fn select_functions() {
+    let data = test_winmd();
+    let metadata = Metadata::parse(&data).unwrap();
+
+    let options = WinmdOptions {
+        backend: Backend::Windows,
+        names: vec!["MessageBoxW".to_string()],
+        dll_name: Some("USER32".to_string()),
+        ..WinmdOptions::default()
+    };
+    let blocks = extern_blocks(&metadata, "USER32.DLL", &options).unwrap();
+    assert_eq!(
+        render_blocks(&blocks),
+        r#"use windows_dll::dll;
+
+#[dll(USER32)]
+extern "system" {
+    #[allow(non_snake_case)]
+    fn MessageBoxW(h_wnd: windows::Win32::Foundation::HWND, lp_text: *const u16, lp_caption: *const u16, u_type: windows::Win32::UI::WindowsAndMessaging::MESSAGEBOX_STYLE) -> i32;
+}
+"#
+    );
+
+    let options = WinmdOptions {
+        filter: Some(Regex::new("^GetWindow").unwrap()),
+        ..WinmdOptions::default()
+    };
+    let blocks = extern_blocks(&metadata, "user32", &options).unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].items.len(), 2);
+
+    let options = WinmdOptions {
+        names: vec!["MessageBoxA".to_string()],
+        ..WinmdOptions::default()
+    };
+    match extern_blocks(&metadata, "user32", &options) {
+        Err(Error::NotFound(name)) => assert_eq!(name, "user32!MessageBoxA"),
+        _ => panic!("expected MessageBoxA not to be found"),
+    }
+}
+
+#[test]
+fn not_metadata() {
+    let data = common::build_dll("test.dll", &[]);
+    assert!(matches!(Metadata::parse(&data), Err(Error::Metadata(_))));
+}
+
+#[test]
+fn crafted_metadata() {
+    // A pointer to a pointer to ... more levels than any real signature has
+    let mut signature = vec![0, 0];
+    signature.extend_from_slice(&[PTR; 100]);
+    signature.push(U4);
+    let mut metadata = MetadataBuilder::new();
+    metadata.type_def("Windows.Win32", "Apis", None, &[]);
+    metadata.function("USER32.dll", "Nested", CALL_CONV_WINAPI, &signature, &[]);
+    let data = metadata.build();
+    let metadata = Metadata::parse(&data).unwrap();
+    assert!(matches!(
+        metadata.imported_functions(),
+        Err(Error::Metadata(_))
+    ));
+
+    // A CLI header at the end of the address space
+    let data = common::build_image(&[], &[(14, 0xffff_fff8, 72)]);
+    assert!(matches!(Metadata::parse(&data), Err(Error::Metadata(_))));
+}
@@ -0,0 +1,74 @@
+use crate::Proc;
+
+// Each #[dll] extern block loaded at runtime writes a record to the `.wdll` section,
+// so that the functions a binary resolves can be listed without running it.
+// A record is laid out as:
+//   "WDLL", version: u8,
+//   lib length: u16, lib (UTF-8),
+//   proc count: u16, then for each proc
+//     0: u8, name length: u16, name (UTF-8)
+//     or 1: u8, ordinal: u16
+// Integers are little-endian. The linker may pad between records with zeros.
+const MAGIC: &[u8] = b"WDLL";
+const VERSION: u8 = 1;
+
+const PROC_NAME: u8 = 0;
+const PROC_ORDINAL: u8 = 1;
+
+pub const fn audit_record_len(lib: &str, procs: &[Proc]) -> usize {
+    let mut len = MAGIC.len() + 1 + 2 + lib.len() + 2;
+    let mut i = 0;
+    while i < procs.len() {
+        len += match &procs[i] {
+            Proc::Name(name) => 1 + 2 + name.len(),
+            Proc::Ordinal(_) => 1 + 2,
+        };
+        i += 1;
+    }
+    len
+}
+
+pub const fn audit_record<const N: usize>(lib: &str, procs: &[Proc]) -> [u8; N] {
+    let record = [0; N];
+    let (record, pos) = put_bytes(record, 0, MAGIC);
+    let (record, pos) = put_bytes(record, pos, &[VERSION]);
+    let (record, pos) = put_str(record, pos, lib);
+    let (mut record, mut pos) = put_bytes(record, pos, &(procs.len() as u16).to_le_bytes());
+
+    let mut i = 0;
+    while i < procs.len() {
+        let (next, next_pos) = match &procs[i] {
+            Proc::Name(name) => {
+                let (record, pos) = put_bytes(record, pos, &[PROC_NAME]);
+                put_str(record, pos, name)
+            }
+            Proc::Ordinal(ordinal) => {
+                let (record, pos) = put_bytes(record, pos, &[PROC_ORDINAL]);
+                put_bytes(record, pos, &ordinal.to_le_bytes())
+            }
+        };
+        record = next;
+        pos = next_pos;
+        i += 1;
+    }
+    record
+}
+
+// A string prefixed with its length
+const fn put_str<const N: usize>(record: [u8; N], pos: usize, s: &str) -> ([u8; N], usize) {
+    let (record, pos) = put_bytes(record, pos, &(s.len() as u16).to_le_bytes());
+    put_bytes(record, pos, s.as_bytes())
+}
+
+const fn put_bytes<const N: usize>(
+    mut record: [u8; N],
+    pos: usize,
+    bytes: &[u8],
+) -> ([u8; N], usize) {
+    let mut i = 0;
+    while i < bytes.len() {
+        record[pos + i] = bytes[i];
+        i += 1;
+    }
+    (record, pos + bytes.len())
+}
@@ -1,21 +1,54 @@
 use crate::{
-    platform::{AtomicDllHandle, AtomicDllProcPtr, DllHandle, DllProcPtr, LPCSTR},
-    Error, ErrorKind, WindowsDll, WindowsDllProc,
+    platform::{AtomicDllHandle, AtomicDllProcPtr, DllHandle, DllProcPtr},
+    stats::{DllStats, ProcStats},
+    Error, ErrorKind, Proc, WindowsDll, WindowsDllProc,
+};
+use core::{
+    ffi::c_void,
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering},
 };
-use core::marker::PhantomData;
 use once_cell::sync::OnceCell;
 
+// Every dll that was loaded or attempted to be loaded, for `stats()`
+static DLLS: AtomicPtr<DllEntry> = AtomicPtr::new(ptr::null_mut());
+
 #[doc(hidden)]
 pub struct DllCache<D> {
     handle: AtomicDllHandle,
-    procs: OnceCell<Vec<AtomicDllProcPtr>>,
+    entry: DllEntry,
+    // The `LEN` caches of procs using the default `WindowsDllProc::cache`
+    indexed_procs: OnceCell<Vec<DllProcCache>>,
     _phantom: PhantomData<D>,
 }
+
+// The part of a `DllCache` that doesn't depend on the dll type
+struct DllEntry {
+    lib: OnceCell<&'static str>,
+    // Intrusive list of every proc cache that was used with this dll,
+    // so they can be cleared when the dll is freed.
+    // This allows functions declared in different extern blocks to share a dll.
+    procs: AtomicPtr<DllProcCache>,
+    load_attempts: AtomicUsize,
+    load_failures: AtomicUsize,
+    registered: AtomicBool,
+    next: AtomicPtr<DllEntry>,
+}
+
 impl<D> DllCache<D> {
     pub const fn empty() -> Self {
         Self {
             handle: AtomicDllHandle::empty(),
-            procs: OnceCell::new(),
+            entry: DllEntry {
+                lib: OnceCell::new(),
+                procs: AtomicPtr::new(ptr::null_mut()),
+                load_attempts: AtomicUsize::new(0),
+                load_failures: AtomicUsize::new(0),
+                registered: AtomicBool::new(false),
+                next: AtomicPtr::new(ptr::null_mut()),
+            },
+            indexed_procs: OnceCell::new(),
             _phantom: PhantomData,
         }
     }
@@ -25,8 +58,8 @@ impl<D> DllCache<D> {
             false
         } else {
             self.handle.clear();
-            for proc in self.procs.get().into_iter().flatten() {
-                proc.store(None);
+            for cached_proc in self.entry.procs() {
+                cached_proc.proc.store(None);
             }
 
             handle.free()
@@ -34,11 +67,70 @@ impl<D> DllCache<D> {
     }
 }
 
+impl DllEntry {
+    fn procs(&self) -> impl Iterator<Item = &'static DllProcCache> {
+        iter_list(&self.procs, |cached_proc| &cached_proc.next)
+    }
+    fn stats(&self) -> DllStats {
+        DllStats {
+            lib: self.lib.get().copied().unwrap_or_default(),
+            load_attempts: self.load_attempts.load(Ordering::Relaxed),
+            load_failures: self.load_failures.load(Ordering::Relaxed),
+            procs: self.procs().filter_map(DllProcCache::stats).collect(),
+        }
+    }
+}
+
+pub(crate) fn dll_stats() -> Vec<DllStats> {
+    iter_list(&DLLS, |entry: &DllEntry| &entry.next)
+        .map(DllEntry::stats)
+        .collect()
+}
+
+// Push to an intrusive list once, `registered` is set by the first call
+fn push_once<T>(
+    head: &AtomicPtr<T>,
+    node: &'static T,
+    registered: &AtomicBool,
+    next: impl Fn(&T) -> &AtomicPtr<T>,
+) {
+    if registered.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let node_ptr = node as *const T as *mut T;
+    let mut current = head.load(Ordering::SeqCst);
+    loop {
+        next(node).store(current, Ordering::SeqCst);
+        match head.compare_exchange(current, node_ptr, Ordering::SeqCst, Ordering::SeqCst) {
+            Ok(_) => break,
+            Err(actual) => current = actual,
+        }
+    }
+}
+fn iter_list<T: 'static>(
+    head: &AtomicPtr<T>,
+    next: impl Fn(&'static T) -> &'static AtomicPtr<T>,
+) -> impl Iterator<Item = &'static T> {
+    let mut node = unsafe { head.load(Ordering::SeqCst).as_ref() };
+    core::iter::from_fn(move || {
+        let current = node?;
+        node = unsafe { next(current).load(Ordering::SeqCst).as_ref() };
+        Some(current)
+    })
+}
+
 impl<D: WindowsDll> DllCache<D> {
-    pub(crate) unsafe fn lib_exists(&self) -> bool {
+    pub(crate) fn indexed_proc(&'static self, cache_index: usize) -> &'static DllProcCache {
+        let procs = self
+            .indexed_procs
+            .get_or_init(|| (0..D::LEN).map(|_| DllProcCache::empty()).collect());
+        &procs[cache_index]
+    }
+    pub(crate) unsafe fn lib_exists(&'static self) -> bool {
         !self.get().is_invalid()
     }
-    unsafe fn get(&self) -> DllHandle {
+    unsafe fn get(&'static self) -> DllHandle {
         let handle = self.handle.load();
 
         let handle = if handle.is_invalid() {
@@ -49,44 +141,154 @@ impl<D: WindowsDll> DllCache<D> {
 
         handle
     }
-    unsafe fn load_and_cache_lib(&self) -> DllHandle {
+    unsafe fn load_and_cache_lib(&'static self) -> DllHandle {
+        let entry = &self.entry;
+        entry.lib.get_or_init(|| D::LIB);
+        push_once(&DLLS, entry, &entry.registered, |entry| &entry.next);
+
         let handle = DllHandle::load(D::LIB_LPCWSTR, D::FLAGS);
 
-        self.procs.get_or_init(|| {
-            let mut procs = Vec::with_capacity(D::LEN);
-            for _ in 0..D::LEN {
-                procs.push(AtomicDllProcPtr::empty());
-            }
-            procs
-        });
-        // Store the handle *after* initializing `self.procs`. This
-        // is required to avoid a race condition in `get_proc_ptr`.
+        entry.load_attempts.fetch_add(1, Ordering::Relaxed);
+        if handle.is_invalid() {
+            entry.load_failures.fetch_add(1, Ordering::Relaxed);
+        }
         self.handle.store(handle);
 
         handle
     }
-    unsafe fn get_proc_ptr(
-        &self,
-        name: LPCSTR,
-        cache_index: usize,
+    unsafe fn get_proc_ptr<P: WindowsDllProc<Dll = D>>(
+        &'static self,
     ) -> Result<DllProcPtr, ErrorKind> {
+        let cached_proc = P::cache();
+
+        // An override is used without loading the dll
+        if let Some(proc) = cached_proc.override_proc.load() {
+            return Ok(proc);
+        }
+
         let library = self.get();
         if library.is_invalid() {
             return Err(ErrorKind::Lib);
         }
 
-        // The unwrap is safe because `self.procs` is guaranteed to
-        // be initialized *before* `self.handle` is set. See
-        // `load_and_cache_lib`.
-        let cached_proc = &self.procs.get().unwrap()[cache_index];
+        if let Some(proc) = cached_proc.proc.load() {
+            return Ok(proc);
+        }
+
+        cached_proc.info.get_or_init(|| (P::PROC, P::CACHE_INDEX));
+        push_once(
+            &self.entry.procs,
+            cached_proc,
+            &cached_proc.registered,
+            |cached_proc| &cached_proc.next,
+        );
 
-        cached_proc
-            .load()
-            .or_else(|| library.get_proc(name))
-            .ok_or(ErrorKind::Proc)
+        let proc = library.get_proc(P::PROC_LPCSTR);
+
+        cached_proc.resolve_attempts.fetch_add(1, Ordering::Relaxed);
+        let proc = match proc {
+            Some(proc) => proc,
+            None => {
+                cached_proc.resolve_failures.fetch_add(1, Ordering::Relaxed);
+                return Err(ErrorKind::Proc);
+            }
+        };
+        cached_proc.proc.store(Some(proc));
+
+        Ok(proc)
+    }
+    /// Resolve the export of `P`, or the replacement set with `override_with`
+    ///
+    /// # Safety
+    ///
+    /// `P::Sig` must be the actual signature of the export, it's only checked by the
+    /// declaration of `P`.
+    pub unsafe fn get_proc<P: WindowsDllProc<Dll = D>>(
+        &'static self,
+    ) -> Result<P::Sig, Error<P>> {
+        let proc = self.get_proc_ptr::<P>()?;
+        Ok(proc.transmute())
     }
-    pub unsafe fn get_proc<P: WindowsDllProc<Dll = D>>(&self) -> Result<P::Sig, Error<P>> {
-        let proc = self.get_proc_ptr(P::PROC_LPCSTR, P::CACHE_INDEX)?;
+    /// The address of the export of `P`, or of the replacement set with `override_with`
+    ///
+    /// # Safety
+    ///
+    /// Loading the dll runs its `DllMain`, so the dll must be safe to load in this process.
+    pub unsafe fn get_proc_address<P: WindowsDllProc<Dll = D>>(
+        &'static self,
+    ) -> Result<*const c_void, Error<P>> {
+        let proc = self.get_proc_ptr::<P>()?;
         Ok(proc.transmute())
     }
 }
+
+#[doc(hidden)]
+pub struct DllProcCache {
+    proc: AtomicDllProcPtr,
+    // Replacement set with `override_with`, kept when the dll is freed
+    override_proc: AtomicDllProcPtr,
+    // `PROC` and `CACHE_INDEX`, set when the proc is first resolved
+    info: OnceCell<(Proc, usize)>,
+    resolve_attempts: AtomicUsize,
+    resolve_failures: AtomicUsize,
+    #[cfg(feature = "call-stats")]
+    calls: AtomicUsize,
+    registered: AtomicBool,
+    next: AtomicPtr<DllProcCache>,
+}
+impl DllProcCache {
+    pub const fn empty() -> Self {
+        Self {
+            proc: AtomicDllProcPtr::empty(),
+            override_proc: AtomicDllProcPtr::empty(),
+            info: OnceCell::new(),
+            resolve_attempts: AtomicUsize::new(0),
+            resolve_failures: AtomicUsize::new(0),
+            #[cfg(feature = "call-stats")]
+            calls: AtomicUsize::new(0),
+            registered: AtomicBool::new(false),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+    /// Use `sig` instead of the export, without loading the dll
+    ///
+    /// # Safety
+    ///
+    /// `T` must be the function pointer type of the `Sig` of this proc, the pointer is
+    /// read back as that type by every later call.
+    pub unsafe fn override_with<T: Copy>(&self, sig: T) {
+        self.override_proc.store(DllProcPtr::from_sig(sig));
+    }
+    /// Use the export again after `override_with`
+    pub fn restore(&self) {
+        self.override_proc.store(None);
+    }
+    /// The replacement set with `override_with`, or the export imported with raw-dylib
+    ///
+    /// # Safety
+    ///
+    /// `T` must be the type passed to `override_with`, the replacement is transmuted to it.
+    pub unsafe fn override_or<T: Copy>(&self, import: T) -> T {
+        match self.override_proc.load() {
+            Some(proc) => proc.transmute(),
+            None => import,
+        }
+    }
+    #[cfg(feature = "call-stats")]
+    #[inline]
+    pub fn count_call(&self) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+    }
+    fn stats(&self) -> Option<ProcStats> {
+        let (proc, cache_index) = self.info.get()?.clone();
+
+        Some(ProcStats {
+            proc,
+            cache_index,
+            resolve_attempts: self.resolve_attempts.load(Ordering::Relaxed),
+            resolve_failures: self.resolve_failures.load(Ordering::Relaxed),
+            #[cfg(feature = "call-stats")]
+            calls: self.calls.load(Ordering::Relaxed),
+        })
+    }
+}
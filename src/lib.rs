@@ -1,18 +1,35 @@
+#[cfg(feature = "audit")]
+mod audit;
 mod cache;
+mod load_flags;
 #[doc(hidden)]
 pub mod macro_internal;
+#[cfg(feature = "mock")]
+pub mod mock;
+mod os_version;
 mod platform;
+#[cfg(feature = "registry")]
+mod registry;
+mod stats;
+#[cfg(feature = "trace")]
+pub mod trace;
 
 #[doc = include_str!("../macro.md")]
 pub use windows_dll_codegen::dll;
 
+pub use load_flags::{LoadFlags, LoadFlagsError};
+pub use os_version::{os_version, override_os_version, restore_os_version, OsVersion};
+#[cfg(feature = "registry")]
+pub use registry::{registered_dlls, RegisteredDll};
+pub use stats::{stats, DllStats, ProcStats};
 pub use platform::flags;
 
-use cache::DllCache;
+use cache::{DllCache, DllProcCache};
 use platform::{LPCSTR, LPCWSTR};
 use core::marker::PhantomData;
 
 pub trait WindowsDll: Sized + 'static {
+    /// The number of procs using the default [`WindowsDllProc::cache`]
     const LEN: usize;
     const LIB: &'static str;
     const LIB_LPCWSTR: LPCWSTR;
@@ -31,16 +48,48 @@ pub trait WindowsDll: Sized + 'static {
 pub trait WindowsDllProc: Sized {
     type Dll: WindowsDll;
     type Sig: Copy;
+    /// The index of the cache of the proc among the `LEN` of its dll, when `cache` isn't
+    /// implemented
     const CACHE_INDEX: usize;
     const PROC: Proc;
     const PROC_LPCSTR: LPCSTR;
 
+    /// The macro implements this with a cache per function, so functions of different
+    /// blocks can share a dll type
+    ///
+    /// By default, the cache is the one at `CACHE_INDEX` of the `LEN` caches of the dll,
+    /// an index out of range fails to compile.
+    ///
+    /// # Safety
+    ///
+    /// The cache has to be used by this proc only, since the address cached in it is called
+    /// as `Sig`. With the default, `CACHE_INDEX` has to be unique among the procs of the dll.
+    unsafe fn cache() -> &'static DllProcCache {
+        let () = CheckCacheIndex::<Self>::IN_RANGE;
+        Self::Dll::cache().indexed_proc(Self::CACHE_INDEX)
+    }
     unsafe fn proc() -> Result<Self::Sig, Error<Self>>;
     unsafe fn exists() -> bool {
         Self::proc().is_ok()
     }
 }
 
+// Fails to compile when the default `WindowsDllProc::cache` is used with an index that isn't
+// below the `LEN` of the dll
+struct CheckCacheIndex<P>(PhantomData<P>);
+impl<P: WindowsDllProc> CheckCacheIndex<P> {
+    const IN_RANGE: () = assert!(
+        P::CACHE_INDEX < <P::Dll as WindowsDll>::LEN,
+        "`CACHE_INDEX` must be below the `LEN` of the dll"
+    );
+}
+
+/// The function pointer type of a function loaded by the **`#[dll]`** macro,
+/// or the reference or pointer type of a static
+///
+/// `Sig<GetDesktopWindow>` is the same as `<GetDesktopWindow as WindowsDllProc>::Sig`.
+pub type Sig<P> = <P as WindowsDllProc>::Sig;
+
 #[derive(Debug, Clone)]
 pub enum Proc {
     Name(&'static str),
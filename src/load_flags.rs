@@ -0,0 +1,223 @@
+use crate::platform::{flags::LOAD_LIBRARY_FLAGS, load_flags_bits, native_load_flags};
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign};
+
+/// Flags for the `dwFlags` parameter of `LoadLibraryExW`
+///
+/// Unlike the constants in the [`flags`](crate::flags) module, this type is the same
+/// for the `winapi` and `windows` backends. Flags can be combined with `|`,
+/// also when passed as the second argument to the **`#[dll]`** attribute.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(transparent)]
+pub struct LoadFlags(u32);
+
+impl LoadFlags {
+    pub const DONT_RESOLVE_DLL_REFERENCES: Self = Self(0x0000_0001);
+    pub const AS_DATAFILE: Self = Self(0x0000_0002);
+    pub const WITH_ALTERED_SEARCH_PATH: Self = Self(0x0000_0008);
+    pub const IGNORE_CODE_AUTHZ_LEVEL: Self = Self(0x0000_0010);
+    pub const AS_IMAGE_RESOURCE: Self = Self(0x0000_0020);
+    pub const AS_DATAFILE_EXCLUSIVE: Self = Self(0x0000_0040);
+    pub const REQUIRE_SIGNED_TARGET: Self = Self(0x0000_0080);
+    pub const SEARCH_DLL_LOAD_DIR: Self = Self(0x0000_0100);
+    pub const SEARCH_APPLICATION_DIR: Self = Self(0x0000_0200);
+    pub const SEARCH_USER_DIRS: Self = Self(0x0000_0400);
+    pub const SEARCH_SYSTEM32: Self = Self(0x0000_0800);
+    pub const SEARCH_DEFAULT_DIRS: Self = Self(0x0000_1000);
+    pub const SAFE_CURRENT_DIRS: Self = Self(0x0000_2000);
+    pub const SEARCH_SYSTEM32_NO_FORWARDER: Self = Self(0x0000_4000);
+    pub const OS_INTEGRITY_CONTINUITY: Self = Self(0x0000_8000);
+
+    const NAMED: [(Self, &'static str); 15] = [
+        (Self::DONT_RESOLVE_DLL_REFERENCES, "DONT_RESOLVE_DLL_REFERENCES"),
+        (Self::AS_DATAFILE, "AS_DATAFILE"),
+        (Self::WITH_ALTERED_SEARCH_PATH, "WITH_ALTERED_SEARCH_PATH"),
+        (Self::IGNORE_CODE_AUTHZ_LEVEL, "IGNORE_CODE_AUTHZ_LEVEL"),
+        (Self::AS_IMAGE_RESOURCE, "AS_IMAGE_RESOURCE"),
+        (Self::AS_DATAFILE_EXCLUSIVE, "AS_DATAFILE_EXCLUSIVE"),
+        (Self::REQUIRE_SIGNED_TARGET, "REQUIRE_SIGNED_TARGET"),
+        (Self::SEARCH_DLL_LOAD_DIR, "SEARCH_DLL_LOAD_DIR"),
+        (Self::SEARCH_APPLICATION_DIR, "SEARCH_APPLICATION_DIR"),
+        (Self::SEARCH_USER_DIRS, "SEARCH_USER_DIRS"),
+        (Self::SEARCH_SYSTEM32, "SEARCH_SYSTEM32"),
+        (Self::SEARCH_DEFAULT_DIRS, "SEARCH_DEFAULT_DIRS"),
+        (Self::SAFE_CURRENT_DIRS, "SAFE_CURRENT_DIRS"),
+        (Self::SEARCH_SYSTEM32_NO_FORWARDER, "SEARCH_SYSTEM32_NO_FORWARDER"),
+        (Self::OS_INTEGRITY_CONTINUITY, "OS_INTEGRITY_CONTINUITY"),
+    ];
+    const ALL: u32 = 0x0000_fffb;
+    const SEARCH: u32 = Self::SEARCH_DLL_LOAD_DIR.0
+        | Self::SEARCH_APPLICATION_DIR.0
+        | Self::SEARCH_USER_DIRS.0
+        | Self::SEARCH_SYSTEM32.0
+        | Self::SEARCH_DEFAULT_DIRS.0
+        | Self::SEARCH_SYSTEM32_NO_FORWARDER.0;
+    const DATAFILE: u32 =
+        Self::AS_DATAFILE.0 | Self::AS_DATAFILE_EXCLUSIVE.0 | Self::AS_IMAGE_RESOURCE.0;
+
+    /// No flags, `LoadLibraryExW` behaves like `LoadLibraryW`
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+    /// Returns `None` if `bits` contains a value that is not a known flag
+    pub const fn from_bits(bits: u32) -> Option<Self> {
+        if bits & !Self::ALL == 0 {
+            Some(Self(bits))
+        } else {
+            None
+        }
+    }
+    pub const fn from_bits_retain(bits: u32) -> Self {
+        Self(bits)
+    }
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+    /// `const` version of the `|` operator
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Check that the flags can be passed to `LoadLibraryExW` together
+    pub const fn validate(self) -> Result<Self, LoadFlagsError> {
+        let search = self.0 & Self::SEARCH != 0;
+
+        if self.0 & !Self::ALL != 0 {
+            Err(LoadFlagsError::UnknownFlags(self.0 & !Self::ALL))
+        } else if search && self.intersects(Self::WITH_ALTERED_SEARCH_PATH) {
+            Err(LoadFlagsError::AlteredSearchPathWithSearchFlags)
+        } else if search && self.0 & Self::DATAFILE != 0 {
+            Err(LoadFlagsError::DatafileWithSearchFlags)
+        } else if self.contains(Self::AS_DATAFILE.union(Self::AS_DATAFILE_EXCLUSIVE)) {
+            Err(LoadFlagsError::DatafileWithDatafileExclusive)
+        } else {
+            Ok(self)
+        }
+    }
+    /// Like [`validate`](Self::validate), but panics if the flags are invalid
+    ///
+    /// When used in a constant, this turns an invalid combination into a compile error.
+    pub const fn validated(self) -> Self {
+        match self.validate() {
+            Ok(flags) => flags,
+            Err(err) => panic!("{}", err.message()),
+        }
+    }
+
+    /// Convert to the flags type of the `winapi` or `windows` backend
+    pub const fn to_native(self) -> LOAD_LIBRARY_FLAGS {
+        native_load_flags(self.0)
+    }
+    /// Convert from the flags type of the `winapi` or `windows` backend
+    pub const fn from_native(flags: LOAD_LIBRARY_FLAGS) -> Self {
+        Self(load_flags_bits(flags))
+    }
+}
+
+impl BitOr for LoadFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+impl BitOrAssign for LoadFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.union(rhs);
+    }
+}
+impl BitAnd for LoadFlags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+impl BitAndAssign for LoadFlags {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl From<LOAD_LIBRARY_FLAGS> for LoadFlags {
+    fn from(flags: LOAD_LIBRARY_FLAGS) -> Self {
+        Self::from_native(flags)
+    }
+}
+impl From<LoadFlags> for LOAD_LIBRARY_FLAGS {
+    fn from(flags: LoadFlags) -> Self {
+        flags.to_native()
+    }
+}
+
+impl core::fmt::Debug for LoadFlags {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.is_empty() {
+            return f.write_str("LoadFlags(empty)");
+        }
+
+        f.write_str("LoadFlags(")?;
+        let mut first = true;
+        for (flag, name) in Self::NAMED.iter() {
+            if self.contains(*flag) {
+                if !first {
+                    f.write_str(" | ")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+        let unknown = self.0 & !Self::ALL;
+        if unknown != 0 {
+            if !first {
+                f.write_str(" | ")?;
+            }
+            write!(f, "{:#x}", unknown)?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// A combination of [`LoadFlags`] that `LoadLibraryExW` does not accept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadFlagsError {
+    UnknownFlags(u32),
+    AlteredSearchPathWithSearchFlags,
+    DatafileWithSearchFlags,
+    DatafileWithDatafileExclusive,
+}
+impl LoadFlagsError {
+    const fn message(self) -> &'static str {
+        match self {
+            Self::UnknownFlags(_) => "unknown LoadLibraryExW flags",
+            Self::AlteredSearchPathWithSearchFlags => {
+                "WITH_ALTERED_SEARCH_PATH cannot be combined with SEARCH_* flags"
+            }
+            Self::DatafileWithSearchFlags => {
+                "AS_DATAFILE, AS_DATAFILE_EXCLUSIVE and AS_IMAGE_RESOURCE cannot be combined with SEARCH_* flags"
+            }
+            Self::DatafileWithDatafileExclusive => {
+                "AS_DATAFILE and AS_DATAFILE_EXCLUSIVE cannot be combined"
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadFlagsError {}
+
+impl core::fmt::Display for LoadFlagsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownFlags(bits) => write!(f, "{}: {:#x}", self.message(), bits),
+            _ => f.write_str(self.message()),
+        }
+    }
+}
@@ -1,12 +1,69 @@
-use crate::platform::{ULONG_PTR, WORD};
+use crate::{
+    platform::{flags::LOAD_LIBRARY_FLAGS, ULONG_PTR, WORD},
+    LoadFlags,
+};
 pub use crate::{
-    cache::DllCache,
+    cache::{DllCache, DllProcCache},
+    os_version::os_version_at_least,
     platform::{LPCSTR, LPCWSTR},
 };
 pub use core::{self, option::Option, result::Result};
+#[cfg(feature = "audit")]
+pub use crate::audit::{audit_record, audit_record_len};
+#[cfg(feature = "registry")]
+pub use crate::registry::{DllRegistration, DLL_REGISTRATIONS};
+#[cfg(feature = "registry")]
+pub use linkme;
 
 // Copied MAKEINTRESOURCEA function from winapi so that it can be const
 #[inline]
 pub const fn make_int_resource_a(i: WORD) -> LPCSTR {
     i as ULONG_PTR as _
 }
+
+// Accepts either `LoadFlags` or the backend's `LOAD_LIBRARY_FLAGS` in a const context,
+// so that both can be passed to the #[dll] macro
+pub struct LoadFlagsArg<T>(pub T);
+impl LoadFlagsArg<LoadFlags> {
+    #[inline]
+    pub const fn load_flags(self) -> LoadFlags {
+        self.0
+    }
+}
+impl LoadFlagsArg<LOAD_LIBRARY_FLAGS> {
+    #[inline]
+    pub const fn load_flags(self) -> LoadFlags {
+        LoadFlags::from_native(self.0)
+    }
+}
+
+// Conversions for the `#[wide]` and `#[ansi]` argument attributes,
+// the returned buffers are null-terminated. They panic on a nul character in the string,
+// which would make the dll see only the part before it.
+pub fn to_wide<S: AsRef<std::ffi::OsStr> + ?Sized>(s: &S) -> Vec<u16> {
+    let mut wide = encode_wide(s.as_ref(), "#[wide]");
+    wide.push(0);
+    wide
+}
+pub fn to_ansi<S: AsRef<std::ffi::OsStr> + ?Sized>(s: &S) -> Vec<u8> {
+    let mut ansi = crate::platform::wide_to_ansi(&encode_wide(s.as_ref(), "#[ansi]"));
+    ansi.push(0);
+    ansi
+}
+fn encode_wide(s: &std::ffi::OsStr, attribute: &str) -> Vec<u16> {
+    #[cfg(windows)]
+    let wide: Vec<u16> = {
+        use std::os::windows::ffi::OsStrExt;
+
+        s.encode_wide().collect()
+    };
+    // Only with the mock loader
+    #[cfg(not(windows))]
+    let wide: Vec<u16> = s.to_string_lossy().encode_utf16().collect();
+    assert!(
+        !wide.contains(&0),
+        "the string passed to a `{}` argument contains a nul character",
+        attribute
+    );
+    wide
+}
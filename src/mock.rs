@@ -0,0 +1,101 @@
+//! An in-memory loader used instead of `LoadLibraryExW` and `GetProcAddress` with the `mock`
+//! feature
+//!
+//! The **`#[dll]`** blocks load the functions registered here instead of the exports of the
+//! dlls on the system, so code using them can be tested on any platform, Linux included.
+//! Dll names are compared like the loader does: case-insensitively, with or without `.dll`,
+//! and without their directory. A dll that wasn't registered fails to load.
+//!
+//! Blocks imported with raw-dylib are linked to the actual dlls and don't use the mock loader.
+use crate::platform::DllProcPtr;
+use std::sync::{PoisonError, RwLock};
+
+struct MockDll {
+    name: String,
+    exports: Vec<(MockExport, DllProcPtr)>,
+}
+
+#[derive(PartialEq)]
+pub(crate) enum MockExport {
+    Name(String),
+    Ordinal(u16),
+}
+
+static DLLS: RwLock<Vec<MockDll>> = RwLock::new(Vec::new());
+
+/// Register a dll that loads successfully, without any exports yet
+pub fn register_dll(dll: &str) {
+    dll_index(dll);
+}
+
+/// Register `function` as the export `name` of `dll`, replacing any function registered with
+/// the same name
+///
+/// # Safety
+///
+/// `function` must be a function pointer with the signature the **`#[dll]`** declarations of
+/// the export have, they call it without any check.
+pub unsafe fn register_proc<T: Copy>(dll: &str, name: &str, function: T) {
+    register(dll, MockExport::Name(name.to_string()), function);
+}
+
+/// Register `function` as the export `ordinal` of `dll`, replacing any function registered
+/// with the same ordinal
+///
+/// # Safety
+///
+/// `function` must be a function pointer with the signature the **`#[dll]`** declarations of
+/// the export have, they call it without any check.
+pub unsafe fn register_ordinal<T: Copy>(dll: &str, ordinal: u16, function: T) {
+    register(dll, MockExport::Ordinal(ordinal), function);
+}
+
+unsafe fn register<T: Copy>(dll: &str, export: MockExport, function: T) {
+    let proc = DllProcPtr::from_sig(function).expect("a registered function can't be null");
+    let index = dll_index(dll);
+    let mut dlls = DLLS.write().unwrap_or_else(PoisonError::into_inner);
+    let exports = &mut dlls[index].exports;
+    exports.retain(|(registered, _)| *registered != export);
+    exports.push((export, proc));
+}
+
+// The index of a registered dll, registering it when it isn't. Dlls are never removed, so
+// the index stays valid as a handle.
+fn dll_index(dll: &str) -> usize {
+    let name = normalize(dll);
+    let mut dlls = DLLS.write().unwrap_or_else(PoisonError::into_inner);
+    match dlls.iter().position(|registered| registered.name == name) {
+        Some(index) => index,
+        None => {
+            dlls.push(MockDll {
+                name,
+                exports: Vec::new(),
+            });
+            dlls.len() - 1
+        }
+    }
+}
+
+pub(crate) fn find_dll(dll: &str) -> Option<usize> {
+    let name = normalize(dll);
+    let dlls = DLLS.read().unwrap_or_else(PoisonError::into_inner);
+    dlls.iter().position(|registered| registered.name == name)
+}
+
+pub(crate) fn find_proc(index: usize, export: &MockExport) -> Option<DllProcPtr> {
+    let dlls = DLLS.read().unwrap_or_else(PoisonError::into_inner);
+    dlls.get(index)?
+        .exports
+        .iter()
+        .find(|(registered, _)| registered == export)
+        .map(|&(_, proc)| proc)
+}
+
+fn normalize(dll: &str) -> String {
+    let file_name = dll.rsplit(&['\\', '/'][..]).next().unwrap_or(dll);
+    let file_name = file_name.to_ascii_lowercase();
+    match file_name.strip_suffix(".dll") {
+        Some(stem) => stem.to_string(),
+        None => file_name,
+    }
+}
@@ -0,0 +1,167 @@
+use crate::platform::{flags, DllHandle};
+use core::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+};
+use once_cell::sync::Lazy;
+use std::sync::{PoisonError, RwLock};
+
+/// The version of Windows the process runs on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OsVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+    /// `VER_PLATFORM_WIN32_NT` (2) on every supported version of Windows
+    pub platform_id: u32,
+    /// The latest installed service pack, like `Service Pack 1`, empty on Windows 10 and later
+    pub csd: String,
+}
+
+impl OsVersion {
+    /// Whether this is the given version or a later one, e.g. `is_at_least(10, 0, 17763)`
+    /// for Windows 10 1809 and Windows 11
+    pub fn is_at_least(&self, major: u32, minor: u32, build: u32) -> bool {
+        (self.major, self.minor, self.build) >= (major, minor, build)
+    }
+}
+
+impl fmt::Display for OsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.build)?;
+        if !self.csd.is_empty() {
+            write!(f, " {}", self.csd)?;
+        }
+        Ok(())
+    }
+}
+
+/// The version of Windows the process runs on, `None` if it couldn't be queried
+///
+/// It's queried once with `RtlGetVersion`. Unlike `GetVersionExW`, it isn't affected by the
+/// compatibility manifest of the executable.
+pub fn os_version() -> Option<OsVersion> {
+    if PARTS.load(Ordering::Acquire) & OVERRIDDEN == 0 {
+        return OS_VERSION.clone();
+    }
+    let overridden = OVERRIDE.read().unwrap_or_else(PoisonError::into_inner);
+    overridden.clone().or_else(|| OS_VERSION.clone())
+}
+
+/// Report `version` from [`os_version`] and to **`#[min_os]`** checks instead of the actual
+/// version, until [`restore_os_version`] is called
+///
+/// This is meant for tests, together with `override_with` on the generated functions.
+/// With the `mock` feature, they can also run on other platforms than Windows.
+///
+/// # Panics
+///
+/// When the major version is above 32767 or the minor version is above 65535.
+pub fn override_os_version(version: OsVersion) {
+    assert!(
+        version.major < 0x8000 && version.minor <= 0xffff,
+        "the overridden version {} is out of range",
+        version
+    );
+    let parts = OVERRIDDEN
+        | u64::from(version.major) << 48
+        | u64::from(version.minor) << 32
+        | u64::from(version.build);
+    *OVERRIDE.write().unwrap_or_else(PoisonError::into_inner) = Some(version);
+    PARTS.store(parts, Ordering::Release);
+}
+
+/// Report the actual version of Windows again after [`override_os_version`]
+pub fn restore_os_version() {
+    PARTS.store(0, Ordering::Release);
+    *OVERRIDE.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+// The major, minor and build number of the version set with `override_os_version`, packed
+// so `#[min_os]` checks don't take a lock, and the whole version for `os_version`
+static PARTS: AtomicU64 = AtomicU64::new(0);
+static OVERRIDE: RwLock<Option<OsVersion>> = RwLock::new(None);
+const OVERRIDDEN: u64 = 1 << 63;
+
+#[allow(non_snake_case, clippy::upper_case_acronyms)]
+#[repr(C)]
+struct OSVERSIONINFOW {
+    dwOSVersionInfoSize: u32,
+    dwMajorVersion: u32,
+    dwMinorVersion: u32,
+    dwBuildNumber: u32,
+    dwPlatformId: u32,
+    szCSDVersion: [u16; 128],
+}
+
+type RtlGetVersion = unsafe extern "system" fn(version_information: *mut OSVERSIONINFOW) -> i32;
+
+// `RtlGetVersion` is loaded directly instead of with `#[dll]`, which would expand with the
+// features of the user: the query would show up in their registry, stats, audit records and
+// traces, run through their call hook, and become a raw-dylib import
+unsafe fn rtl_get_version() -> Option<RtlGetVersion> {
+    const NTDLL: &[u16] = &[
+        'n' as u16, 't' as u16, 'd' as u16, 'l' as u16, 'l' as u16, '.' as u16, 'd' as u16,
+        'l' as u16, 'l' as u16, 0,
+    ];
+    let ntdll = DllHandle::load(NTDLL.as_ptr(), flags::NO_FLAGS);
+    if ntdll.is_invalid() {
+        return None;
+    }
+    ntdll
+        .get_proc(b"RtlGetVersion\0".as_ptr() as _)
+        .map(|proc| proc.transmute())
+}
+
+static OS_VERSION: Lazy<Option<OsVersion>> = Lazy::new(|| unsafe {
+    let mut info = OSVERSIONINFOW {
+        dwOSVersionInfoSize: core::mem::size_of::<OSVERSIONINFOW>() as u32,
+        dwMajorVersion: 0,
+        dwMinorVersion: 0,
+        dwBuildNumber: 0,
+        dwPlatformId: 0,
+        szCSDVersion: [0; 128],
+    };
+    match rtl_get_version().map(|rtl_get_version| rtl_get_version(&mut info)) {
+        Some(status) if status >= 0 => {
+            let csd_len = info
+                .szCSDVersion
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(info.szCSDVersion.len());
+            Some(OsVersion {
+                major: info.dwMajorVersion,
+                minor: info.dwMinorVersion,
+                build: info.dwBuildNumber,
+                platform_id: info.dwPlatformId,
+                csd: String::from_utf16_lossy(&info.szCSDVersion[..csd_len]),
+            })
+        }
+        _ => None,
+    }
+});
+
+// Whether Windows is at least the version of a `#[min_os]` attribute, comparing only
+// the parts it was given. An unknown version is assumed to be older.
+pub fn os_version_at_least(major: Option<u32>, minor: Option<u32>, build: Option<u32>) -> bool {
+    let parts = PARTS.load(Ordering::Acquire);
+    let version = if parts & OVERRIDDEN != 0 {
+        [
+            (parts >> 48) as u32 & 0x7fff,
+            (parts >> 32) as u32 & 0xffff,
+            parts as u32,
+        ]
+    } else {
+        match OS_VERSION.as_ref() {
+            Some(version) => [version.major, version.minor, version.build],
+            None => return false,
+        }
+    };
+    for (&min, &actual) in [major, minor, build].iter().zip(version.iter()) {
+        match min {
+            Some(min) if actual != min => return actual > min,
+            _ => {}
+        }
+    }
+    true
+}
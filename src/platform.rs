@@ -1,9 +1,14 @@
-#[cfg(feature = "winapi")]
+#[cfg(all(feature = "winapi", not(feature = "mock")))]
 mod winapi_crate;
-#[cfg(feature = "winapi")]
+#[cfg(all(feature = "winapi", not(feature = "mock")))]
 pub use winapi_crate::*;
 
-#[cfg(feature = "windows")]
+#[cfg(all(feature = "windows", not(feature = "mock")))]
 mod windows_crate;
-#[cfg(feature = "windows")]
+#[cfg(all(feature = "windows", not(feature = "mock")))]
 pub use windows_crate::*;
+
+#[cfg(feature = "mock")]
+mod mock;
+#[cfg(feature = "mock")]
+pub use self::mock::*;
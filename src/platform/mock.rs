@@ -0,0 +1,139 @@
+use crate::mock::{find_dll, find_proc, MockExport};
+use core::{
+    mem::{size_of, transmute},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::ffi::CStr;
+
+type NonNullFarProc = unsafe extern "system" fn() -> isize;
+#[allow(clippy::upper_case_acronyms)]
+type FARPROC = Option<NonNullFarProc>;
+#[allow(non_camel_case_types)]
+pub(crate) type ULONG_PTR = usize;
+#[allow(clippy::upper_case_acronyms)]
+pub(crate) type WORD = u16;
+pub type LPCWSTR = *const u16;
+pub type LPCSTR = *const u8;
+
+pub mod flags {
+    pub const NO_FLAGS: LOAD_LIBRARY_FLAGS = 0;
+
+    #[allow(non_camel_case_types)]
+    pub type LOAD_LIBRARY_FLAGS = u32;
+
+    pub const DONT_RESOLVE_DLL_REFERENCES: LOAD_LIBRARY_FLAGS = 0x0000_0001;
+    pub const LOAD_LIBRARY_AS_DATAFILE: LOAD_LIBRARY_FLAGS = 0x0000_0002;
+    pub const LOAD_WITH_ALTERED_SEARCH_PATH: LOAD_LIBRARY_FLAGS = 0x0000_0008;
+    pub const LOAD_IGNORE_CODE_AUTHZ_LEVEL: LOAD_LIBRARY_FLAGS = 0x0000_0010;
+    pub const LOAD_LIBRARY_AS_IMAGE_RESOURCE: LOAD_LIBRARY_FLAGS = 0x0000_0020;
+    pub const LOAD_LIBRARY_AS_DATAFILE_EXCLUSIVE: LOAD_LIBRARY_FLAGS = 0x0000_0040;
+    pub const LOAD_LIBRARY_REQUIRE_SIGNED_TARGET: LOAD_LIBRARY_FLAGS = 0x0000_0080;
+    pub const LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR: LOAD_LIBRARY_FLAGS = 0x0000_0100;
+    pub const LOAD_LIBRARY_SEARCH_APPLICATION_DIR: LOAD_LIBRARY_FLAGS = 0x0000_0200;
+    pub const LOAD_LIBRARY_SEARCH_USER_DIRS: LOAD_LIBRARY_FLAGS = 0x0000_0400;
+    pub const LOAD_LIBRARY_SEARCH_SYSTEM32: LOAD_LIBRARY_FLAGS = 0x0000_0800;
+    pub const LOAD_LIBRARY_SEARCH_DEFAULT_DIRS: LOAD_LIBRARY_FLAGS = 0x0000_1000;
+    pub const LOAD_LIBRARY_SAFE_CURRENT_DIRS: LOAD_LIBRARY_FLAGS = 0x0000_2000;
+    pub const LOAD_LIBRARY_SEARCH_SYSTEM32_NO_FORWARDER: LOAD_LIBRARY_FLAGS = 0x0000_4000;
+    pub const LOAD_LIBRARY_OS_INTEGRITY_CONTINUITY: LOAD_LIBRARY_FLAGS = 0x0000_8000;
+}
+
+#[repr(transparent)]
+pub(crate) struct AtomicDllHandle(AtomicUsize);
+impl AtomicDllHandle {
+    pub(crate) const fn empty() -> Self {
+        Self(AtomicUsize::new(0))
+    }
+    pub(crate) fn load(&self) -> DllHandle {
+        DllHandle(self.0.load(Ordering::SeqCst))
+    }
+    pub(crate) fn store(&self, handle: DllHandle) {
+        self.0.store(handle.0, Ordering::SeqCst);
+    }
+    pub(crate) fn clear(&self) {
+        self.0.store(0, Ordering::SeqCst);
+    }
+}
+
+// The index of the dll in the mock loader plus one, so 0 is invalid like a null `HMODULE`
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub(crate) struct DllHandle(usize);
+impl DllHandle {
+    pub(crate) unsafe fn load(lib_file_name: LPCWSTR, _flags: flags::LOAD_LIBRARY_FLAGS) -> Self {
+        let mut len = 0;
+        while *lib_file_name.add(len) != 0 {
+            len += 1;
+        }
+        let name = String::from_utf16_lossy(core::slice::from_raw_parts(lib_file_name, len));
+        Self(find_dll(&name).map_or(0, |index| index + 1))
+    }
+    pub(crate) fn is_invalid(&self) -> bool {
+        self.0 == 0
+    }
+    pub(crate) unsafe fn free(self) -> bool {
+        !self.is_invalid()
+    }
+    pub(crate) unsafe fn get_proc(&self, name: LPCSTR) -> Option<DllProcPtr> {
+        // Ordinals are passed like `MAKEINTRESOURCEA` does
+        let export = if (name as usize) >> 16 == 0 {
+            MockExport::Ordinal(name as usize as u16)
+        } else {
+            MockExport::Name(CStr::from_ptr(name as _).to_string_lossy().into_owned())
+        };
+        find_proc(self.0.checked_sub(1)?, &export)
+    }
+}
+
+#[repr(transparent)]
+pub(crate) struct AtomicDllProcPtr(AtomicUsize);
+impl AtomicDllProcPtr {
+    pub(crate) const fn empty() -> Self {
+        Self(AtomicUsize::new(0))
+    }
+    pub(crate) unsafe fn load(&self) -> Option<DllProcPtr> {
+        DllProcPtr::new(transmute::<usize, FARPROC>(self.0.load(Ordering::SeqCst)))
+    }
+    pub(crate) fn store(&self, handle: Option<DllProcPtr>) {
+        // Any function pointer fits in a usize
+        self.0.store(
+            handle
+                .map(|proc| unsafe { transmute::<DllProcPtr, usize>(proc) })
+                .unwrap_or(0),
+            Ordering::SeqCst,
+        );
+    }
+}
+
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub(crate) struct DllProcPtr(NonNullFarProc);
+impl DllProcPtr {
+    fn new(proc: FARPROC) -> Option<Self> {
+        proc.map(DllProcPtr)
+    }
+    pub(crate) unsafe fn transmute<T: Copy>(self) -> T {
+        *transmute::<&NonNullFarProc, &T>(&self.0)
+    }
+    pub(crate) unsafe fn from_sig<T: Copy>(sig: T) -> Option<Self> {
+        // Reading a `FARPROC` out of a smaller type would read past it
+        assert_eq!(
+            size_of::<T>(),
+            size_of::<FARPROC>(),
+            "the signature of an override must be a function pointer"
+        );
+        Self::new(*transmute::<&T, &FARPROC>(&sig))
+    }
+}
+
+pub(crate) const fn native_load_flags(bits: u32) -> flags::LOAD_LIBRARY_FLAGS {
+    bits
+}
+pub(crate) const fn load_flags_bits(flags: flags::LOAD_LIBRARY_FLAGS) -> u32 {
+    flags
+}
+
+// There is no ANSI code page without Windows, strings are passed as UTF-8
+pub(crate) fn wide_to_ansi(wide: &[u16]) -> Vec<u8> {
+    String::from_utf16_lossy(wide).into_bytes()
+}
@@ -1,5 +1,5 @@
 use core::{
-    mem::transmute,
+    mem::{size_of, transmute},
     ptr,
     sync::atomic::{AtomicPtr, Ordering},
 };
@@ -11,7 +11,11 @@ pub(crate) use winapi::shared::{
 pub use winapi::um::winnt::{LPCSTR, LPCWSTR};
 use winapi::{
     shared::minwindef::{__some_function, FARPROC, HINSTANCE__, TRUE},
-    um::libloaderapi::{FreeLibrary, GetProcAddress, LoadLibraryExW},
+    um::{
+        libloaderapi::{FreeLibrary, GetProcAddress, LoadLibraryExW},
+        stringapiset::WideCharToMultiByte,
+        winnls::CP_ACP,
+    },
 };
 
 pub mod flags {
@@ -86,6 +90,7 @@ impl AtomicDllProcPtr {
     }
 }
 
+#[derive(Clone, Copy)]
 #[repr(transparent)]
 pub(crate) struct DllProcPtr(ptr::NonNull<__some_function>);
 impl DllProcPtr {
@@ -93,6 +98,48 @@ impl DllProcPtr {
         ptr::NonNull::new(proc).map(DllProcPtr)
     }
     pub(crate) unsafe fn transmute<T: Copy>(self) -> T {
-        *transmute::<_, &T>(&self.0)
+        *transmute::<&ptr::NonNull<__some_function>, &T>(&self.0)
+    }
+    pub(crate) unsafe fn from_sig<T: Copy>(sig: T) -> Option<Self> {
+        // Reading a `FARPROC` out of a smaller type would read past it
+        assert_eq!(
+            size_of::<T>(),
+            size_of::<FARPROC>(),
+            "the signature of an override must be a function pointer"
+        );
+        Self::new(*transmute::<&T, &FARPROC>(&sig))
+    }
+}
+
+pub(crate) const fn native_load_flags(bits: u32) -> flags::LOAD_LIBRARY_FLAGS {
+    bits
+}
+pub(crate) const fn load_flags_bits(flags: flags::LOAD_LIBRARY_FLAGS) -> u32 {
+    flags
+}
+
+// Convert a UTF-16 string to the ANSI code page, characters it doesn't have become `?`
+pub(crate) fn wide_to_ansi(wide: &[u16]) -> Vec<u8> {
+    if wide.is_empty() {
+        return Vec::new();
+    }
+    unsafe {
+        let convert = |ansi: *mut u8, len: i32| {
+            WideCharToMultiByte(
+                CP_ACP,
+                0,
+                wide.as_ptr(),
+                wide.len() as i32,
+                ansi as _,
+                len,
+                ptr::null(),
+                ptr::null_mut(),
+            )
+        };
+        let len = convert(ptr::null_mut(), 0);
+        let mut ansi = vec![0; len.max(0) as usize];
+        let written = convert(ansi.as_mut_ptr(), len);
+        ansi.truncate(written.max(0) as usize);
+        ansi
     }
 }
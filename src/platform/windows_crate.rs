@@ -1,5 +1,6 @@
 use core::{
-    mem::transmute,
+    mem::{size_of, transmute},
+    ptr,
     sync::atomic::{AtomicIsize, AtomicUsize, Ordering},
 };
 
@@ -15,6 +16,7 @@ use windows::{
 type NonNullFarProc = unsafe extern "system" fn() -> isize;
 #[allow(non_camel_case_types)]
 pub(crate) type ULONG_PTR = usize;
+#[allow(clippy::upper_case_acronyms)]
 pub(crate) type WORD = u16;
 pub type LPCWSTR = *const u16;
 pub type LPCSTR = *const u8;
@@ -79,16 +81,20 @@ impl AtomicDllProcPtr {
         Self(AtomicUsize::new(0))
     }
     pub(crate) unsafe fn load(&self) -> Option<DllProcPtr> {
-        DllProcPtr::new(transmute(self.0.load(Ordering::SeqCst)))
+        DllProcPtr::new(transmute::<usize, FARPROC>(self.0.load(Ordering::SeqCst)))
     }
-    pub(crate) unsafe fn store(&self, handle: Option<DllProcPtr>) {
+    pub(crate) fn store(&self, handle: Option<DllProcPtr>) {
+        // Any function pointer fits in a usize
         self.0.store(
-            handle.map(|proc| transmute(proc)).unwrap_or(0),
+            handle
+                .map(|proc| unsafe { transmute::<DllProcPtr, usize>(proc) })
+                .unwrap_or(0),
             Ordering::SeqCst,
         );
     }
 }
 
+#[derive(Clone, Copy)]
 #[repr(transparent)]
 pub(crate) struct DllProcPtr(NonNullFarProc);
 impl DllProcPtr {
@@ -96,6 +102,65 @@ impl DllProcPtr {
         proc.map(DllProcPtr)
     }
     pub(crate) unsafe fn transmute<T: Copy>(self) -> T {
-        *transmute::<_, &T>(&self.0)
+        *transmute::<&NonNullFarProc, &T>(&self.0)
+    }
+    pub(crate) unsafe fn from_sig<T: Copy>(sig: T) -> Option<Self> {
+        // Reading a `FARPROC` out of a smaller type would read past it
+        assert_eq!(
+            size_of::<T>(),
+            size_of::<FARPROC>(),
+            "the signature of an override must be a function pointer"
+        );
+        Self::new(*transmute::<&T, &FARPROC>(&sig))
+    }
+}
+
+pub(crate) const fn native_load_flags(bits: u32) -> flags::LOAD_LIBRARY_FLAGS {
+    flags::LOAD_LIBRARY_FLAGS(bits)
+}
+pub(crate) const fn load_flags_bits(flags: flags::LOAD_LIBRARY_FLAGS) -> u32 {
+    flags.0
+}
+
+const CP_ACP: u32 = 0;
+
+// Declared here since its signature differs between the supported versions of windows-rs
+#[link(name = "kernel32")]
+extern "system" {
+    fn WideCharToMultiByte(
+        code_page: u32,
+        flags: u32,
+        wide_char_str: *const u16,
+        wide_char_len: i32,
+        multi_byte_str: *mut u8,
+        multi_byte_len: i32,
+        default_char: *const u8,
+        used_default_char: *mut i32,
+    ) -> i32;
+}
+
+// Convert a UTF-16 string to the ANSI code page, characters it doesn't have become `?`
+pub(crate) fn wide_to_ansi(wide: &[u16]) -> Vec<u8> {
+    if wide.is_empty() {
+        return Vec::new();
+    }
+    unsafe {
+        let convert = |ansi: *mut u8, len: i32| {
+            WideCharToMultiByte(
+                CP_ACP,
+                0,
+                wide.as_ptr(),
+                wide.len() as i32,
+                ansi,
+                len,
+                ptr::null(),
+                ptr::null_mut(),
+            )
+        };
+        let len = convert(ptr::null_mut(), 0);
+        let mut ansi = vec![0; len.max(0) as usize];
+        let written = convert(ansi.as_mut_ptr(), len);
+        ansi.truncate(written.max(0) as usize);
+        ansi
     }
 }
@@ -0,0 +1,46 @@
+use crate::{LoadFlags, Proc};
+
+// Filled at link time with one entry per #[dll] macro expansion
+#[doc(hidden)]
+#[linkme::distributed_slice]
+pub static DLL_REGISTRATIONS: [DllRegistration] = [..];
+
+#[doc(hidden)]
+pub struct DllRegistration {
+    pub lib: &'static str,
+    pub flags: LoadFlags,
+    pub procs: &'static [Proc],
+}
+
+/// A dll declared with the **`#[dll]`** macro somewhere in the binary
+#[derive(Debug, Clone)]
+pub struct RegisteredDll {
+    pub lib: &'static str,
+    pub flags: LoadFlags,
+    /// The functions and statics declared for the dll, in all extern blocks
+    pub procs: Vec<Proc>,
+}
+
+/// Every dll the binary may load with the **`#[dll]`** macro, whether it was used yet or not
+///
+/// Declarations of the same dll with the same flags are merged into one entry.
+/// The order of the entries is unspecified.
+pub fn registered_dlls() -> Vec<RegisteredDll> {
+    let mut dlls: Vec<RegisteredDll> = Vec::new();
+
+    for registration in DLL_REGISTRATIONS.iter() {
+        let existing = dlls.iter_mut().find(|dll| {
+            dll.lib.eq_ignore_ascii_case(registration.lib) && dll.flags == registration.flags
+        });
+        match existing {
+            Some(dll) => dll.procs.extend_from_slice(registration.procs),
+            None => dlls.push(RegisteredDll {
+                lib: registration.lib,
+                flags: registration.flags,
+                procs: registration.procs.to_vec(),
+            }),
+        }
+    }
+
+    dlls
+}
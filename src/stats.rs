@@ -0,0 +1,47 @@
+use crate::Proc;
+
+/// Snapshot of the load counters of every dll that the **`#[dll]`** macro tried to load
+///
+/// With the `registry` feature, the dlls that were declared but never used are included too,
+/// with zeroed counters. Without it, dlls that were never used are not included. The counters
+/// are updated with relaxed atomic operations, they are cheap enough to always be enabled.
+pub fn stats() -> Vec<DllStats> {
+    #[allow(unused_mut)]
+    let mut stats = crate::cache::dll_stats();
+    #[cfg(feature = "registry")]
+    for registered in crate::registered_dlls() {
+        if !stats.iter().any(|dll| dll.lib.eq_ignore_ascii_case(registered.lib)) {
+            stats.push(DllStats {
+                lib: registered.lib,
+                load_attempts: 0,
+                load_failures: 0,
+                procs: Vec::new(),
+            });
+        }
+    }
+    stats
+}
+
+#[derive(Debug, Clone)]
+pub struct DllStats {
+    /// The name of the dll, see [`WindowsDll::LIB`](crate::WindowsDll::LIB)
+    pub lib: &'static str,
+    /// Calls to `LoadLibraryExW`, which happen until the dll was loaded successfully
+    pub load_attempts: usize,
+    pub load_failures: usize,
+    /// Every function or static of the dll that was resolved at least once
+    pub procs: Vec<ProcStats>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcStats {
+    pub proc: Proc,
+    /// See [`WindowsDllProc::CACHE_INDEX`](crate::WindowsDllProc::CACHE_INDEX)
+    pub cache_index: usize,
+    /// Calls to `GetProcAddress`, which happen until the proc was found
+    pub resolve_attempts: usize,
+    pub resolve_failures: usize,
+    /// Calls made through the generated function, only counted with the `call-stats` feature
+    #[cfg(feature = "call-stats")]
+    pub calls: usize,
+}
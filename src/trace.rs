@@ -0,0 +1,181 @@
+use crate::{Proc, WindowsDll, WindowsDllProc};
+use core::fmt::{self, Debug};
+use once_cell::sync::OnceCell;
+use std::time::{Duration, Instant};
+
+static CALL_HOOK: OnceCell<Box<dyn CallHook>> = OnceCell::new();
+
+/// Observes every call made through a function generated by the **`#[dll]`** macro
+///
+/// Set it with [`set_call_hook`].
+pub trait CallHook: Send + Sync + 'static {
+    /// Called after the function was loaded, right before calling it
+    fn before(&self, call: &Call<'_>);
+    /// Called right after the function returned
+    fn after(&self, call: &CallReturn<'_>);
+}
+
+/// A call about to be made, passed to [`CallHook::before`]
+#[derive(Debug)]
+pub struct Call<'a> {
+    /// The name of the dll, see [`WindowsDll::LIB`]
+    pub lib: &'static str,
+    pub proc: &'a Proc,
+    pub args: CallArgs<'a>,
+}
+
+/// The arguments of a [`Call`] and their names
+///
+/// Arguments with a type that doesn't implement `Debug` are formatted as `_`.
+#[derive(Clone, Copy)]
+pub struct CallArgs<'a>(&'a [(&'static str, &'a dyn Debug)]);
+impl<'a> CallArgs<'a> {
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &'a dyn Debug)> + 'a {
+        self.0.iter().copied()
+    }
+}
+impl Debug for CallArgs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tuple = f.debug_tuple("");
+        for (name, value) in self.iter() {
+            tuple.field(&format_args!("{}: {:?}", name, value));
+        }
+        tuple.finish()
+    }
+}
+
+/// A finished call, passed to [`CallHook::after`]
+#[derive(Debug)]
+pub struct CallReturn<'a> {
+    /// The name of the dll, see [`WindowsDll::LIB`]
+    pub lib: &'static str,
+    pub proc: &'a Proc,
+    /// The value returned by the dll, before any conversion by the generated function
+    pub value: &'a dyn Debug,
+    pub duration: Duration,
+}
+
+/// Set the global [`CallHook`], this can only be done once
+pub fn set_call_hook(hook: Box<dyn CallHook>) -> Result<(), SetCallHookError> {
+    CALL_HOOK.set(hook).map_err(|_| SetCallHookError(()))
+}
+
+/// Returned by [`set_call_hook`] if a hook was already set
+pub struct SetCallHookError(());
+
+impl std::error::Error for SetCallHookError {}
+
+impl fmt::Display for SetCallHookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a call hook was already set")
+    }
+}
+impl Debug for SetCallHookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SetCallHookError")
+    }
+}
+
+// Used by the generated functions, returns `None` if no hook is set
+#[doc(hidden)]
+pub fn before<P: WindowsDllProc>(args: &[(&'static str, &dyn Debug)]) -> Option<Instant> {
+    let hook = CALL_HOOK.get()?;
+    hook.before(&Call {
+        lib: P::Dll::LIB,
+        proc: &P::PROC,
+        args: CallArgs(args),
+    });
+
+    Some(Instant::now())
+}
+#[doc(hidden)]
+pub fn after<P: WindowsDllProc>(started: Option<Instant>, value: &dyn Debug) {
+    if let (Some(started), Some(hook)) = (started, CALL_HOOK.get()) {
+        hook.after(&CallReturn {
+            lib: P::Dll::LIB,
+            proc: &P::PROC,
+            value,
+            duration: started.elapsed(),
+        });
+    }
+}
+
+// Autoref specialization, so that `Debug` is used if the argument implements it:
+// `(&&TraceArg(&value)).trace_arg()` picks `ViaDebug` before falling back to `ViaOpaque`
+#[doc(hidden)]
+pub struct TraceArg<'a, T>(pub &'a T);
+
+#[doc(hidden)]
+pub trait ViaDebug<'a> {
+    fn trace_arg(&self) -> &'a dyn Debug;
+}
+impl<'a, T: Debug> ViaDebug<'a> for &TraceArg<'a, T> {
+    fn trace_arg(&self) -> &'a dyn Debug {
+        self.0
+    }
+}
+
+#[doc(hidden)]
+pub trait ViaOpaque<'a> {
+    fn trace_arg(&self) -> &'a dyn Debug;
+}
+impl<'a, T> ViaOpaque<'a> for TraceArg<'a, T> {
+    fn trace_arg(&self) -> &'a dyn Debug {
+        &Opaque
+    }
+}
+
+struct Opaque;
+impl Debug for Opaque {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("_")
+    }
+}
+
+/// A [`CallHook`] logging calls with the `log` crate at the `trace` level
+#[cfg(feature = "log")]
+pub struct LogHook;
+
+#[cfg(feature = "log")]
+impl CallHook for LogHook {
+    fn before(&self, call: &Call<'_>) {
+        log::trace!(target: "windows_dll", "{}!{}{:?}", call.lib, call.proc, call.args);
+    }
+    fn after(&self, call: &CallReturn<'_>) {
+        log::trace!(
+            target: "windows_dll",
+            "{}!{} returned {:?} after {:?}",
+            call.lib,
+            call.proc,
+            call.value,
+            call.duration,
+        );
+    }
+}
+
+/// A [`CallHook`] emitting `tracing` events at the `TRACE` level
+#[cfg(feature = "tracing")]
+pub struct TracingHook;
+
+#[cfg(feature = "tracing")]
+impl CallHook for TracingHook {
+    fn before(&self, call: &Call<'_>) {
+        tracing::trace!(
+            target: "windows_dll",
+            lib = call.lib,
+            proc = %call.proc,
+            args = ?call.args,
+            "call",
+        );
+    }
+    fn after(&self, call: &CallReturn<'_>) {
+        tracing::trace!(
+            target: "windows_dll",
+            lib = call.lib,
+            proc = %call.proc,
+            value = ?call.value,
+            duration = ?call.duration,
+            "return",
+        );
+    }
+}
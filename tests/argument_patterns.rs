@@ -0,0 +1,22 @@
+use windows_dll::dll;
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "MulDiv"]
+    fn mul_div(func: i32, _: i32, cache: i32) -> i32;
+    #[link_name = "MulDiv"]
+    fn mul_div_pattern(number: i32, Numerator(numerator): Numerator, arg1: i32) -> i32;
+}
+
+#[repr(transparent)]
+struct Numerator(i32);
+
+#[test]
+fn shadowing_argument_names() {
+    assert_eq!(unsafe { mul_div(6, 4, 3) }, 8);
+}
+
+#[test]
+fn pattern_arguments() {
+    assert_eq!(unsafe { mul_div_pattern(6, Numerator(4), 3) }, 8);
+}
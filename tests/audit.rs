@@ -0,0 +1,19 @@
+#![cfg(feature = "audit")]
+
+use windows_dll::{
+    macro_internal::{audit_record, audit_record_len},
+    Proc,
+};
+
+const PROCS: &[Proc] = &[Proc::Name("GetDesktopWindow"), Proc::Ordinal(2)];
+const LEN: usize = audit_record_len("user32", PROCS);
+static RECORD: [u8; LEN] = audit_record::<LEN>("user32", PROCS);
+
+#[test]
+fn record_layout() {
+    let mut expected = b"WDLL\x01\x06\x00user32\x02\x00".to_vec();
+    expected.extend_from_slice(b"\x00\x10\x00GetDesktopWindow");
+    expected.extend_from_slice(b"\x01\x02\x00");
+
+    assert_eq!(&RECORD[..], &expected[..]);
+}
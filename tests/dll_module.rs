@@ -0,0 +1,42 @@
+use windows_dll::{dll, WindowsDll, WindowsDllProc};
+
+#[dll(msvcrt, type_name = Msvcrt)]
+mod msvcrt {
+    use core::ffi::c_void;
+
+    // The CRT uses the C calling convention, the blocks of a module share the dll type
+    extern "C" {
+        #[link_name = "_errno"]
+        pub fn errno() -> *mut i32;
+    }
+
+    extern "C" {
+        pub fn sprintf(buffer: *mut u8, format: *const u8, ...) -> i32;
+        #[allow(non_upper_case_globals)]
+        pub static mut _environ: *mut *mut c_void;
+    }
+
+    pub const NAME: &str = "msvcrt";
+}
+
+#[test]
+fn shared_dll_type() {
+    assert_eq!(<msvcrt::Msvcrt as WindowsDll>::LIB, "msvcrt");
+    assert_eq!(<msvcrt::Msvcrt as WindowsDll>::LEN, 3);
+    assert_eq!(<msvcrt::errno as WindowsDllProc>::CACHE_INDEX, 0);
+    assert_eq!(<msvcrt::sprintf as WindowsDllProc>::CACHE_INDEX, 1);
+    assert_eq!(<msvcrt::_environ as WindowsDllProc>::CACHE_INDEX, 2);
+    assert_eq!(msvcrt::NAME, "msvcrt");
+}
+
+#[test]
+fn call_module_functions() {
+    let mut buffer = [0u8; 8];
+    unsafe {
+        assert!(!msvcrt::errno().is_null());
+        assert!(!msvcrt::_environ().is_null());
+
+        let len = msvcrt::sprintf()(buffer.as_mut_ptr(), b"%d\0".as_ptr(), 12i32);
+        assert_eq!(&buffer[..len as usize], b"12");
+    }
+}
@@ -0,0 +1,81 @@
+use platform::*;
+use windows_dll::{dll, WindowsDll};
+
+#[dll("user32.dll", type_name = User32, vis = pub(crate))]
+extern "system" {
+    #[link_name = "GetDesktopWindow"]
+    fn get_desktop_window() -> HWND;
+}
+
+// A dll type only used by `shared_type`, which frees it
+#[dll(kernel32, type_name = Kernel32)]
+extern "system" {
+    #[link_name = "GetCurrentProcessId"]
+    fn get_current_process_id() -> u32;
+}
+
+// Add more functions to the `Kernel32` type declared above
+#[dll(type_name = Kernel32)]
+extern "system" {
+    #[link_name = "GetCurrentThreadId"]
+    fn get_current_thread_id() -> u32;
+}
+
+// A second type for the same dll in the same module
+#[dll("user32.dll", type_name = User32Fallible)]
+extern "system" {
+    #[link_name = "GetDesktopWindow"]
+    #[fallible]
+    fn try_get_desktop_window() -> HWND;
+}
+
+#[dll("api-ms-win-core-synch-l1-2-0")]
+extern "system" {
+    #[link_name = "WakeByAddressAll"]
+    fn wake_by_address_all(address: *mut c_void);
+}
+
+#[test]
+fn custom_type_name() {
+    assert_eq!(<User32 as WindowsDll>::LIB, "user32.dll");
+    assert_eq!(<User32Fallible as WindowsDll>::LIB, "user32.dll");
+    unsafe {
+        assert!(User32::exists());
+        assert!(get_desktop_window::exists());
+        assert!(User32Fallible::exists());
+        assert!(try_get_desktop_window::exists());
+    }
+}
+
+#[test]
+fn shared_type() {
+    unsafe {
+        assert!(get_current_process_id::exists());
+        assert!(get_current_thread_id::exists());
+
+        assert!(Kernel32::free());
+        assert!(get_current_process_id::exists());
+        assert!(get_current_thread_id::exists());
+    }
+}
+
+#[test]
+fn sanitized_type_name() {
+    assert_eq!(
+        <api_ms_win_core_synch_l1_2_0 as WindowsDll>::LIB,
+        "api-ms-win-core-synch-l1-2-0"
+    );
+    assert!(unsafe { wake_by_address_all::exists() });
+}
+
+#[cfg(feature = "winapi")]
+mod platform {
+    pub use core::ffi::c_void;
+    pub use winapi::shared::windef::HWND;
+}
+
+#[cfg(feature = "windows")]
+mod platform {
+    pub use core::ffi::c_void;
+    pub use windows::Win32::Foundation::HWND;
+}
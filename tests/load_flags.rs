@@ -0,0 +1,97 @@
+use platform::*;
+use windows_dll::{dll, flags::*, LoadFlags, LoadFlagsError};
+
+#[dll("bcrypt", LoadFlags::SEARCH_SYSTEM32)]
+extern "system" {
+    #[link_name = "BCryptAddContextFunction"]
+    fn bcrypt_add_context_function(
+        dw_table: ULONG,
+        psz_context: LPCWSTR,
+        dw_interface: ULONG,
+        psz_function: LPCWSTR,
+        dw_position: ULONG,
+    ) -> BOOL;
+}
+
+#[dll(
+    "firewallapi.dll",
+    LoadFlags::SEARCH_APPLICATION_DIR | LoadFlags::SEARCH_DLL_LOAD_DIR
+)]
+extern "system" {
+    #[link_name = "FWAddFirewallRule"]
+    pub fn fw_add_firewall_rule() -> ();
+}
+
+#[dll("ntdll", LOAD_LIBRARY_SEARCH_SYSTEM32 | LoadFlags::SEARCH_APPLICATION_DIR)]
+extern "system" {
+    #[link_name = "RtlGetVersion"]
+    fn rtl_get_version(lp_version_information: *mut u8) -> i32;
+}
+
+#[test]
+fn assert_load_flags_passed() {
+    assert!(
+        unsafe { bcrypt_add_context_function::exists() },
+        "Didn't find bcrypt.dll in system dir..."
+    );
+    assert!(
+        unsafe { !fw_add_firewall_rule::exists() },
+        "Found firewallapi.dll in application dir..."
+    );
+    assert!(
+        unsafe { rtl_get_version::exists() },
+        "Didn't find ntdll.dll in system dir..."
+    );
+}
+
+#[test]
+fn convert_native_flags() {
+    let flags = LoadFlags::SEARCH_SYSTEM32 | LoadFlags::SEARCH_APPLICATION_DIR;
+
+    assert_eq!(LoadFlags::from(LOAD_LIBRARY_SEARCH_SYSTEM32), LoadFlags::SEARCH_SYSTEM32);
+    assert_eq!(LoadFlags::from(flags.to_native()), flags);
+    assert_eq!(flags.bits(), 0x0a00);
+}
+
+#[test]
+fn validate_flags() {
+    assert!(LoadFlags::empty().validate().is_ok());
+    assert!((LoadFlags::SEARCH_SYSTEM32 | LoadFlags::SEARCH_USER_DIRS)
+        .validate()
+        .is_ok());
+    assert!((LoadFlags::AS_DATAFILE | LoadFlags::AS_IMAGE_RESOURCE)
+        .validate()
+        .is_ok());
+    assert_eq!(
+        (LoadFlags::AS_DATAFILE | LoadFlags::SEARCH_SYSTEM32).validate(),
+        Err(LoadFlagsError::DatafileWithSearchFlags)
+    );
+    assert_eq!(
+        (LoadFlags::WITH_ALTERED_SEARCH_PATH | LoadFlags::SEARCH_DEFAULT_DIRS).validate(),
+        Err(LoadFlagsError::AlteredSearchPathWithSearchFlags)
+    );
+    assert_eq!(
+        (LoadFlags::AS_DATAFILE | LoadFlags::AS_DATAFILE_EXCLUSIVE).validate(),
+        Err(LoadFlagsError::DatafileWithDatafileExclusive)
+    );
+    assert_eq!(
+        LoadFlags::from_bits_retain(0x4).validate(),
+        Err(LoadFlagsError::UnknownFlags(0x4))
+    );
+    assert_eq!(LoadFlags::from_bits(0x4), None);
+}
+
+#[cfg(feature = "winapi")]
+mod platform {
+    pub use winapi::shared::{
+        minwindef::{BOOL, ULONG},
+        ntdef::LPCWSTR,
+    };
+}
+
+#[cfg(feature = "windows")]
+mod platform {
+    pub use windows::{core::PCWSTR as LPCWSTR, Win32::Foundation::BOOL};
+
+    pub type ULONG = u32;
+}
@@ -0,0 +1,67 @@
+use windows_dll::{
+    flags::{self, LOAD_LIBRARY_FLAGS},
+    macro_internal::{DllCache, LPCSTR, LPCWSTR},
+    Error, Proc, WindowsDll, WindowsDllProc,
+};
+
+// Implemented by hand without `WindowsDllProc::cache`, like before the macro cached each
+// function separately
+enum Kernel32 {}
+
+impl WindowsDll for Kernel32 {
+    const LEN: usize = 2;
+    const LIB: &'static str = "kernel32";
+    const LIB_LPCWSTR: LPCWSTR = [
+        'k' as u16, 'e' as u16, 'r' as u16, 'n' as u16, 'e' as u16, 'l' as u16, '3' as u16,
+        '2' as u16, 0,
+    ]
+    .as_ptr();
+    const FLAGS: LOAD_LIBRARY_FLAGS = flags::NO_FLAGS;
+
+    unsafe fn cache() -> &'static DllCache<Self> {
+        static LIB_CACHE: DllCache<Kernel32> = DllCache::empty();
+
+        &LIB_CACHE
+    }
+}
+
+enum GetTickCount {}
+
+impl WindowsDllProc for GetTickCount {
+    type Dll = Kernel32;
+    type Sig = unsafe extern "system" fn() -> u32;
+    const CACHE_INDEX: usize = 0;
+    const PROC: Proc = Proc::Name("GetTickCount");
+    const PROC_LPCSTR: LPCSTR = b"GetTickCount\0".as_ptr() as _;
+
+    unsafe fn proc() -> Result<Self::Sig, Error<Self>> {
+        Kernel32::cache().get_proc::<Self>()
+    }
+}
+
+enum Missing {}
+
+impl WindowsDllProc for Missing {
+    type Dll = Kernel32;
+    type Sig = unsafe extern "system" fn();
+    const CACHE_INDEX: usize = 1;
+    const PROC: Proc = Proc::Name("ThisFunctionDoesNotExist");
+    const PROC_LPCSTR: LPCSTR = b"ThisFunctionDoesNotExist\0".as_ptr() as _;
+
+    unsafe fn proc() -> Result<Self::Sig, Error<Self>> {
+        Kernel32::cache().get_proc::<Self>()
+    }
+}
+
+#[test]
+fn manual_impl() {
+    unsafe {
+        let get_tick_count = GetTickCount::proc().unwrap();
+        get_tick_count();
+        assert!(GetTickCount::exists());
+        assert!(!Missing::exists());
+
+        assert!(Kernel32::free());
+        assert!(GetTickCount::exists());
+    }
+}
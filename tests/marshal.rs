@@ -0,0 +1,89 @@
+use windows_dll::dll;
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "GetModuleHandleW"]
+    fn get_module_handle_w(#[wide] module_name: &str) -> isize;
+    #[link_name = "GetModuleHandleA"]
+    #[fallible]
+    fn get_module_handle_a(#[ansi] module_name: &str) -> isize;
+    #[link_name = "SetEnvironmentVariableW"]
+    #[bool]
+    fn set_environment_variable(#[wide] name: &str, #[wide] value: String) -> bool;
+    #[link_name = "SetEnvironmentVariableA"]
+    #[bool]
+    fn set_environment_variable_a(#[ansi] name: &str, #[ansi] value: &str) -> bool;
+    #[link_name = "GetSystemTimeAsFileTime"]
+    #[safe]
+    fn get_system_time(#[out] file_time: &mut u64);
+}
+
+#[dll(user32)]
+extern "system" {
+    #[link_name = "EnableWindow"]
+    #[bool]
+    fn enable_window(window: isize, #[bool] enable: bool) -> bool;
+}
+
+#[test]
+fn string_arguments() {
+    unsafe {
+        assert_ne!(get_module_handle_w("kernel32.dll"), 0);
+        assert_eq!(get_module_handle_w("not_loaded.dll"), 0);
+        assert_eq!(
+            get_module_handle_a("kernel32.dll").unwrap(),
+            get_module_handle_w("kernel32.dll")
+        );
+        assert!(set_environment_variable("WINDOWS_DLL_MARSHAL", "1".to_string()));
+    }
+    assert_eq!(std::env::var("WINDOWS_DLL_MARSHAL").unwrap(), "1");
+}
+
+#[test]
+fn ansi_code_page() {
+    // In every Western and UTF-8 ANSI code page
+    unsafe {
+        assert!(set_environment_variable_a(
+            "WINDOWS_DLL_MARSHAL_ANSI",
+            "café"
+        ));
+    }
+    assert_eq!(std::env::var("WINDOWS_DLL_MARSHAL_ANSI").unwrap(), "café");
+}
+
+#[test]
+#[should_panic(expected = "contains a nul character")]
+fn wide_nul_character() {
+    unsafe {
+        get_module_handle_w("kernel32.dll\0user32.dll");
+    }
+}
+
+#[test]
+#[should_panic(expected = "contains a nul character")]
+fn ansi_nul_character() {
+    unsafe {
+        set_environment_variable_a("WINDOWS_DLL_MARSHAL_NUL", "a\0b");
+    }
+}
+
+#[test]
+fn out_arguments() {
+    let mut file_time = 0;
+    get_system_time(&mut file_time);
+    assert_ne!(file_time, 0);
+}
+
+#[test]
+fn raw_functions() {
+    let name: Vec<u16> = "kernel32.dll\0".encode_utf16().collect();
+    let mut file_time = 0;
+    unsafe {
+        assert_ne!(get_module_handle_w::raw(name.as_ptr()), 0);
+        get_system_time::raw(&mut file_time);
+        // No window, so the previous state can't be returned
+        assert_eq!(enable_window::raw(0, 1), 0);
+        assert!(!enable_window(0, true));
+    }
+    assert_ne!(file_time, 0);
+}
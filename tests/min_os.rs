@@ -0,0 +1,37 @@
+use windows_dll::dll;
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "GetCurrentProcessId"]
+    #[min_os(major = 5, minor = 1)]
+    fn current_process_id() -> u32;
+    #[link_name = "GetCurrentProcessId"]
+    #[min_os(build = 4294967295)]
+    #[fallible]
+    fn future_process_id() -> u32;
+    #[min_os(major = 4294967295, build = 0)]
+    static FutureExport: u32;
+}
+
+#[test]
+fn supported_os() {
+    unsafe {
+        assert!(current_process_id::exists());
+        assert_eq!(current_process_id(), std::process::id());
+    }
+}
+
+#[test]
+fn unsupported_os() {
+    unsafe {
+        // The export exists, but isn't looked up on older versions of Windows
+        assert!(!future_process_id::exists());
+        assert!(future_process_id::ptr().is_none());
+        assert!(future_process_id::address().is_none());
+        assert!(matches!(
+            future_process_id(),
+            Err(err) if matches!(err.kind, windows_dll::ErrorKind::Proc)
+        ));
+        assert!(!FutureExport::exists());
+    }
+}
@@ -0,0 +1,121 @@
+#![cfg(feature = "mock")]
+
+use windows_dll::{
+    dll, mock, os_version, override_os_version, restore_os_version, ErrorKind, OsVersion,
+};
+
+#[dll(user32)]
+extern "system" {
+    #[allow(non_snake_case)]
+    fn GetDpiForSystem() -> u32;
+    #[link_name = "GetSystemMetrics"]
+    #[fallible]
+    fn get_system_metrics(index: i32) -> i32;
+    #[link_ordinal = 2]
+    #[fallible]
+    fn by_ordinal() -> u32;
+}
+
+#[dll("C:\\Windows\\System32\\Not_Registered.DLL")]
+extern "system" {
+    #[link_name = "Anything"]
+    #[fallible]
+    fn not_registered();
+}
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "GetCurrentProcessId"]
+    #[min_os(major = 10, minor = 0, build = 17763)]
+    fn current_process_id() -> u32;
+}
+
+extern "system" fn get_dpi_for_system() -> u32 {
+    144
+}
+extern "system" fn ordinal_2() -> u32 {
+    2
+}
+extern "system" fn get_current_process_id() -> u32 {
+    42
+}
+
+#[allow(non_snake_case, clippy::upper_case_acronyms)]
+#[repr(C)]
+struct OSVERSIONINFOW {
+    dwOSVersionInfoSize: u32,
+    dwMajorVersion: u32,
+    dwMinorVersion: u32,
+    dwBuildNumber: u32,
+    dwPlatformId: u32,
+    szCSDVersion: [u16; 128],
+}
+
+#[allow(non_snake_case)]
+unsafe extern "system" fn RtlGetVersion(info: *mut OSVERSIONINFOW) -> i32 {
+    (*info).dwMajorVersion = 10;
+    (*info).dwMinorVersion = 0;
+    (*info).dwBuildNumber = 19045;
+    (*info).dwPlatformId = 2;
+    0
+}
+
+#[test]
+fn mock_functions() {
+    unsafe {
+        mock::register_proc(
+            "USER32.dll",
+            "GetDpiForSystem",
+            get_dpi_for_system as extern "system" fn() -> u32,
+        );
+        mock::register_ordinal("user32", 2, ordinal_2 as extern "system" fn() -> u32);
+
+        assert_eq!(GetDpiForSystem(), 144);
+        assert_eq!(by_ordinal().unwrap(), 2);
+        assert!(get_system_metrics(0).is_err());
+        assert!(!get_system_metrics::exists());
+
+        // Dlls are found by their file name
+        assert!(matches!(not_registered().unwrap_err().kind, ErrorKind::Lib));
+        mock::register_dll("not_registered");
+        assert!(matches!(
+            not_registered().unwrap_err().kind,
+            ErrorKind::Proc
+        ));
+    }
+}
+
+// The version is queried once, so it is only used in this test
+#[test]
+fn mock_os_version() {
+    unsafe {
+        mock::register_proc(
+            "ntdll",
+            "RtlGetVersion",
+            RtlGetVersion as unsafe extern "system" fn(*mut OSVERSIONINFOW) -> i32,
+        );
+        mock::register_proc(
+            "kernel32",
+            "GetCurrentProcessId",
+            get_current_process_id as extern "system" fn() -> u32,
+        );
+    }
+
+    let actual = os_version().unwrap();
+    assert_eq!(actual.to_string(), "10.0.19045");
+    assert!(unsafe { current_process_id::exists() });
+
+    override_os_version(OsVersion {
+        major: 6,
+        minor: 1,
+        build: 7601,
+        platform_id: 2,
+        csd: "Service Pack 1".to_string(),
+    });
+    assert_eq!(os_version().unwrap().csd, "Service Pack 1");
+    assert!(unsafe { !current_process_id::exists() });
+
+    restore_os_version();
+    assert_eq!(os_version(), Some(actual));
+    assert_eq!(unsafe { current_process_id() }, 42);
+}
@@ -0,0 +1,58 @@
+use platform::*;
+use windows_dll::{dll, flags::*, LoadFlags};
+
+#[dll(name = "bcrypt", flags = LOAD_LIBRARY_SEARCH_SYSTEM32)]
+extern "system" {
+    #[link_name = "BCryptAddContextFunction"]
+    fn bcrypt_add_context_function(
+        dw_table: ULONG,
+        psz_context: LPCWSTR,
+        dw_interface: ULONG,
+        psz_function: LPCWSTR,
+        dw_position: ULONG,
+    ) -> BOOL;
+}
+
+#[dll("firewallapi.dll", flags = LoadFlags::SEARCH_APPLICATION_DIR)]
+extern "system" {
+    #[link_name = "FWAddFirewallRule"]
+    pub fn fw_add_firewall_rule() -> ();
+}
+
+#[dll(name = user32)]
+extern "system" {
+    #[link_name = "GetDesktopWindow"]
+    pub fn get_desktop_window() -> HWND;
+}
+
+#[test]
+fn assert_named_args_passed() {
+    assert!(
+        unsafe { bcrypt_add_context_function::exists() },
+        "Didn't find bcrypt.dll in system dir..."
+    );
+    assert!(
+        unsafe { !fw_add_firewall_rule::exists() },
+        "Found firewallapi.dll in application dir..."
+    );
+    assert!(unsafe { get_desktop_window::exists() });
+}
+
+#[cfg(feature = "winapi")]
+mod platform {
+    pub use winapi::shared::{
+        minwindef::{BOOL, ULONG},
+        ntdef::LPCWSTR,
+        windef::HWND,
+    };
+}
+
+#[cfg(feature = "windows")]
+mod platform {
+    pub use windows::{
+        core::PCWSTR as LPCWSTR,
+        Win32::Foundation::{BOOL, HWND},
+    };
+
+    pub type ULONG = u32;
+}
@@ -0,0 +1,50 @@
+use windows_dll::{dll, os_version, override_os_version, restore_os_version, OsVersion};
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "GetCurrentProcessId"]
+    #[min_os(major = 10, minor = 0, build = 17763)]
+    fn current_process_id() -> u32;
+}
+
+fn version(major: u32, minor: u32, build: u32, csd: &str) -> OsVersion {
+    OsVersion {
+        major,
+        minor,
+        build,
+        platform_id: 2,
+        csd: csd.to_string(),
+    }
+}
+
+#[test]
+fn compare_versions() {
+    let windows_7 = version(6, 1, 7601, "Service Pack 1");
+    assert!(windows_7.is_at_least(6, 1, 7600));
+    assert!(windows_7.is_at_least(6, 0, 9999));
+    assert!(!windows_7.is_at_least(6, 2, 0));
+    assert_eq!(windows_7.to_string(), "6.1.7601 Service Pack 1");
+
+    let windows_11 = version(10, 0, 22000, "");
+    assert!(windows_11.is_at_least(10, 0, 17763));
+    assert!(!windows_11.is_at_least(10, 0, 22621));
+    assert_eq!(windows_11.to_string(), "10.0.22000");
+}
+
+// The overridden version is global, so it is only changed in this test
+#[test]
+fn query_and_override_version() {
+    let actual = os_version().expect("RtlGetVersion failed");
+    assert_eq!(actual.platform_id, 2);
+    assert!(actual.is_at_least(6, 0, 0));
+
+    override_os_version(version(6, 1, 7601, "Service Pack 1"));
+    assert_eq!(os_version().unwrap().build, 7601);
+    assert!(unsafe { !current_process_id::exists() });
+
+    override_os_version(version(10, 0, 17763, ""));
+    assert!(unsafe { current_process_id::exists() });
+
+    restore_os_version();
+    assert_eq!(os_version(), Some(actual));
+}
@@ -0,0 +1,48 @@
+use windows_dll::dll;
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "MulDiv"]
+    fn mul_div(number: i32, numerator: i32, denominator: i32) -> i32;
+}
+
+#[dll("this_dll_does_not_exist")]
+extern "system" {
+    #[fallible]
+    fn missing_function(value: u32) -> u32;
+}
+
+unsafe extern "system" fn fake_mul_div(_: i32, _: i32, _: i32) -> i32 {
+    -1
+}
+unsafe extern "system" fn fake_missing_function(value: u32) -> u32 {
+    value + 1
+}
+
+#[test]
+fn override_and_restore() {
+    unsafe {
+        assert_eq!(mul_div(6, 4, 3), 8);
+
+        mul_div::override_with(fake_mul_div);
+        assert_eq!(mul_div(6, 4, 3), -1);
+        assert_eq!(mul_div::address(), Some(fake_mul_div as *const _));
+
+        mul_div::restore();
+        assert_eq!(mul_div(6, 4, 3), 8);
+    }
+}
+
+#[test]
+fn override_without_dll() {
+    unsafe {
+        assert!(missing_function(1).is_err());
+
+        missing_function::override_with(fake_missing_function);
+        assert!(missing_function::exists());
+        assert_eq!(missing_function(1).unwrap(), 2);
+
+        missing_function::restore();
+        assert!(!missing_function::exists());
+    }
+}
@@ -0,0 +1,37 @@
+use core::ffi::c_void;
+use windows_dll::{dll, Sig};
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "GetCurrentProcessId"]
+    fn get_current_process_id() -> u32;
+    #[link_name = "GetModuleHandleW"]
+    fn get_module_handle(module_name: *const u16) -> isize;
+    #[link_name = "GetProcAddress"]
+    fn get_proc_address(module: isize, proc_name: *const u8) -> *const c_void;
+    #[link_name = "ThisFunctionDoesNotExist"]
+    fn does_not_exist();
+}
+
+#[test]
+fn function_pointer() {
+    unsafe {
+        let ptr: Option<Sig<get_current_process_id>> = get_current_process_id::ptr();
+        assert_eq!(ptr.unwrap()(), std::process::id());
+        assert!(does_not_exist::ptr().is_none());
+    }
+}
+
+#[test]
+fn function_address() {
+    unsafe {
+        let kernel32: Vec<u16> = "kernel32.dll\0".encode_utf16().collect();
+        let expected = get_proc_address(
+            get_module_handle(kernel32.as_ptr()),
+            b"GetCurrentProcessId\0".as_ptr(),
+        );
+
+        assert_eq!(get_current_process_id::address(), Some(expected));
+        assert_eq!(does_not_exist::address(), None);
+    }
+}
@@ -0,0 +1,70 @@
+use windows_dll::dll;
+
+#[dll(kernel32, raw_dylib = true)]
+extern "system" {
+    #[link_name = "GetCurrentProcessId"]
+    fn current_process_id() -> u32;
+    #[link_name = "MulDiv"]
+    #[fallible]
+    fn mul_div(number: i32, numerator: i32, denominator: i32) -> i32;
+}
+
+#[dll("kernel32.dll", type_name = Kernel32Lazy, raw_dylib = false)]
+extern "system" {
+    #[link_name = "GetCurrentProcessId"]
+    fn lazy_current_process_id() -> u32;
+}
+
+// Overridden in `raw_dylib_override`, separate from `mul_div` since tests run in parallel
+#[dll(type_name = kernel32)]
+extern "system" {
+    #[link_name = "MulDiv"]
+    #[fallible]
+    fn overridden_mul_div(number: i32, numerator: i32, denominator: i32) -> i32;
+}
+
+unsafe extern "system" fn fake_mul_div(_: i32, _: i32, _: i32) -> i32 {
+    -1
+}
+
+#[test]
+fn raw_dylib_call() {
+    unsafe {
+        assert_eq!(current_process_id(), std::process::id());
+        assert_eq!(mul_div(6, 4, 3).unwrap(), 8);
+    }
+}
+
+#[test]
+fn raw_dylib_always_exists() {
+    unsafe {
+        assert!(kernel32::exists());
+        assert!(current_process_id::exists());
+        assert!(current_process_id::address().is_some());
+        // The dll is loaded with the executable, and stays loaded
+        assert!(!kernel32::free());
+        assert_eq!(current_process_id(), std::process::id());
+    }
+}
+
+#[test]
+fn raw_dylib_override() {
+    unsafe {
+        overridden_mul_div::override_with(fake_mul_div);
+        assert_eq!(overridden_mul_div(6, 4, 3).unwrap(), -1);
+        assert_eq!(
+            overridden_mul_div::address(),
+            Some(fake_mul_div as *const _)
+        );
+
+        overridden_mul_div::restore();
+        assert_eq!(overridden_mul_div(6, 4, 3).unwrap(), 8);
+    }
+}
+
+#[test]
+fn lazy_and_raw_dylib_agree() {
+    unsafe {
+        assert_eq!(lazy_current_process_id(), current_process_id());
+    }
+}
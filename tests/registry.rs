@@ -0,0 +1,47 @@
+#![cfg(feature = "registry")]
+
+use windows_dll::{dll, registered_dlls, LoadFlags, Proc, RegisteredDll};
+
+#[dll(user32, type_name = User32)]
+extern "system" {
+    #[allow(non_snake_case)]
+    fn GetDesktopWindow() -> isize;
+}
+
+#[dll(type_name = User32)]
+extern "system" {
+    #[link_ordinal = 2]
+    fn user32_ordinal();
+}
+
+#[dll(bcrypt, LoadFlags::SEARCH_SYSTEM32)]
+extern "system" {
+    #[allow(non_snake_case)]
+    fn BCryptGenRandom();
+}
+
+fn registered(lib: &str) -> RegisteredDll {
+    registered_dlls()
+        .into_iter()
+        .find(|dll| dll.lib == lib)
+        .unwrap_or_else(|| panic!("{} is not registered", lib))
+}
+
+#[test]
+fn registered_without_loading() {
+    let user32 = registered("user32");
+    assert_eq!(user32.flags, LoadFlags::empty());
+    assert_eq!(user32.procs.len(), 2);
+    assert!(user32
+        .procs
+        .iter()
+        .any(|proc| matches!(proc, Proc::Name("GetDesktopWindow"))));
+    assert!(user32
+        .procs
+        .iter()
+        .any(|proc| matches!(proc, Proc::Ordinal(2))));
+
+    let bcrypt = registered("bcrypt");
+    assert_eq!(bcrypt.flags, LoadFlags::SEARCH_SYSTEM32);
+    assert!(matches!(bcrypt.procs[..], [Proc::Name("BCryptGenRandom")]));
+}
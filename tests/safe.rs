@@ -0,0 +1,27 @@
+use std::error::Error;
+use windows_dll::dll;
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "GetCurrentProcessId"]
+    #[safe]
+    fn get_current_process_id() -> u32;
+    #[link_name = "GetTickCount64"]
+    #[safe(strict)]
+    #[fallible]
+    fn get_tick_count() -> u64;
+    #[link_name = "GetSystemTimeAsFileTime"]
+    #[safe]
+    fn get_system_time(file_time: &mut [u32; 2]);
+}
+
+#[test]
+fn call_safe_functions() -> Result<(), Box<dyn Error>> {
+    assert_eq!(get_current_process_id(), std::process::id());
+    assert!(get_tick_count()? > 0);
+
+    let mut file_time = [0; 2];
+    get_system_time(&mut file_time);
+    assert_ne!(file_time, [0; 2]);
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use std::error::Error;
+use windows_dll::dll;
+
+#[dll(ntdll)]
+extern "system" {
+    #[allow(non_upper_case_globals)]
+    static NlsAnsiCodePage: u16;
+    #[link_name = "NlsMbCodePageTag"]
+    #[fallible]
+    static MB_CODE_PAGE_TAG: u8;
+    static NotAnExportedStatic: u32;
+}
+
+#[dll(msvcrt)]
+extern "C" {
+    #[allow(non_upper_case_globals)]
+    static mut _environ: *mut *mut u8;
+}
+
+#[test]
+fn read_static() -> Result<(), Box<dyn Error>> {
+    unsafe {
+        assert_ne!(*NlsAnsiCodePage(), 0);
+        assert!(*MB_CODE_PAGE_TAG()? <= 1);
+    }
+    Ok(())
+}
+
+#[test]
+fn mutable_static() {
+    unsafe {
+        let environ = _environ();
+        assert!(!environ.is_null());
+    }
+}
+
+#[test]
+fn static_exists() {
+    unsafe {
+        assert!(NlsAnsiCodePage::exists());
+        assert!(!NotAnExportedStatic::exists());
+    }
+}
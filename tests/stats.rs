@@ -0,0 +1,84 @@
+use windows_dll::{dll, stats, DllStats, Proc};
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "MulDiv"]
+    fn mul_div(number: i32, numerator: i32, denominator: i32) -> i32;
+    #[link_name = "ThisFunctionDoesNotExist"]
+    #[fallible]
+    fn does_not_exist();
+}
+
+#[dll("never_used")]
+extern "system" {
+    #[fallible]
+    fn never_called();
+}
+
+#[dll("this_dll_does_not_exist")]
+extern "system" {
+    #[fallible]
+    fn missing_dll_function();
+}
+
+fn dll_stats(lib: &str) -> DllStats {
+    stats()
+        .into_iter()
+        .find(|dll| dll.lib == lib)
+        .unwrap_or_else(|| panic!("no stats for {}", lib))
+}
+
+#[test]
+fn count_loads_and_calls() {
+    unsafe {
+        for _ in 0..3 {
+            assert_eq!(mul_div(6, 4, 3), 8);
+        }
+        assert!(does_not_exist().is_err());
+        assert!(does_not_exist().is_err());
+        assert!(missing_dll_function().is_err());
+        assert!(missing_dll_function().is_err());
+    }
+
+    let kernel32 = dll_stats("kernel32");
+    assert_eq!(kernel32.load_attempts, 1);
+    assert_eq!(kernel32.load_failures, 0);
+
+    let mul_div = kernel32
+        .procs
+        .iter()
+        .find(|proc| matches!(proc.proc, Proc::Name("MulDiv")))
+        .unwrap();
+    assert_eq!(mul_div.cache_index, 0);
+    assert_eq!(mul_div.resolve_attempts, 1);
+    assert_eq!(mul_div.resolve_failures, 0);
+    #[cfg(feature = "call-stats")]
+    assert_eq!(mul_div.calls, 3);
+
+    let does_not_exist = kernel32
+        .procs
+        .iter()
+        .find(|proc| proc.cache_index == 1)
+        .unwrap();
+    assert_eq!(does_not_exist.resolve_attempts, 2);
+    assert_eq!(does_not_exist.resolve_failures, 2);
+
+    let missing_dll = dll_stats("this_dll_does_not_exist");
+    assert_eq!(missing_dll.load_attempts, 2);
+    assert_eq!(missing_dll.load_failures, 2);
+    assert!(missing_dll.procs.is_empty());
+}
+
+#[cfg(feature = "registry")]
+#[test]
+fn unused_dlls() {
+    let never_used = dll_stats("never_used");
+    assert_eq!(never_used.load_attempts, 0);
+    assert!(never_used.procs.is_empty());
+}
+
+#[cfg(not(feature = "registry"))]
+#[test]
+fn unused_dlls() {
+    assert!(stats().iter().all(|dll| dll.lib != "never_used"));
+}
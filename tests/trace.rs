@@ -0,0 +1,69 @@
+#![cfg(feature = "trace")]
+
+use std::sync::Mutex;
+use windows_dll::{
+    dll,
+    trace::{set_call_hook, Call, CallHook, CallReturn},
+};
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "MulDiv"]
+    fn mul_div(number: i32, numerator: i32, denominator: i32) -> i32;
+    #[link_name = "SetLastError"]
+    fn set_last_error(error: u32);
+    #[link_name = "GetModuleHandleW"]
+    #[fallible]
+    fn get_module_handle(#[wide] module_name: &str) -> isize;
+}
+
+// Passed as the `DWORD` of `SetLastError`, without a `Debug` impl
+#[repr(transparent)]
+struct NotDebug(u32);
+
+#[dll(type_name = kernel32)]
+extern "system" {
+    #[link_name = "SetLastError"]
+    fn set_last_error_opaque(error: NotDebug);
+}
+
+static CALLS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct RecordCalls;
+impl CallHook for RecordCalls {
+    fn before(&self, call: &Call<'_>) {
+        let call = format!("{}!{}{:?}", call.lib, call.proc, call.args);
+        CALLS.lock().unwrap().push(call);
+    }
+    fn after(&self, call: &CallReturn<'_>) {
+        let call = format!("{}!{} -> {:?}", call.lib, call.proc, call.value);
+        CALLS.lock().unwrap().push(call);
+    }
+}
+
+#[test]
+fn call_hook() {
+    set_call_hook(Box::new(RecordCalls)).unwrap();
+    assert!(set_call_hook(Box::new(RecordCalls)).is_err());
+
+    unsafe {
+        mul_div(6, 4, 3);
+        set_last_error(0);
+        get_module_handle("not_loaded.dll").unwrap();
+        set_last_error_opaque(NotDebug(0));
+    }
+
+    assert_eq!(
+        *CALLS.lock().unwrap(),
+        [
+            "kernel32!MulDiv(number: 6, numerator: 4, denominator: 3)",
+            "kernel32!MulDiv -> 8",
+            "kernel32!SetLastError(error: 0)",
+            "kernel32!SetLastError -> ()",
+            "kernel32!GetModuleHandleW(module_name: \"not_loaded.dll\")",
+            "kernel32!GetModuleHandleW -> 0",
+            "kernel32!SetLastError(error: _)",
+            "kernel32!SetLastError -> ()",
+        ]
+    );
+}
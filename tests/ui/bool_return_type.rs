@@ -0,0 +1,9 @@
+use windows_dll::dll;
+
+#[dll(user32)]
+extern "system" {
+    #[bool]
+    fn IsWindowVisible(window: isize) -> i32;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll(user32, name = "user32.dll")]
+extern "system" {
+    fn GetDesktopWindow();
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use windows_dll::dll;
+
+#[dll(user32, type_name = User32)]
+extern "system" {
+    fn GetDesktopWindow();
+}
+
+#[dll(type_name = User32, flags = windows_dll::flags::LOAD_LIBRARY_SEARCH_SYSTEM32)]
+extern "system" {
+    fn GetShellWindow();
+}
+
+fn main() {}
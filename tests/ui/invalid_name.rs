@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll(1)]
+extern "system" {
+    fn GetDesktopWindow();
+}
+
+fn main() {}
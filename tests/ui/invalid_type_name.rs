@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll("C:\\Windows\\System32\\---.dll")]
+extern "system" {
+    fn GetDesktopWindow();
+}
+
+fn main() {}
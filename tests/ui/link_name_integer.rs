@@ -0,0 +1,9 @@
+use windows_dll::dll;
+
+#[dll(user32)]
+extern "system" {
+    #[link_name = 1]
+    fn get_desktop_window() -> isize;
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use windows_dll::dll;
+
+#[dll(uxtheme)]
+extern "system" {
+    #[link_ordinal = "133"]
+    fn allow_dark_mode_for_window(hwnd: isize, allow: i32) -> i32;
+}
+
+fn main() {}
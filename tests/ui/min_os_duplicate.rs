@@ -0,0 +1,9 @@
+use windows_dll::dll;
+
+#[dll(kernel32)]
+extern "system" {
+    #[min_os(build = 17763, build = 22000)]
+    fn GetCurrentProcessId() -> u32;
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use windows_dll::dll;
+
+#[dll(user32)]
+mod user32;
+
+fn main() {}
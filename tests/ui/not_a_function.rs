@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll(user32)]
+extern "system" {
+    type HWND;
+}
+
+fn main() {}
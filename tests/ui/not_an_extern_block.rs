@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll(user32)]
+fn get_desktop_window() -> isize {
+    0
+}
+
+fn main() {}
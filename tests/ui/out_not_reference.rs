@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll(kernel32)]
+extern "system" {
+    fn GetSystemTimeAsFileTime(#[out] file_time: u64);
+}
+
+fn main() {}
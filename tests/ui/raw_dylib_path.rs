@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll("C:\\Program Files\\Vendor\\vendor.dll", raw_dylib = true)]
+extern "system" {
+    fn vendor_init();
+}
+
+fn main() {}
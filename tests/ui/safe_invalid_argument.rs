@@ -0,0 +1,9 @@
+use windows_dll::dll;
+
+#[dll(kernel32)]
+extern "system" {
+    #[safe(always)]
+    fn GetCurrentProcessId() -> u32;
+}
+
+fn main() {}
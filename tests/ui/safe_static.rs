@@ -0,0 +1,9 @@
+use windows_dll::dll;
+
+#[dll(ntdll)]
+extern "system" {
+    #[safe]
+    static NlsAnsiCodePage: u16;
+}
+
+fn main() {}
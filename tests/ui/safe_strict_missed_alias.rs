@@ -0,0 +1,22 @@
+use windows_dll::dll;
+
+// Aliases are recognized by name, so an alias the macro doesn't know is accepted
+type MyPtr = *mut u8;
+
+// And a type named like a Win32 alias is rejected, even when it isn't a pointer
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+struct HANDLE(u32);
+
+#[dll(kernel32)]
+extern "system" {
+    #[link_name = "GetCurrentThreadId"]
+    #[safe(strict)]
+    fn missed_alias(ptr: MyPtr) -> u32;
+    #[link_name = "GetCurrentProcessId"]
+    #[safe(strict)]
+    fn not_a_pointer(handle: HANDLE) -> u32;
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use windows_dll::dll;
+
+#[dll(kernel32)]
+extern "system" {
+    #[safe(strict)]
+    fn GetSystemTimeAsFileTime(file_time: Option<&mut *mut u64>);
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use windows_dll::dll;
+
+type HWND = isize;
+
+#[dll(user32)]
+extern "system" {
+    #[safe(strict)]
+    fn GetDpiForWindow(hwnd: HWND) -> u32;
+}
+
+fn main() {}
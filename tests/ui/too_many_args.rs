@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll(user32, windows_dll::flags::LOAD_LIBRARY_SEARCH_SYSTEM32, windows_dll::flags::LOAD_LIBRARY_SEARCH_SYSTEM32)]
+extern "system" {
+    fn GetDesktopWindow();
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll(user32, type_name = self::User32)]
+extern "system" {
+    fn GetDesktopWindow();
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll(user32, lazy = true)]
+extern "system" {
+    fn GetDesktopWindow();
+}
+
+fn main() {}
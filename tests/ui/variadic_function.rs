@@ -0,0 +1,8 @@
+use windows_dll::dll;
+
+#[dll(user32)]
+extern "system" {
+    fn wsprintfW(buffer: *mut u16, format: *const u16, ...) -> i32;
+}
+
+fn main() {}
@@ -0,0 +1,35 @@
+use std::error::Error;
+use windows_dll::dll;
+
+#[dll(user32)]
+extern "C" {
+    #[allow(non_snake_case)]
+    fn wsprintfW(buffer: *mut u16, format: *const u16, ...) -> i32;
+}
+
+#[dll(msvcrt)]
+extern "C" {
+    #[fallible]
+    fn sprintf(buffer: *mut u8, format: *const u8, ...) -> i32;
+}
+
+#[test]
+fn call_variadic() {
+    let format: Vec<u16> = "%d-%s\0".encode_utf16().collect();
+    let arg: Vec<u16> = "dll\0".encode_utf16().collect();
+    let mut buffer = [0u16; 32];
+
+    let len = unsafe { wsprintfW()(buffer.as_mut_ptr(), format.as_ptr(), 42i32, arg.as_ptr()) };
+
+    assert_eq!(String::from_utf16_lossy(&buffer[..len as usize]), "42-dll");
+}
+
+#[test]
+fn call_fallible_variadic() -> Result<(), Box<dyn Error>> {
+    let mut buffer = [0u8; 32];
+
+    let len = unsafe { sprintf()?(buffer.as_mut_ptr(), b"%u%c\0".as_ptr(), 7u32, b'!' as i32) };
+
+    assert_eq!(&buffer[..len as usize], b"7!");
+    Ok(())
+}